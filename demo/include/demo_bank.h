@@ -0,0 +1,69 @@
+#ifndef DEMO_BANK_H
+#define DEMO_BANK_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit by hand. */
+
+#include <stdint.h>
+#include <stddef.h>
+
+// Returned by every call that can fail
+typedef enum BankStatus {
+  BANK_STATUS_OK = 0,
+  // A null pointer, or a string that isn't UTF-8
+  BANK_STATUS_INVALID_ARGUMENT = 1,
+  BANK_STATUS_ACCOUNT_NOT_FOUND = 2,
+  // Refused by the ledger, e.g. insufficient funds
+  BANK_STATUS_REJECTED = 3,
+  BANK_STATUS_READ_ONLY = 4,
+  BANK_STATUS_UNAVAILABLE = 5,
+  // `bank_poll_event` found nothing waiting
+  BANK_STATUS_NO_EVENT = 6,
+  BANK_STATUS_FAILED = 7,
+} BankStatus;
+
+// Opaque to C
+typedef struct BankService BankService;
+
+// Start a service with `count` accounts: `accounts[i]` opens with
+// `balances[i]`. Returns null if an argument is invalid or the service's
+// runtime can't start.
+//
+// # Safety
+// `accounts` and `balances` must each point to `count` valid elements
+BankService *bank_service_new(const char *const *accounts, const int32_t *balances, size_t count);
+
+// Stop the service, which checks its books first. Null is ignored.
+//
+// # Safety
+// `service` must come from `bank_service_new` and not be used afterwards
+void bank_service_free(BankService *service);
+
+// On success the new balance goes in `balance`, if that isn't null
+//
+// # Safety
+// `service` must be live, `account` a valid C string, `balance` null or
+// writable
+BankStatus bank_deposit(BankService *service, const char *account, int32_t amount, int32_t *balance);
+
+// # Safety
+// As for `bank_deposit`
+BankStatus bank_get_balance(BankService *service, const char *account, int32_t *balance);
+
+// Take the next event without waiting, as a line of the form
+// "<seq>\t<topic>\t<event>". Answers NoEvent when nothing is waiting. The
+// string is the caller's, to free with `bank_string_free`.
+//
+// # Safety
+// `service` must be live and `event` writable
+BankStatus bank_poll_event(BankService *service, char **event);
+
+// Null is ignored
+//
+// # Safety
+// `string` must come from this library and not be used afterwards
+void bank_string_free(char *string);
+
+// A static description of a status; never freed
+const char *bank_status_message(BankStatus status);
+
+#endif  /* DEMO_BANK_H */
@@ -0,0 +1,216 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::ops::Deref;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::intern::intern;
+use crate::ledger::{self, CASH_ACCOUNT, CLEARING_ACCOUNT, FX_ACCOUNT};
+
+// The ledger's own contra accounts, which every ID type has to be able to
+// name without clashing with a customer's
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Internal {
+    Cash,
+    Fx,
+    Clearing,
+}
+
+impl Internal {
+    pub const ALL: [Internal; 3] = [Internal::Cash, Internal::Fx, Internal::Clearing];
+}
+
+// What the ledger keys accounts by. Display is how an account appears in
+// memos, the hash chain and anything else written out.
+pub trait AccountId: Clone + Eq + Ord + Hash + fmt::Display + fmt::Debug + Send + Sync + 'static {
+    fn internal(account: Internal) -> Self;
+
+    fn is_internal(&self) -> bool {
+        Internal::ALL.iter().any(|&account| *self == Self::internal(account))
+    }
+}
+
+// Anything an account can be named by in a ledger call: the ID itself, or
+// for named accounts a plain string. Lookups go through here so a `&str`
+// never has to become an ID just to find one.
+pub trait AccountKey<A: AccountId>: fmt::Display {
+    fn to_id(&self) -> A;
+    fn is(&self, id: &A) -> bool;
+    // Names one of the ledger's internal accounts
+    fn is_reserved(&self) -> bool;
+    fn find<'m, V>(&self, map: &'m HashMap<A, V>) -> Option<&'m V>;
+    fn find_mut<'m, V>(&self, map: &'m mut HashMap<A, V>) -> Option<&'m mut V>;
+}
+
+impl<A: AccountId> AccountKey<A> for A {
+    fn to_id(&self) -> A {
+        self.clone()
+    }
+
+    fn is(&self, id: &A) -> bool {
+        self == id
+    }
+
+    fn is_reserved(&self) -> bool {
+        AccountId::is_internal(self)
+    }
+
+    fn find<'m, V>(&self, map: &'m HashMap<A, V>) -> Option<&'m V> {
+        map.get(self)
+    }
+
+    fn find_mut<'m, V>(&self, map: &'m mut HashMap<A, V>) -> Option<&'m mut V> {
+        map.get_mut(self)
+    }
+}
+
+// Account name, interned: every copy of it shares one allocation, so keying
+// maps and postings by it costs a reference count rather than a string
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AccountName(Arc<str>);
+
+impl AccountId for AccountName {
+    fn internal(account: Internal) -> Self {
+        AccountName::from(match account {
+            Internal::Cash => CASH_ACCOUNT,
+            Internal::Fx => FX_ACCOUNT,
+            Internal::Clearing => CLEARING_ACCOUNT,
+        })
+    }
+
+    // Without interning the reserved names
+    fn is_internal(&self) -> bool {
+        ledger::is_internal(self)
+    }
+}
+
+impl From<&str> for AccountName {
+    fn from(name: &str) -> Self {
+        AccountName(intern(name))
+    }
+}
+
+impl Deref for AccountName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for AccountName {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for AccountName {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for AccountName {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl fmt::Display for AccountName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl fmt::Debug for AccountName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl AccountKey<AccountName> for str {
+    fn to_id(&self) -> AccountName {
+        AccountName::from(self)
+    }
+
+    fn is(&self, id: &AccountName) -> bool {
+        self == &**id
+    }
+
+    fn is_reserved(&self) -> bool {
+        ledger::is_internal(self)
+    }
+
+    fn find<'m, V>(&self, map: &'m HashMap<AccountName, V>) -> Option<&'m V> {
+        map.get(self)
+    }
+
+    fn find_mut<'m, V>(&self, map: &'m mut HashMap<AccountName, V>) -> Option<&'m mut V> {
+        map.get_mut(self)
+    }
+}
+
+impl AccountKey<AccountName> for String {
+    fn to_id(&self) -> AccountName {
+        AccountName::from(self.as_str())
+    }
+
+    fn is(&self, id: &AccountName) -> bool {
+        self == &**id
+    }
+
+    fn is_reserved(&self) -> bool {
+        ledger::is_internal(self)
+    }
+
+    fn find<'m, V>(&self, map: &'m HashMap<AccountName, V>) -> Option<&'m V> {
+        map.get(self.as_str())
+    }
+
+    fn find_mut<'m, V>(&self, map: &'m mut HashMap<AccountName, V>) -> Option<&'m mut V> {
+        map.get_mut(self.as_str())
+    }
+}
+
+// Account number. The top three numbers are the internal accounts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AccountNumber(pub u64);
+
+impl AccountId for AccountNumber {
+    fn internal(account: Internal) -> Self {
+        AccountNumber(match account {
+            Internal::Cash => u64::MAX,
+            Internal::Fx => u64::MAX - 1,
+            Internal::Clearing => u64::MAX - 2,
+        })
+    }
+}
+
+impl fmt::Display for AccountNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+// Account UUID. The internal accounts take UUIDs 1 to 3, which no generator
+// hands out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AccountUuid(pub Uuid);
+
+impl AccountId for AccountUuid {
+    fn internal(account: Internal) -> Self {
+        AccountUuid(Uuid::from_u128(match account {
+            Internal::Cash => 1,
+            Internal::Fx => 2,
+            Internal::Clearing => 3,
+        }))
+    }
+}
+
+impl fmt::Display for AccountUuid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
@@ -0,0 +1,196 @@
+use std::cell::Cell;
+use std::future::{poll_fn, Future};
+use std::pin::pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+// Labels that can be told apart; allocations under any more go to slot 0
+// with those outside every profiled task
+const SLOTS: usize = 64;
+
+// Label of each slot after the first
+static LABELS: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+// Allocation use of each scenario run so far, in order
+static SCENARIOS: Mutex<Vec<(&'static str, Usage)>> = Mutex::new(Vec::new());
+
+struct Counters {
+    allocations: AtomicU64,
+    bytes: AtomicU64,
+    frees: AtomicU64,
+    freed: AtomicU64,
+}
+
+static COUNTERS: [Counters; SLOTS] = [const {
+    Counters {
+        allocations: AtomicU64::new(0),
+        bytes: AtomicU64::new(0),
+        frees: AtomicU64::new(0),
+        freed: AtomicU64::new(0),
+    }
+}; SLOTS];
+
+thread_local! {
+    // Slot of the profiled task being polled on this thread. Read by the
+    // allocator, so it must never allocate itself.
+    static CURRENT: Cell<usize> = const { Cell::new(0) };
+}
+
+// What was allocated and freed while a label was being polled. Memory
+// freed by another task counts against that task, so `live` is only
+// meaningful over everything.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub allocations: u64,
+    pub bytes: u64,
+    pub frees: u64,
+    pub freed: u64,
+}
+
+impl Usage {
+    pub fn live(&self) -> i64 {
+        self.bytes as i64 - self.freed as i64
+    }
+
+    fn since(&self, earlier: Usage) -> Usage {
+        Usage {
+            allocations: self.allocations - earlier.allocations,
+            bytes: self.bytes - earlier.bytes,
+            frees: self.frees - earlier.frees,
+            freed: self.freed - earlier.freed,
+        }
+    }
+
+    fn read(counters: &Counters) -> Usage {
+        Usage {
+            allocations: counters.allocations.load(Ordering::Relaxed),
+            bytes: counters.bytes.load(Ordering::Relaxed),
+            frees: counters.frees.load(Ordering::Relaxed),
+            freed: counters.freed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// Counting only happens in builds with the `alloc-profiling` feature,
+// which installs the counting allocator
+pub fn enabled() -> bool {
+    cfg!(feature = "alloc-profiling")
+}
+
+fn slot(label: &'static str) -> usize {
+    let mut labels = LABELS.lock().unwrap();
+    match labels.iter().position(|&known| known == label) {
+        Some(index) => index + 1,
+        None if labels.len() + 1 < SLOTS => {
+            labels.push(label);
+            labels.len()
+        },
+        None => 0,
+    }
+}
+
+// Puts the previous label back even if the poll panics
+struct Restore(usize);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.set(self.0));
+    }
+}
+
+// Run `future` with its allocations counted under `label`. A profiled
+// future inside another counts under its own label; tasks it spawns count
+// under whatever they're wrapped in, if anything.
+pub async fn profiled<F: Future>(label: &'static str, future: F) -> F::Output {
+    let slot = slot(label);
+    let mut future = pin!(future);
+    poll_fn(|cx| {
+        let _restore = Restore(CURRENT.with(|current| current.replace(slot)));
+        future.as_mut().poll(cx)
+    })
+    .await
+}
+
+// Allocations by every task since the process started
+pub fn total() -> Usage {
+    COUNTERS.iter().map(Usage::read).fold(Usage::default(), |total, usage| Usage {
+        allocations: total.allocations + usage.allocations,
+        bytes: total.bytes + usage.bytes,
+        frees: total.frees + usage.frees,
+        freed: total.freed + usage.freed,
+    })
+}
+
+// Run a scenario under its name and record what the whole process
+// allocated meanwhile, background tasks included. Scenarios have to run
+// one at a time for this to mean anything.
+pub async fn measure<F: Future>(scenario: &'static str, future: F) -> F::Output {
+    let before = total();
+    let output = profiled(scenario, future).await;
+    let usage = total().since(before);
+    SCENARIOS.lock().unwrap().push((scenario, usage));
+    output
+}
+
+#[cfg(feature = "alloc-profiling")]
+mod counting {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::Ordering;
+
+    use super::{Counters, COUNTERS, CURRENT};
+
+    fn counters() -> &'static Counters {
+        // The thread may be past its thread-locals while tearing down
+        &COUNTERS[CURRENT.try_with(|current| current.get()).unwrap_or(0)]
+    }
+
+    // The system allocator, counting into the slot of whatever profiled
+    // task the calling thread is polling
+    pub struct CountingAllocator;
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc(layout);
+            if !ptr.is_null() {
+                let counters = counters();
+                counters.allocations.fetch_add(1, Ordering::Relaxed);
+                counters.bytes.fetch_add(layout.size() as u64, Ordering::Relaxed);
+            }
+            ptr
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout);
+            let counters = counters();
+            counters.frees.fetch_add(1, Ordering::Relaxed);
+            counters.freed.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(feature = "alloc-profiling")]
+pub use counting::CountingAllocator;
+
+fn print_row(name: &str, usage: Usage) {
+    println!("{:<20} {:>12} {:>14} {:>12} {:>14}", name, usage.allocations, usage.bytes, usage.frees, usage.live());
+}
+
+// Print allocations per scenario and per profiled task
+pub fn print_report() {
+    if !enabled() {
+        return;
+    }
+
+    println!("\n=== Allocations ===");
+    println!("{:<20} {:>12} {:>14} {:>12} {:>14}", "scenario", "allocs", "bytes", "frees", "live bytes");
+    for &(scenario, usage) in SCENARIOS.lock().unwrap().iter() {
+        print_row(scenario, usage);
+    }
+
+    println!("\n{:<20} {:>12} {:>14} {:>12} {:>14}", "task", "allocs", "bytes", "frees", "live bytes");
+    let labels = LABELS.lock().unwrap().clone();
+    for (index, label) in labels.iter().enumerate() {
+        print_row(label, Usage::read(&COUNTERS[index + 1]));
+    }
+    print_row("(unlabelled)", Usage::read(&COUNTERS[0]));
+}
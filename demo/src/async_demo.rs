@@ -1,5 +1,8 @@
 use tokio::time::{sleep, Duration};
 
+use crate::config::Config;
+use crate::scenario::{scenario, Scenario};
+
 #[derive(Debug)]
 struct TaskResult {
     name: String,
@@ -21,8 +24,7 @@ async fn execute_task(name: &str, duration: u64) -> TaskResult {
     }
 }
 
-#[tokio::main]
-async fn main() {
+async fn run_async_tasks(_cfg: Config) {
     println!("Rust Demo Start\n");
     
     // Create multiple async tasks
@@ -35,6 +37,15 @@ async fn main() {
     println!("Starting task execution now...\n");
     
     // Wait for results
-    let results = tokio::join!(task1, task2);
-    println!("\nAll results: {:?}", results);
+    let (result1, result2) = tokio::join!(task1, task2);
+    println!("\nAll results:");
+    for task in [result1, result2] {
+        println!("  {} ({}ms): {}", task.name, task.duration, task.result);
+    }
+}
+
+pub fn scenarios() -> Vec<Box<dyn Scenario>> {
+    vec![
+        scenario("async-tasks", "Futures are lazy until awaited together with join!", run_async_tasks),
+    ]
 }
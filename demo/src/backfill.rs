@@ -0,0 +1,129 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tokio::time::{Duration, Instant};
+
+use crate::currency::Currency;
+use crate::ledger::Ledger;
+use crate::snapshot::{read_snapshot, write_snapshot, LedgerCopy, Record, SnapshotFormat};
+
+// What `demo backfill` does with a snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackfillMode {
+    // Synthesize the history, write it out, then verify what was written
+    Write,
+    // Show the entries that would be synthesized; write nothing
+    DryRun,
+    // Only check that a snapshot's journal adds up to its balances
+    Verify,
+}
+
+// Balances from a snapshot taken before the books kept a journal, as the
+// mutable bank's were: every balance, and no history behind it
+type Holdings = BTreeMap<String, BTreeMap<Currency, i32>>;
+
+fn read_balances(path: &Path, format: SnapshotFormat) -> Result<Holdings, String> {
+    let mut holdings = Holdings::new();
+    let mut entries = 0;
+    for record in read_snapshot(path, format).map_err(|e| format!("{}: {}", path.display(), e))? {
+        match record.map_err(|e| format!("{}: {}", path.display(), e))? {
+            Record::Balance { account, currency, balance } => {
+                holdings.entry(account).or_default().insert(currency.parse()?, balance);
+            },
+            Record::Entry(_) => entries += 1,
+        }
+    }
+    if entries > 0 {
+        return Err(format!("{} already has {} journal entries; nothing to backfill", path.display(), entries));
+    }
+    Ok(holdings)
+}
+
+// One opening entry per account and currency held, so replaying the journal
+// from the start gives exactly the snapshot's balances
+fn synthesize(holdings: &Holdings) -> Result<Ledger, String> {
+    let mut ledger = Ledger::new();
+    for (account, balances) in holdings {
+        let base = balances.get(&Currency::BASE).copied().unwrap_or(0);
+        ledger.open_account(account, base).map_err(|e| format!("{}: {}", account, e))?;
+        for (&currency, &balance) in balances.iter().filter(|(currency, _)| **currency != Currency::BASE) {
+            ledger.add_currency(account, currency).map_err(|e| format!("{}: {}", account, e))?;
+            if balance != 0 {
+                ledger.open_balance_in(account, balance, currency).map_err(|e| format!("{}: {}", account, e))?;
+            }
+        }
+    }
+    Ok(ledger)
+}
+
+// Replay a snapshot's journal and compare it with its balance records.
+// Returns one line per disagreement.
+fn verify(path: &Path, format: SnapshotFormat) -> Result<Vec<String>, String> {
+    let mut recorded: HashMap<(String, String), i32> = HashMap::new();
+    let mut replayed: HashMap<(String, String), i32> = HashMap::new();
+    for record in read_snapshot(path, format).map_err(|e| format!("{}: {}", path.display(), e))? {
+        match record.map_err(|e| format!("{}: {}", path.display(), e))? {
+            Record::Balance { account, currency, balance } => {
+                recorded.insert((account, currency), balance);
+            },
+            Record::Entry(entry) => {
+                for posting in entry.postings {
+                    let amount = if posting.debit { -posting.amount } else { posting.amount };
+                    *replayed.entry((posting.account, posting.currency)).or_insert(0) += amount;
+                }
+            },
+        }
+    }
+
+    // Internal accounts have no balance records, so only customers' count
+    let mut mismatches: Vec<String> = recorded
+        .iter()
+        .filter_map(|((account, currency), &balance)| {
+            let journal = replayed.get(&(account.clone(), currency.clone())).copied().unwrap_or(0);
+            (journal != balance).then(|| format!("{} has {} {} but the journal says {}", account, balance, currency, journal))
+        })
+        .collect();
+    mismatches.sort();
+    Ok(mismatches)
+}
+
+// `demo backfill`: give a balances-only snapshot the opening entries that
+// explain it, written to `output` in the same format
+pub fn run_backfill(input: &Path, output: &Path, format: SnapshotFormat, mode: BackfillMode) -> Result<(), String> {
+    if mode == BackfillMode::Verify {
+        return report_verification(input, format);
+    }
+
+    let holdings = read_balances(input, format)?;
+    let ledger = synthesize(&holdings)?;
+    let violations = ledger.violations();
+    if let Some(violation) = violations.first() {
+        return Err(format!("Synthesized history breaks the books: {}", violation));
+    }
+    println!("{} accounts, {} opening entries to synthesize", holdings.len(), ledger.entries().len());
+
+    if mode == BackfillMode::DryRun {
+        for entry in ledger.entries() {
+            println!("  #{:<4} {}", entry.seq, entry.memo);
+        }
+        println!("Dry run: nothing written");
+        return Ok(());
+    }
+
+    let report = write_snapshot(&LedgerCopy::of(&ledger), output, format, Instant::now(), Duration::ZERO)
+        .map_err(|e| format!("{}: {}", output.display(), e))?;
+    println!("Wrote {} entries, {} bytes to {}", report.entries, report.bytes, output.display());
+    report_verification(output, format)
+}
+
+fn report_verification(path: &Path, format: SnapshotFormat) -> Result<(), String> {
+    let mismatches = verify(path, format)?;
+    for mismatch in &mismatches {
+        println!("  {}", mismatch);
+    }
+    if mismatches.is_empty() {
+        println!("Verified: {}'s journal adds up to its balances", path.display());
+        Ok(())
+    } else {
+        Err(format!("{} balances disagree with the journal", mismatches.len()))
+    }
+}
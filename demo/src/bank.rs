@@ -0,0 +1,1280 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, sleep_until, Duration, Instant};
+
+use crate::allocs;
+use crate::calibration;
+use crate::chain::Receipt;
+use crate::clock::Timestamp;
+use crate::config::ServiceMode;
+use crate::crash;
+use crate::currency::{Currency, Rates};
+use crate::fees::{FeeBreakdown, FeeSchedule};
+use crate::ids::Id;
+use crate::ledger::{Compaction, Hold, JournalEntry, Ledger, PayrollMode, PayrollReport, RetentionPolicy, Staged, Violation};
+use crate::metadata::{MergePolicy, MetadataStore, Stamp};
+use crate::metrics::{self, MeteredSender};
+use crate::oplog::{Oplog, Shipment};
+use crate::pubsub::{Envelope, Router};
+use crate::query::{Query, QueryRow};
+use crate::schedule::{SchedulePolicy, Scheduler};
+use crate::slowlog;
+use crate::snapshot::{write_snapshot, LedgerCopy, SnapshotFormat, SnapshotMode, SnapshotReport};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BankError {
+    // Refused by the ledger, e.g. unknown account or insufficient funds
+    Rejected(&'static str),
+    // The service is in read-only mode
+    ReadOnly,
+    // The client used up its allowance for the named window
+    QuotaExceeded(&'static str),
+    // The API key is unknown, revoked or not allowed to do this
+    Unauthorized(&'static str),
+    // The client cancelled the request before it finished
+    Cancelled,
+    // No manager to take the request, or it died before answering
+    ManagerUnavailable,
+    // Reading or writing persisted state failed
+    Storage(String),
+    // A wait ran out before its condition held
+    TimedOut,
+    // A batch's operations were posted in order until the ledger refused
+    // one; the first `committed` of them stay posted
+    BatchIncomplete { committed: usize, reason: &'static str },
+}
+
+impl fmt::Display for BankError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BankError::Rejected(reason) => write!(f, "{}", reason),
+            BankError::ReadOnly => write!(f, "Bank is in read-only mode"),
+            BankError::QuotaExceeded(window) => write!(f, "Quota exceeded for this {}", window),
+            BankError::Unauthorized(reason) => write!(f, "Unauthorized: {}", reason),
+            BankError::Cancelled => write!(f, "Request was cancelled"),
+            BankError::ManagerUnavailable => write!(f, "Bank manager unavailable"),
+            BankError::Storage(e) => write!(f, "Storage error: {}", e),
+            BankError::TimedOut => write!(f, "Timed out"),
+            BankError::BatchIncomplete { committed, reason } => {
+                write!(f, "Batch stopped after {} operation(s): {}", committed, reason)
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum BankMessage {
+    Deposit { 
+        account: String, 
+        amount: i32, 
+        respond_to: oneshot::Sender<Result<i32, BankError>> 
+    },
+    Transfer {
+        from: String,
+        to: String,
+        amount: i32,
+        respond_to: oneshot::Sender<Result<i32, BankError>>
+    },
+    // Transfer answering with the fee charged as well as the balance
+    TransferItemized {
+        from: String,
+        to: String,
+        amount: i32,
+        respond_to: oneshot::Sender<Result<TransferReport, BankError>>
+    },
+    // Charge transfers by this schedule from now on, or stop charging;
+    // answers with the schedule it replaces
+    SetFeeSchedule {
+        schedule: Option<FeeSchedule>,
+        respond_to: oneshot::Sender<Result<Option<FeeSchedule>, BankError>>
+    },
+    // Deposit answered on a shared reply channel, matched by correlation ID
+    TaggedDeposit {
+        id: Id,
+        account: String,
+        amount: i32,
+        respond_to: MeteredSender<TaggedReply>
+    },
+    // Create an account with an opening balance; existing accounts are refused
+    OpenAccount {
+        account: String,
+        opening_balance: i32,
+        respond_to: oneshot::Sender<Result<(), BankError>>
+    },
+    // Soft-delete an empty account: it disappears from listings and refuses
+    // operations, but keeps its history and can be restored until purged
+    CloseAccount {
+        account: String,
+        respond_to: oneshot::Sender<Result<(), BankError>>
+    },
+    RestoreAccount {
+        account: String,
+        respond_to: oneshot::Sender<Result<(), BankError>>
+    },
+    // Permanently remove accounts closed for longer than `retention`
+    Purge {
+        retention: Duration,
+        respond_to: oneshot::Sender<Vec<String>>
+    },
+    // Make `account` a sub-account of `parent`; transfers between the two
+    // are then recorded as moves
+    SetParent {
+        account: String,
+        parent: String,
+        respond_to: oneshot::Sender<Result<(), BankError>>
+    },
+    // Let an account hold another currency
+    AddCurrency {
+        account: String,
+        currency: Currency,
+        respond_to: oneshot::Sender<Result<(), BankError>>
+    },
+    // Answers with the account's balance in `currency` afterwards
+    DepositIn {
+        account: String,
+        amount: i32,
+        currency: Currency,
+        respond_to: oneshot::Sender<Result<i32, BankError>>
+    },
+    // Move money in `currency`, which the payee must hold too unless
+    // `convert_to` names one of theirs to convert into. Answers with the
+    // amount credited to the payee.
+    TransferIn {
+        from: String,
+        to: String,
+        amount: i32,
+        currency: Currency,
+        convert_to: Option<Currency>,
+        respond_to: oneshot::Sender<Result<i32, BankError>>
+    },
+    // Every currency an account holds and its balance in each
+    Holdings {
+        account: String,
+        respond_to: oneshot::Sender<Result<BTreeMap<Currency, i32>, BankError>>
+    },
+    // Reserve funds for a payment that is captured or voided later; answers
+    // with the hold's ID. Uncaptured holds lapse after `ttl`.
+    Authorize {
+        from: String,
+        to: String,
+        amount: i32,
+        ttl: Duration,
+        respond_to: oneshot::Sender<Result<Id, BankError>>
+    },
+    // Post a held payment; answers with the payer's balance afterwards
+    Capture {
+        hold: Id,
+        respond_to: oneshot::Sender<Result<i32, BankError>>
+    },
+    Void {
+        hold: Id,
+        respond_to: oneshot::Sender<Result<(), BankError>>
+    },
+    // Release holds past their TTL; answers with their IDs
+    ExpireHolds {
+        respond_to: oneshot::Sender<Vec<Id>>
+    },
+    // Choose how concurrent writes to a metadata key are reconciled;
+    // answers with the key's previous policy, if it had one
+    DefineMetadataKey {
+        key: String,
+        policy: MergePolicy,
+        respond_to: oneshot::Sender<Option<MergePolicy>>
+    },
+    // Write a note on an account; answers with the key's values afterwards
+    SetMetadata {
+        account: String,
+        key: String,
+        value: String,
+        stamp: Stamp,
+        respond_to: oneshot::Sender<Result<Vec<String>, BankError>>
+    },
+    Metadata {
+        account: String,
+        respond_to: oneshot::Sender<Result<BTreeMap<String, Vec<String>>, BankError>>
+    },
+    // Open accounts, sorted by name
+    ListAccounts {
+        respond_to: oneshot::Sender<Vec<String>>
+    },
+    // Run several operations as one transaction, see `BatchOp`
+    Batch {
+        ops: Vec<BatchOp>,
+        respond_to: oneshot::Sender<Result<BatchReport, BankError>>
+    },
+    // Debit one account and credit several, see `PayrollMode`
+    Payroll {
+        from: String,
+        credits: Vec<(String, i32)>,
+        mode: PayrollMode,
+        respond_to: oneshot::Sender<Result<PayrollReport, BankError>>
+    },
+    Balance {
+        account: String,
+        respond_to: oneshot::Sender<Result<i32, BankError>>
+    },
+    // Balance as kept and as replayed from the journal, for reconciliation
+    AuditBalance {
+        account: String,
+        respond_to: oneshot::Sender<Result<BalanceAudit, BankError>>
+    },
+    // Answer with the balance once it's at least `at_least`, or TimedOut
+    // after `timeout`. The manager keeps the waiter and checks it whenever
+    // it publishes a change, so waiting costs no requests.
+    WaitForBalance {
+        account: String,
+        at_least: i32,
+        timeout: Duration,
+        respond_to: oneshot::Sender<Result<i32, BankError>>
+    },
+    // Balance minus funds on hold
+    AvailableBalance {
+        account: String,
+        respond_to: oneshot::Sender<Result<i32, BankError>>
+    },
+    TotalBalance {
+        respond_to: oneshot::Sender<i32>
+    },
+    // Every open account's balance at once
+    Balances {
+        respond_to: oneshot::Sender<HashMap<String, i32>>
+    },
+    Query {
+        query: Query,
+        respond_to: oneshot::Sender<Vec<QueryRow>>
+    },
+    // The most recent `last` journal entries, oldest first
+    Journal {
+        last: usize,
+        respond_to: oneshot::Sender<Vec<JournalEntry>>
+    },
+    // Hash-chain proof that a transaction is in the journal
+    Receipt {
+        transaction: Id,
+        respond_to: oneshot::Sender<Result<Receipt, BankError>>
+    },
+    // Jump the ledger's wall clock to simulate skew; answers with the
+    // hybrid clock's reading afterwards
+    SkewClock {
+        offset: chrono::Duration,
+        respond_to: oneshot::Sender<Timestamp>
+    },
+    // Verify the books now, publishing an alarm for every violation found
+    CheckInvariants {
+        respond_to: oneshot::Sender<Vec<Violation>>
+    },
+    // Write balances and journal to a file
+    Snapshot {
+        path: PathBuf,
+        mode: SnapshotMode,
+        format: SnapshotFormat,
+        respond_to: oneshot::Sender<Result<SnapshotReport, BankError>>
+    },
+    // Roll journal entries beyond the policy's limits into a summary
+    Compact {
+        policy: RetentionPolicy,
+        respond_to: oneshot::Sender<Compaction>
+    },
+    // Receive every future event whose topic matches the pattern
+    Subscribe {
+        pattern: String,
+        respond_to: oneshot::Sender<mpsc::Receiver<Envelope<BankEvent>>>
+    },
+    // Reconnect after a disconnect: replays matching events after `last_seen`,
+    // then continues with live events
+    Resubscribe {
+        pattern: String,
+        last_seen: u64,
+        respond_to: oneshot::Sender<Result<mpsc::Receiver<Envelope<BankEvent>>, BankError>>
+    },
+    // Stream committed journal entries after `after` to a follower: first
+    // those already in the journal, then each new one
+    ShipOplog {
+        after: u64,
+        respond_to: oneshot::Sender<Result<mpsc::Receiver<Shipment>, BankError>>
+    },
+    Statement {
+        request_id: Id,
+        account: String,
+        entries: usize,
+        progress: MeteredSender<Progress>,
+        respond_to: oneshot::Sender<Result<Statement, BankError>>
+    },
+    // Stop an in-flight long operation; answers whether it was still running
+    Cancel {
+        request_id: Id,
+        respond_to: oneshot::Sender<bool>
+    }
+}
+
+// One step of a batch. When a deposit or transfer fails the batch rolls back
+// to the innermost savepoint and carries on; with no savepoint to fall back
+// to, the whole batch is rejected and nothing is posted.
+#[derive(Debug, Clone)]
+pub enum BatchOp {
+    Deposit { account: String, amount: i32 },
+    Transfer { from: String, to: String, amount: i32 },
+    Savepoint(String),
+    RollbackTo(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BalanceAudit {
+    pub balance: i32,
+    pub journal: i32,
+    // Latest event published when the balances were read
+    pub seq: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct TransferReport {
+    pub transaction: Id,
+    // The payer's balance, fee included
+    pub balance: i32,
+    pub fee: FeeBreakdown,
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchReport {
+    // Operations posted to the ledger
+    pub committed: usize,
+    // What was rolled back, and why
+    pub rollbacks: Vec<String>,
+}
+
+// Published by the manager after every successful mutation
+#[derive(Debug, Clone)]
+pub enum BankEvent {
+    // `balance` is the account's balance in `currency` afterwards
+    Deposited { transaction: Id, account: String, currency: Currency, amount: i32, balance: i32 },
+    // `fee` is the fees account and what it was credited, if the payer was
+    // charged on top of `amount`
+    Transferred { transaction: Id, from: String, to: String, currency: Currency, amount: i32, fee: Option<(String, i32)> },
+    // A transfer converted on the way: `amount` in `currency` left the payer
+    // and `credited` in `to_currency` reached the payee
+    Exchanged {
+        transaction: Id,
+        from: String,
+        to: String,
+        currency: Currency,
+        amount: i32,
+        to_currency: Currency,
+        credited: i32,
+    },
+    Alarm { violation: Violation },
+    AccountClosed { account: String },
+    AccountRestored { account: String },
+    AccountPurged { account: String },
+    AccountLinked { account: String, parent: String },
+    HoldExpired { hold: Id, from: String, amount: i32 },
+}
+
+impl BankMessage {
+    // The request and its parameters, without its reply channel
+    pub fn describe(&self) -> String {
+        match self {
+            BankMessage::Deposit { account, amount, .. } => format!("Deposit {{ account: {:?}, amount: {} }}", account, amount),
+            BankMessage::Transfer { from, to, amount, .. } => {
+                format!("Transfer {{ from: {:?}, to: {:?}, amount: {} }}", from, to, amount)
+            },
+            BankMessage::TransferItemized { from, to, amount, .. } => {
+                format!("TransferItemized {{ from: {:?}, to: {:?}, amount: {} }}", from, to, amount)
+            },
+            BankMessage::SetFeeSchedule { schedule, .. } => format!("SetFeeSchedule {{ schedule: {:?} }}", schedule),
+            BankMessage::TaggedDeposit { id, account, amount, .. } => {
+                format!("TaggedDeposit {{ id: {}, account: {:?}, amount: {} }}", id, account, amount)
+            },
+            BankMessage::OpenAccount { account, opening_balance, .. } => {
+                format!("OpenAccount {{ account: {:?}, opening_balance: {} }}", account, opening_balance)
+            },
+            BankMessage::CloseAccount { account, .. } => format!("CloseAccount {{ account: {:?} }}", account),
+            BankMessage::RestoreAccount { account, .. } => format!("RestoreAccount {{ account: {:?} }}", account),
+            BankMessage::Purge { retention, .. } => format!("Purge {{ retention: {:?} }}", retention),
+            BankMessage::SetParent { account, parent, .. } => {
+                format!("SetParent {{ account: {:?}, parent: {:?} }}", account, parent)
+            },
+            BankMessage::AddCurrency { account, currency, .. } => {
+                format!("AddCurrency {{ account: {:?}, currency: {:?} }}", account, currency)
+            },
+            BankMessage::DepositIn { account, amount, currency, .. } => {
+                format!("DepositIn {{ account: {:?}, amount: {}, currency: {:?} }}", account, amount, currency)
+            },
+            BankMessage::TransferIn { from, to, amount, currency, convert_to, .. } => format!(
+                "TransferIn {{ from: {:?}, to: {:?}, amount: {}, currency: {:?}, convert_to: {:?} }}",
+                from, to, amount, currency, convert_to
+            ),
+            BankMessage::Holdings { account, .. } => format!("Holdings {{ account: {:?} }}", account),
+            BankMessage::Authorize { from, to, amount, ttl, .. } => {
+                format!("Authorize {{ from: {:?}, to: {:?}, amount: {}, ttl: {:?} }}", from, to, amount, ttl)
+            },
+            BankMessage::Capture { hold, .. } => format!("Capture {{ hold: {} }}", hold),
+            BankMessage::Void { hold, .. } => format!("Void {{ hold: {} }}", hold),
+            BankMessage::ExpireHolds { .. } => "ExpireHolds".to_string(),
+            BankMessage::DefineMetadataKey { key, policy, .. } => {
+                format!("DefineMetadataKey {{ key: {:?}, policy: {:?} }}", key, policy)
+            },
+            BankMessage::SetMetadata { account, key, value, stamp, .. } => format!(
+                "SetMetadata {{ account: {:?}, key: {:?}, value: {:?}, stamp: {:?} }}",
+                account, key, value, stamp
+            ),
+            BankMessage::Metadata { account, .. } => format!("Metadata {{ account: {:?} }}", account),
+            BankMessage::ListAccounts { .. } => "ListAccounts".to_string(),
+            BankMessage::Batch { ops, .. } => format!("Batch {{ ops: {:?} }}", ops),
+            BankMessage::Payroll { from, credits, mode, .. } => {
+                format!("Payroll {{ from: {:?}, credits: {:?}, mode: {:?} }}", from, credits, mode)
+            },
+            BankMessage::Balance { account, .. } => format!("Balance {{ account: {:?} }}", account),
+            BankMessage::AuditBalance { account, .. } => format!("AuditBalance {{ account: {:?} }}", account),
+            BankMessage::WaitForBalance { account, at_least, timeout, .. } => {
+                format!("WaitForBalance {{ account: {:?}, at_least: {}, timeout: {:?} }}", account, at_least, timeout)
+            },
+            BankMessage::AvailableBalance { account, .. } => format!("AvailableBalance {{ account: {:?} }}", account),
+            BankMessage::TotalBalance { .. } => "TotalBalance".to_string(),
+            BankMessage::Balances { .. } => "Balances".to_string(),
+            BankMessage::Query { query, .. } => format!("Query {{ query: {:?} }}", query),
+            BankMessage::Journal { last, .. } => format!("Journal {{ last: {} }}", last),
+            BankMessage::Receipt { transaction, .. } => format!("Receipt {{ transaction: {} }}", transaction),
+            BankMessage::SkewClock { offset, .. } => format!("SkewClock {{ offset: {} }}", offset),
+            BankMessage::CheckInvariants { .. } => "CheckInvariants".to_string(),
+            BankMessage::Snapshot { path, mode, format, .. } => {
+                format!("Snapshot {{ path: {:?}, mode: {:?}, format: {:?} }}", path, mode, format)
+            },
+            BankMessage::Compact { policy, .. } => format!("Compact {{ policy: {:?} }}", policy),
+            BankMessage::Subscribe { pattern, .. } => format!("Subscribe {{ pattern: {:?} }}", pattern),
+            BankMessage::Resubscribe { pattern, last_seen, .. } => {
+                format!("Resubscribe {{ pattern: {:?}, last_seen: {} }}", pattern, last_seen)
+            },
+            BankMessage::ShipOplog { after, .. } => format!("ShipOplog {{ after: {} }}", after),
+            BankMessage::Statement { request_id, account, entries, .. } => {
+                format!("Statement {{ request_id: {}, account: {:?}, entries: {} }}", request_id, account, entries)
+            },
+            BankMessage::Cancel { request_id, .. } => format!("Cancel {{ request_id: {} }}", request_id),
+        }
+    }
+
+    // Rough relative cost, for schedulers: reads are cheapest, single
+    // writes next, and bulk work grows with its size
+    pub fn estimated_cost(&self) -> u32 {
+        match self {
+            BankMessage::Balance { .. }
+            | BankMessage::AvailableBalance { .. }
+            | BankMessage::AuditBalance { .. }
+            | BankMessage::WaitForBalance { .. }
+            | BankMessage::Holdings { .. }
+            | BankMessage::Metadata { .. }
+            | BankMessage::ListAccounts { .. }
+            | BankMessage::TotalBalance { .. }
+            | BankMessage::Receipt { .. }
+            | BankMessage::Cancel { .. } => 1,
+            BankMessage::Batch { ops, .. } => 2 + ops.len() as u32,
+            BankMessage::Payroll { credits, .. } => 2 + credits.len() as u32,
+            BankMessage::Balances { .. }
+            | BankMessage::Query { .. }
+            | BankMessage::Journal { .. }
+            | BankMessage::ShipOplog { .. } => 10,
+            BankMessage::CheckInvariants { .. }
+            | BankMessage::Snapshot { .. }
+            | BankMessage::Compact { .. }
+            | BankMessage::Statement { .. } => 50,
+            _ => 2,
+        }
+    }
+}
+
+impl BankEvent {
+    // Whether the event is about `account`, as either side of a payment
+    pub fn involves(&self, account: &str) -> bool {
+        match self {
+            BankEvent::Deposited { account: subject, .. }
+            | BankEvent::AccountClosed { account: subject }
+            | BankEvent::AccountRestored { account: subject }
+            | BankEvent::AccountPurged { account: subject }
+            | BankEvent::HoldExpired { from: subject, .. } => subject == account,
+            BankEvent::Transferred { from, to, fee, .. } => {
+                from == account || to == account || fee.as_ref().is_some_and(|(fees, _)| fees == account)
+            },
+            BankEvent::Exchanged { from, to, .. } => from == account || to == account,
+            BankEvent::AccountLinked { account: subject, parent } => subject == account || parent == account,
+            BankEvent::Alarm { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for BankEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BankEvent::Deposited { transaction, account, currency, amount, balance } => {
+                write!(f, "{} deposited {} {} (balance {}, tx {})", account, amount, currency, balance, transaction)
+            },
+            BankEvent::Transferred { transaction, from, to, currency, amount, fee } => {
+                write!(f, "{} sent {} {} to {}", from, amount, currency, to)?;
+                if let Some((fees, fee)) = fee {
+                    write!(f, ", fee {} to {}", fee, fees)?;
+                }
+                write!(f, " (tx {})", transaction)
+            },
+            BankEvent::Exchanged { transaction, from, to, currency, amount, to_currency, credited } => {
+                write!(f, "{} sent {} {} to {} as {} {} (tx {})", from, amount, currency, to, credited, to_currency, transaction)
+            },
+            BankEvent::Alarm { violation } => write!(f, "ALARM: {}", violation),
+            BankEvent::AccountClosed { account } => write!(f, "{} closed", account),
+            BankEvent::AccountRestored { account } => write!(f, "{} restored", account),
+            BankEvent::AccountPurged { account } => write!(f, "{} purged", account),
+            BankEvent::AccountLinked { account, parent } => write!(f, "{} is now under {}", account, parent),
+            BankEvent::HoldExpired { hold, from, amount } => write!(f, "hold {} of {} on {} expired", hold, amount, from),
+        }
+    }
+}
+
+pub const ALARM_TOPIC: &str = "alarms.invariants";
+
+// Topics are lowercase so patterns don't depend on how names are capitalized
+fn deposit_topic(account: &str) -> String {
+    format!("account.{}.deposit", account.to_lowercase())
+}
+
+// `action` is one of closed, restored, purged or hold-expired
+fn lifecycle_topic(account: &str, action: &str) -> String {
+    format!("account.{}.{}", account.to_lowercase(), action)
+}
+
+fn transfer_topic(from: &str, to: &str) -> String {
+    format!("transfers.{}.{}", from.to_lowercase(), to.to_lowercase())
+}
+
+// The fees account's side of a charged transfer, for its event
+fn fee_credit(ledger: &Ledger, fee: &FeeBreakdown) -> Option<(String, i32)> {
+    ledger.fee_account().filter(|_| fee.total > 0).map(|fees| (fees.to_string(), fee.total))
+}
+
+// Background task asking the manager to verify its books on every tick.
+// It only holds a weak sender so it never keeps the manager alive, and it
+// stops by itself once the manager's last client is gone.
+pub fn spawn_watchdog(tx: &mpsc::Sender<BankMessage>, interval: Duration) -> JoinHandle<()> {
+    let tx = tx.downgrade();
+    tokio::spawn(async move {
+        loop {
+            sleep(interval).await;
+            let Some(tx) = tx.upgrade() else { break };
+
+            let (resp_tx, resp_rx) = oneshot::channel();
+            if tx.send(BankMessage::CheckInvariants { respond_to: resp_tx }).await.is_err() {
+                break;
+            }
+            drop(tx);
+
+            match resp_rx.await {
+                Ok(violations) if !violations.is_empty() => {
+                    println!("Watchdog found {} violation(s)", violations.len());
+                },
+                Ok(_) => {},
+                Err(_) => break,
+            }
+        }
+    })
+}
+
+// Background task applying a retention policy on every tick. Like the
+// watchdog it only holds a weak sender.
+pub fn spawn_compactor(tx: &mpsc::Sender<BankMessage>, policy: RetentionPolicy, interval: Duration) -> JoinHandle<()> {
+    let tx = tx.downgrade();
+    tokio::spawn(async move {
+        loop {
+            sleep(interval).await;
+            let Some(tx) = tx.upgrade() else { break };
+
+            let (resp_tx, resp_rx) = oneshot::channel();
+            if tx.send(BankMessage::Compact { policy, respond_to: resp_tx }).await.is_err() {
+                break;
+            }
+            drop(tx);
+
+            match resp_rx.await {
+                Ok(compaction) if compaction.compacted > 0 => {
+                    println!(
+                        "Compactor folded {} entries - {} entries, ~{} bytes remain",
+                        compaction.compacted, compaction.entries, compaction.bytes
+                    );
+                },
+                Ok(_) => {},
+                Err(_) => break,
+            }
+        }
+    })
+}
+
+// Background task purging accounts once they have been closed for longer
+// than `retention`; until then they can be restored
+pub fn spawn_purger(tx: &mpsc::Sender<BankMessage>, retention: Duration, interval: Duration) -> JoinHandle<()> {
+    let tx = tx.downgrade();
+    tokio::spawn(async move {
+        loop {
+            sleep(interval).await;
+            let Some(tx) = tx.upgrade() else { break };
+
+            let (resp_tx, resp_rx) = oneshot::channel();
+            if tx.send(BankMessage::Purge { retention, respond_to: resp_tx }).await.is_err() {
+                break;
+            }
+            drop(tx);
+
+            match resp_rx.await {
+                Ok(purged) if !purged.is_empty() => println!("Purger removed {}", purged.join(", ")),
+                Ok(_) => {},
+                Err(_) => break,
+            }
+        }
+    })
+}
+
+// Releases lapsed holds every `interval`, like the purger
+pub fn spawn_hold_expirer(tx: &mpsc::Sender<BankMessage>, interval: Duration) -> JoinHandle<()> {
+    let tx = tx.downgrade();
+    tokio::spawn(async move {
+        loop {
+            sleep(interval).await;
+            let Some(tx) = tx.upgrade() else { break };
+
+            let (resp_tx, resp_rx) = oneshot::channel();
+            if tx.send(BankMessage::ExpireHolds { respond_to: resp_tx }).await.is_err() {
+                break;
+            }
+            drop(tx);
+
+            match resp_rx.await {
+                Ok(expired) if !expired.is_empty() => println!("Expirer released {} hold(s)", expired.len()),
+                Ok(_) => {},
+                Err(_) => break,
+            }
+        }
+    })
+}
+
+#[derive(Debug)]
+pub struct TaggedReply {
+    pub id: Id,
+    pub result: Result<i32, BankError>,
+}
+
+#[derive(Debug)]
+pub struct Progress {
+    pub pct: u8,
+    pub eta: Duration,
+}
+
+#[derive(Debug)]
+pub struct Statement {
+    pub account: String,
+    pub entries: usize,
+    pub closing_balance: i32,
+}
+
+// Long-running job: works through the entries in chunks, reporting progress
+// after each one and stopping early if the client stops waiting or cancels
+// the request by ID
+async fn generate_statement(
+    account: String,
+    closing_balance: i32,
+    entries: usize,
+    progress: MeteredSender<Progress>,
+    mut respond_to: oneshot::Sender<Result<Statement, BankError>>,
+    cancelled: Arc<AtomicBool>,
+) {
+    const CHUNKS: usize = 10;
+    let started = Instant::now();
+
+    for chunk in 1..=CHUNKS {
+        tokio::select! {
+            // Dropping the response receiver is how a client cancels
+            _ = respond_to.closed() => {
+                println!("Statement for {} cancelled after {}%", account, (chunk - 1) * 100 / CHUNKS);
+                return;
+            }
+            // Simulate processing entries / CHUNKS entries
+            _ = sleep(Duration::from_millis(300)) => {}
+        }
+
+        if cancelled.load(Ordering::Relaxed) {
+            println!("Statement for {} cancelled by request after {}%", account, chunk * 100 / CHUNKS);
+            let _ = respond_to.send(Err(BankError::Cancelled));
+            return;
+        }
+
+        let elapsed = started.elapsed();
+        let eta = elapsed / chunk as u32 * (CHUNKS - chunk) as u32;
+        let _ = progress.send(Progress { pct: (chunk * 100 / CHUNKS) as u8, eta }).await;
+    }
+
+    let _ = respond_to.send(Ok(Statement { account, entries, closing_balance }));
+}
+
+// Bank manager: owns the books and handles one message at a time.
+// Once every sender is gone it verifies that the books still balance.
+pub async fn run_bank_manager(
+    rx: mpsc::Receiver<BankMessage>,
+    accounts: HashMap<String, i32>,
+    delay: Duration,
+) {
+    // Nobody will ever switch this manager out of read-write mode
+    let (_, mode) = watch::channel(ServiceMode::ReadWrite);
+    run_bank_manager_with_mode(rx, accounts, delay, mode).await
+}
+
+// Manager taking requests in the order the policy picks rather than as
+// they arrived
+pub async fn run_bank_manager_scheduled(
+    rx: mpsc::Receiver<BankMessage>,
+    accounts: HashMap<String, i32>,
+    delay: Duration,
+    policy: SchedulePolicy,
+) {
+    let (_, mode) = watch::channel(ServiceMode::ReadWrite);
+    allocs::profiled("bank-manager", run_manager(rx, accounts, delay, mode, Scheduler::new(policy))).await
+}
+
+pub async fn run_bank_manager_with_mode(
+    rx: mpsc::Receiver<BankMessage>,
+    accounts: HashMap<String, i32>,
+    delay: Duration,
+    mode: watch::Receiver<ServiceMode>,
+) {
+    allocs::profiled("bank-manager", run_manager(rx, accounts, delay, mode, Scheduler::new(SchedulePolicy::Fifo))).await
+}
+
+async fn run_manager(
+    mut rx: mpsc::Receiver<BankMessage>,
+    accounts: HashMap<String, i32>,
+    delay: Duration,
+    mut mode: watch::Receiver<ServiceMode>,
+    mut scheduler: Scheduler,
+) {
+    let mut ledger = Ledger::with_opening_balances(accounts);
+    let mut events = Router::new();
+    let mut oplog = Oplog::new(&ledger);
+    let mut metadata = MetadataStore::new();
+    let rates = Rates::default();
+    // Cancellation flags of long operations, by request ID
+    let mut in_flight: HashMap<Id, Arc<AtomicBool>> = HashMap::new();
+    // Requests numbered in arrival order, for crash reports
+    let mut requests = 0;
+    let mut waiters: Vec<BalanceWaiter> = vec![];
+
+    loop {
+        let next_deadline = waiters.iter().map(|waiter| waiter.deadline).min();
+        let msg = tokio::select! {
+            msg = scheduler.next(&mut rx) => match msg {
+                Some(msg) => msg,
+                None => break,
+            },
+            Ok(()) = mode.changed() => {
+                println!("Manager switched to {:?}", *mode.borrow_and_update());
+                continue;
+            }
+            _ = sleep_until(next_deadline.unwrap_or_else(Instant::now)), if next_deadline.is_some() => {
+                expire_waiters(&mut waiters);
+                continue;
+            }
+        };
+
+        // Manager processes each request sequentially
+        requests += 1;
+        crash::record_request(requests);
+        let started = Instant::now();
+        calibration::simulate_work(delay).await;
+        slowlog::check(started.elapsed(), || msg.describe());
+        let writable = *mode.borrow() == ServiceMode::ReadWrite;
+        let last_seq = events.last_seq();
+
+        match msg {
+            BankMessage::Deposit { account, amount, respond_to } => {
+                let result = deposit(&mut ledger, writable, &account, amount);
+                if let Ok(balance) = result {
+                    let transaction = ledger.last_transaction();
+                    events.publish(&deposit_topic(&account), BankEvent::Deposited { transaction, account, currency: Currency::BASE, amount, balance });
+                }
+                let _ = respond_to.send(result);
+            },
+            BankMessage::Transfer { from, to, amount, respond_to } => {
+                let result = if writable {
+                    ledger.itemized_transfer(&from, &to, amount, Currency::BASE).map_err(BankError::Rejected)
+                } else {
+                    Err(BankError::ReadOnly)
+                };
+                let result = result.map(|(balance, fee)| {
+                    let transaction = ledger.last_transaction();
+                    let fee = fee_credit(&ledger, &fee);
+                    events.publish(&transfer_topic(&from, &to), BankEvent::Transferred { transaction, from, to, currency: Currency::BASE, amount, fee });
+                    balance
+                });
+                let _ = respond_to.send(result);
+            },
+            BankMessage::TransferItemized { from, to, amount, respond_to } => {
+                let result = if writable {
+                    ledger.itemized_transfer(&from, &to, amount, Currency::BASE).map_err(BankError::Rejected)
+                } else {
+                    Err(BankError::ReadOnly)
+                };
+                let result = result.map(|(balance, fee)| {
+                    let transaction = ledger.last_transaction();
+                    let credit = fee_credit(&ledger, &fee);
+                    events.publish(&transfer_topic(&from, &to), BankEvent::Transferred { transaction, from, to, currency: Currency::BASE, amount, fee: credit });
+                    TransferReport { transaction, balance, fee }
+                });
+                let _ = respond_to.send(result);
+            },
+            BankMessage::SetFeeSchedule { schedule, respond_to } => {
+                let result = if writable {
+                    ledger.set_fee_schedule(schedule).map_err(BankError::Rejected)
+                } else {
+                    Err(BankError::ReadOnly)
+                };
+                let _ = respond_to.send(result);
+            },
+            BankMessage::TaggedDeposit { id, account, amount, respond_to } => {
+                let result = deposit(&mut ledger, writable, &account, amount);
+                if let Ok(balance) = result {
+                    let transaction = ledger.last_transaction();
+                    events.publish(&deposit_topic(&account), BankEvent::Deposited { transaction, account, currency: Currency::BASE, amount, balance });
+                }
+                let _ = respond_to.send(TaggedReply { id, result }).await;
+            },
+            BankMessage::OpenAccount { account, opening_balance, respond_to } => {
+                let result = if !writable {
+                    Err(BankError::ReadOnly)
+                } else {
+                    ledger.open_account(&account, opening_balance).map_err(BankError::Rejected)
+                };
+                let _ = respond_to.send(result);
+            },
+            BankMessage::CloseAccount { account, respond_to } => {
+                let result = if writable {
+                    ledger.close_account(&account).map_err(BankError::Rejected)
+                } else {
+                    Err(BankError::ReadOnly)
+                };
+                if result.is_ok() {
+                    events.publish(&lifecycle_topic(&account, "closed"), BankEvent::AccountClosed { account });
+                }
+                let _ = respond_to.send(result);
+            },
+            BankMessage::RestoreAccount { account, respond_to } => {
+                let result = if writable {
+                    ledger.restore_account(&account).map_err(BankError::Rejected)
+                } else {
+                    Err(BankError::ReadOnly)
+                };
+                if result.is_ok() {
+                    events.publish(&lifecycle_topic(&account, "restored"), BankEvent::AccountRestored { account });
+                }
+                let _ = respond_to.send(result);
+            },
+            BankMessage::Purge { retention, respond_to } => {
+                let purged: Vec<String> = if writable {
+                    ledger.purge_closed(retention).iter().map(ToString::to_string).collect()
+                } else {
+                    vec![]
+                };
+                for account in &purged {
+                    events.publish(&lifecycle_topic(account, "purged"), BankEvent::AccountPurged { account: account.clone() });
+                }
+                let _ = respond_to.send(purged);
+            },
+            BankMessage::SetParent { account, parent, respond_to } => {
+                let result = if writable {
+                    ledger.set_parent(&account, &parent).map_err(BankError::Rejected)
+                } else {
+                    Err(BankError::ReadOnly)
+                };
+                if result.is_ok() {
+                    events.publish(&lifecycle_topic(&account, "linked"), BankEvent::AccountLinked { account, parent });
+                }
+                let _ = respond_to.send(result);
+            },
+            BankMessage::AddCurrency { account, currency, respond_to } => {
+                let result = if writable {
+                    ledger.add_currency(&account, currency).map_err(BankError::Rejected)
+                } else {
+                    Err(BankError::ReadOnly)
+                };
+                let _ = respond_to.send(result);
+            },
+            BankMessage::DepositIn { account, amount, currency, respond_to } => {
+                let result = if writable {
+                    ledger.deposit_in(&account, amount, currency).map_err(BankError::Rejected)
+                } else {
+                    Err(BankError::ReadOnly)
+                };
+                if let Ok(balance) = result {
+                    let transaction = ledger.last_transaction();
+                    events.publish(&deposit_topic(&account), BankEvent::Deposited { transaction, account, currency, amount, balance });
+                }
+                let _ = respond_to.send(result);
+            },
+            BankMessage::TransferIn { from, to, amount, currency, convert_to, respond_to } => {
+                let to_currency = convert_to.unwrap_or(currency);
+                let result = if !writable {
+                    Err(BankError::ReadOnly)
+                } else if to_currency == currency {
+                    ledger
+                        .itemized_transfer(&from, &to, amount, currency)
+                        .map(|(_, fee)| (amount, fee_credit(&ledger, &fee)))
+                        .map_err(BankError::Rejected)
+                } else {
+                    ledger.exchange(&from, &to, amount, currency, to_currency, &rates).map(|credited| (credited, None)).map_err(BankError::Rejected)
+                };
+                let result = result.map(|(credited, fee)| {
+                    let transaction = ledger.last_transaction();
+                    let topic = transfer_topic(&from, &to);
+                    let event = if to_currency == currency {
+                        BankEvent::Transferred { transaction, from, to, currency, amount, fee }
+                    } else {
+                        BankEvent::Exchanged { transaction, from, to, currency, amount, to_currency, credited }
+                    };
+                    events.publish(&topic, event);
+                    credited
+                });
+                let _ = respond_to.send(result);
+            },
+            BankMessage::Holdings { account, respond_to } => {
+                let result = ledger.holdings(&account).ok_or(BankError::Rejected("Account not found"));
+                let _ = respond_to.send(result);
+            },
+            BankMessage::Authorize { from, to, amount, ttl, respond_to } => {
+                let result = if writable {
+                    ledger.authorize(&from, &to, amount, ttl).map_err(BankError::Rejected)
+                } else {
+                    Err(BankError::ReadOnly)
+                };
+                let _ = respond_to.send(result);
+            },
+            BankMessage::Capture { hold, respond_to } => {
+                let result = if writable { ledger.capture(hold).map_err(BankError::Rejected) } else { Err(BankError::ReadOnly) };
+                let result = result.map(|Hold { from, to, amount, .. }| {
+                    let balance = ledger.balance(&from).unwrap_or_default();
+                    let transaction = ledger.last_transaction();
+                    events.publish(&transfer_topic(&from, &to), BankEvent::Transferred { transaction, from: from.to_string(), to: to.to_string(), currency: Currency::BASE, amount, fee: None });
+                    balance
+                });
+                let _ = respond_to.send(result);
+            },
+            BankMessage::Void { hold, respond_to } => {
+                let result = if writable {
+                    ledger.void(hold).map(|_| ()).map_err(BankError::Rejected)
+                } else {
+                    Err(BankError::ReadOnly)
+                };
+                let _ = respond_to.send(result);
+            },
+            BankMessage::ExpireHolds { respond_to } => {
+                // Expiry only gives money back, so it runs in read-only mode too
+                let expired = ledger.expire_holds();
+                for Hold { id, from, amount, .. } in &expired {
+                    let event = BankEvent::HoldExpired { hold: *id, from: from.to_string(), amount: *amount };
+                    events.publish(&lifecycle_topic(from, "hold-expired"), event);
+                }
+                let _ = respond_to.send(expired.iter().map(|hold| hold.id).collect());
+            },
+            BankMessage::DefineMetadataKey { key, policy, respond_to } => {
+                let _ = respond_to.send(metadata.define_key(&key, policy));
+            },
+            BankMessage::SetMetadata { account, key, value, stamp, respond_to } => {
+                let result = if !writable {
+                    Err(BankError::ReadOnly)
+                } else if ledger.balance(&account).is_none() {
+                    Err(BankError::Rejected("Account not found"))
+                } else {
+                    Ok(metadata.write(&account, &key, &value, stamp))
+                };
+                let _ = respond_to.send(result);
+            },
+            BankMessage::Metadata { account, respond_to } => {
+                let result = match ledger.balance(&account) {
+                    Some(_) => Ok(metadata.get(&account)),
+                    None => Err(BankError::Rejected("Account not found")),
+                };
+                let _ = respond_to.send(result);
+            },
+            BankMessage::ListAccounts { respond_to } => {
+                let _ = respond_to.send(ledger.accounts().iter().map(ToString::to_string).collect());
+            },
+            BankMessage::Batch { ops, respond_to } => {
+                let result = if writable { stage_batch(&ledger, ops) } else { Err(BankError::ReadOnly) };
+                let result = result.and_then(|(staged, rollbacks)| {
+                    // Everything was validated in order, so posting should
+                    // agree; if the ledger refuses an operation anyway, stop
+                    // there and say how much was posted
+                    let mut committed = 0;
+                    for op in staged {
+                        let posted = match op {
+                            Staged::Deposit { account, amount } => ledger.deposit(&account, amount).map(|balance| {
+                                let transaction = ledger.last_transaction();
+                                events.publish(&deposit_topic(&account), BankEvent::Deposited { transaction, account: account.to_string(), currency: Currency::BASE, amount, balance });
+                            }),
+                            Staged::Transfer { from, to, amount } => ledger.itemized_transfer(&from, &to, amount, Currency::BASE).map(|(_, fee)| {
+                                let transaction = ledger.last_transaction();
+                                let fee = fee_credit(&ledger, &fee);
+                                events.publish(&transfer_topic(&from, &to), BankEvent::Transferred { transaction, from: from.to_string(), to: to.to_string(), currency: Currency::BASE, amount, fee });
+                            }),
+                        };
+                        if let Err(reason) = posted {
+                            return Err(BankError::BatchIncomplete { committed, reason });
+                        }
+                        committed += 1;
+                    }
+                    Ok(BatchReport { committed, rollbacks })
+                });
+                let _ = respond_to.send(result);
+            },
+            BankMessage::Payroll { from, credits, mode, respond_to } => {
+                let result = if writable {
+                    ledger.payroll(&from, &credits, mode).map_err(BankError::Rejected)
+                } else {
+                    Err(BankError::ReadOnly)
+                };
+                if let Ok(report) = &result {
+                    for payment in &report.paid {
+                        let event = BankEvent::Transferred {
+                            transaction: payment.transaction,
+                            from: from.clone(),
+                            to: payment.to.to_string(),
+                            currency: Currency::BASE,
+                            amount: payment.amount,
+                            fee: None,
+                        };
+                        events.publish(&transfer_topic(&from, &payment.to), event);
+                    }
+                }
+                let _ = respond_to.send(result);
+            },
+            BankMessage::Balance { account, respond_to } => {
+                let result = ledger.balance(&account).ok_or(BankError::Rejected("Account not found"));
+                let _ = respond_to.send(result);
+            },
+            BankMessage::AuditBalance { account, respond_to } => {
+                let result = match (ledger.balance(&account), ledger.journal_balance(&account)) {
+                    (Some(balance), Some(journal)) => Ok(BalanceAudit { balance, journal, seq: events.last_seq() }),
+                    _ => Err(BankError::Rejected("Account not found")),
+                };
+                let _ = respond_to.send(result);
+            },
+            BankMessage::WaitForBalance { account, at_least, timeout, respond_to } => match ledger.balance(&account) {
+                Some(balance) if balance >= at_least => {
+                    let _ = respond_to.send(Ok(balance));
+                },
+                Some(_) => match Instant::now().checked_add(timeout) {
+                    Some(deadline) => waiters.push(BalanceWaiter { account, at_least, deadline, respond_to }),
+                    None => {
+                        let _ = respond_to.send(Err(BankError::Rejected("Wait too long")));
+                    },
+                },
+                None => {
+                    let _ = respond_to.send(Err(BankError::Rejected("Account not found")));
+                },
+            },
+            BankMessage::AvailableBalance { account, respond_to } => {
+                let result = ledger.available(&account).ok_or(BankError::Rejected("Account not found"));
+                let _ = respond_to.send(result);
+            },
+            BankMessage::TotalBalance { respond_to } => {
+                let _ = respond_to.send(ledger.total_balance());
+            },
+            BankMessage::Balances { respond_to } => {
+                let balances = ledger
+                    .accounts()
+                    .into_iter()
+                    .filter_map(|account| ledger.balance(&account).map(|balance| (account.to_string(), balance)))
+                    .collect();
+                let _ = respond_to.send(balances);
+            },
+            BankMessage::Query { query, respond_to } => {
+                let _ = respond_to.send(query.run(&ledger));
+            },
+            BankMessage::Journal { last, respond_to } => {
+                let entries = ledger.entries();
+                let _ = respond_to.send(entries[entries.len().saturating_sub(last)..].to_vec());
+            },
+            BankMessage::Receipt { transaction, respond_to } => {
+                let result = ledger.receipt(transaction).ok_or(BankError::Rejected("Transaction not found"));
+                let _ = respond_to.send(result);
+            },
+            BankMessage::SkewClock { offset, respond_to } => {
+                let _ = respond_to.send(ledger.skew_clock(offset));
+            },
+            BankMessage::CheckInvariants { respond_to } => {
+                let violations = ledger.violations();
+                for violation in &violations {
+                    events.publish(ALARM_TOPIC, BankEvent::Alarm { violation: violation.clone() });
+                }
+                let _ = respond_to.send(violations);
+            },
+            BankMessage::Snapshot { path, mode, format, respond_to } => {
+                let started = Instant::now();
+                let copy = LedgerCopy::of(&ledger);
+                match mode {
+                    SnapshotMode::Inline => {
+                        let result = write_snapshot(&copy, &path, format, started, Duration::ZERO)
+                            .map(|report| SnapshotReport { manager_time: report.total_time, ..report })
+                            .map_err(|e| BankError::Storage(e.to_string()));
+                        let _ = respond_to.send(result);
+                    },
+                    // Only the copy is made here; serializing and writing
+                    // happen off the manager so it keeps serving
+                    SnapshotMode::Background => {
+                        let manager_time = started.elapsed();
+                        tokio::task::spawn_blocking(move || {
+                            let result = write_snapshot(&copy, &path, format, started, manager_time)
+                                .map_err(|e| BankError::Storage(e.to_string()));
+                            let _ = respond_to.send(result);
+                        });
+                    },
+                }
+            },
+            BankMessage::Compact { policy, respond_to } => {
+                let compaction = ledger.compact(&policy);
+                metrics::set_gauge("ledger_entries", compaction.entries as u64);
+                metrics::set_gauge("ledger_bytes", compaction.bytes as u64);
+                let _ = respond_to.send(compaction);
+            },
+            BankMessage::Subscribe { pattern, respond_to } => {
+                let _ = respond_to.send(events.subscribe(&pattern, 64));
+            },
+            BankMessage::Resubscribe { pattern, last_seen, respond_to } => {
+                let result = events.resubscribe(&pattern, last_seen, 64).map_err(|truncated| {
+                    println!("Cannot replay from #{}: history starts at #{}", last_seen + 1, truncated.oldest);
+                    BankError::Rejected("Missed events are no longer retained")
+                });
+                let _ = respond_to.send(result);
+            },
+            BankMessage::ShipOplog { after, respond_to } => {
+                let _ = respond_to.send(oplog.follow(&ledger, after));
+            },
+            BankMessage::Statement { request_id, account, entries, progress, respond_to } => {
+                match ledger.balance(&account) {
+                    // Hand the slow work to its own task so the manager keeps serving
+                    Some(balance) => {
+                        // Flags only the manager still holds belong to finished jobs
+                        in_flight.retain(|_, flag| Arc::strong_count(flag) > 1);
+                        let cancelled = Arc::new(AtomicBool::new(false));
+                        in_flight.insert(request_id, Arc::clone(&cancelled));
+                        tokio::spawn(generate_statement(account, balance, entries, progress, respond_to, cancelled));
+                    },
+                    None => {
+                        let _ = respond_to.send(Err(BankError::Rejected("Account not found")));
+                    }
+                }
+            },
+            BankMessage::Cancel { request_id, respond_to } => {
+                let running = match in_flight.remove(&request_id) {
+                    Some(flag) if Arc::strong_count(&flag) > 1 => {
+                        flag.store(true, Ordering::Relaxed);
+                        true
+                    },
+                    _ => false,
+                };
+                let _ = respond_to.send(running);
+            }
+        }
+
+        // Only a published change can satisfy a waiter
+        if events.last_seq() != last_seq {
+            wake_waiters(&mut waiters, &ledger);
+        }
+        oplog.ship(&ledger);
+    }
+
+    if events.dropped() > 0 {
+        println!("{} events dropped by slow subscribers", events.dropped());
+    }
+    match ledger.check_invariants() {
+        Ok(()) => println!("Books balanced across {} journal entries", ledger.entries().len()),
+        Err(e) => println!("Invariant violated: {}", e),
+    }
+}
+
+// A `WaitForBalance` request the manager is holding on to
+struct BalanceWaiter {
+    account: String,
+    at_least: i32,
+    deadline: Instant,
+    respond_to: oneshot::Sender<Result<i32, BankError>>,
+}
+
+// Answer the waiters whose balance got there, or whose account went away.
+// Waiters whose callers gave up are dropped.
+fn wake_waiters(waiters: &mut Vec<BalanceWaiter>, ledger: &Ledger) {
+    let (done, waiting) = std::mem::take(waiters).into_iter().partition(|waiter: &BalanceWaiter| {
+        waiter.respond_to.is_closed() || ledger.balance(&waiter.account).is_none_or(|balance| balance >= waiter.at_least)
+    });
+    *waiters = waiting;
+    for waiter in done {
+        let result = ledger.balance(&waiter.account).ok_or(BankError::Rejected("Account not found"));
+        let _ = waiter.respond_to.send(result);
+    }
+}
+
+fn expire_waiters(waiters: &mut Vec<BalanceWaiter>) {
+    let now = Instant::now();
+    let (expired, waiting) = std::mem::take(waiters).into_iter().partition(|waiter: &BalanceWaiter| waiter.deadline <= now);
+    *waiters = waiting;
+    for waiter in expired {
+        let _ = waiter.respond_to.send(Err(BankError::TimedOut));
+    }
+}
+
+// Validate a batch against the books without changing them, applying its
+// savepoints and rollbacks. Returns the operations left to post.
+fn stage_batch(ledger: &Ledger, ops: Vec<BatchOp>) -> Result<(Vec<Staged>, Vec<String>), BankError> {
+    let mut transaction = ledger.begin();
+    let mut rollbacks = vec![];
+
+    for (step, op) in (1..).zip(ops) {
+        let result = match op {
+            BatchOp::Deposit { account, amount } => transaction.deposit(&account, amount).map(|_| ()),
+            BatchOp::Transfer { from, to, amount } => transaction.transfer(&from, &to, amount).map(|_| ()),
+            BatchOp::Savepoint(name) => {
+                transaction.savepoint(&name);
+                Ok(())
+            },
+            BatchOp::RollbackTo(name) => transaction.rollback_to(&name).map(|undone| {
+                rollbacks.push(format!("step {}: rolled back {} operation(s) to {}", step, undone, name));
+            }),
+        };
+
+        if let Err(reason) = result {
+            let Some(savepoint) = transaction.latest_savepoint().map(str::to_string) else {
+                return Err(BankError::Rejected(reason));
+            };
+            let undone = transaction.rollback_to(&savepoint).map_err(BankError::Rejected)?;
+            rollbacks.push(format!("step {} failed ({}): rolled back {} operation(s) to {}", step, reason, undone, savepoint));
+        }
+    }
+
+    Ok((transaction.commit(), rollbacks))
+}
+
+fn deposit(ledger: &mut Ledger, writable: bool, account: &str, amount: i32) -> Result<i32, BankError> {
+    if !writable {
+        return Err(BankError::ReadOnly);
+    }
+    ledger.deposit(account, amount).map_err(BankError::Rejected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn descriptions_leave_out_the_reply_channel() {
+        let (respond_to, _) = oneshot::channel();
+        let message = BankMessage::Transfer { from: "Alice".to_string(), to: "Bob".to_string(), amount: 5, respond_to };
+        assert_eq!(message.describe(), r#"Transfer { from: "Alice", to: "Bob", amount: 5 }"#);
+
+        let (respond_to, _) = oneshot::channel();
+        assert_eq!(BankMessage::ListAccounts { respond_to }.describe(), "ListAccounts");
+    }
+}
@@ -0,0 +1,1312 @@
+use std::collections::{BTreeMap, HashMap};
+use tokio::sync::{oneshot, watch};
+use tokio::task::JoinSet;
+use tokio::time::{sleep, timeout, Duration, Instant};
+
+use crate::bank::{run_bank_manager, run_bank_manager_scheduled, run_bank_manager_with_mode, BankError, BankMessage, BatchOp, ALARM_TOPIC};
+use crate::client::{
+    add_currency, authorize, available_balance, balance, balances, batch, capture, check_invariants, close_account,
+    define_metadata_key, deposit, deposit_in, holdings, journal, list_accounts, metadata, payroll, receipt, restore_account, set_fee_schedule, set_metadata, skew_clock, subscribe, transfer, transfer_in,
+    transfer_itemized, void, wait_for_balance,
+};
+use crate::blocking::BlockingBank;
+use crate::chaos::{self, Subsystem};
+use crate::clock::HybridClock;
+use crate::config::{Config, ServiceMode};
+use crate::context::AppContext;
+use crate::currency::Currency;
+use crate::fees::{FeeRule, FeeSchedule};
+use crate::hedge::HedgedReader;
+use crate::import;
+use crate::keys::{self, Operation, Scope};
+use crate::ledger::{PayrollMode, RetentionPolicy};
+use crate::metadata::{MergePolicy, Stamp};
+use crate::metrics::{channel_with_metrics, MeteredSender};
+use crate::oplog::Follower;
+use crate::quota::{self, QuotaLimits};
+use crate::reads::{ReadConsistency, ReconcilePolicy};
+use crate::region::{CrossRegion, Topology};
+use crate::scenario::{checked_scenario, scenario, Outcome, Postcondition, Scenario};
+use crate::schedule::{self, SchedulePolicy};
+use crate::snapshot::{read_snapshot, Record, SnapshotFormat, SnapshotMode};
+use crate::supervisor::Supervisor;
+
+fn log(start: Instant, details: &str) {
+    println!("[{:>4}ms] {}", start.elapsed().as_millis(), details);
+}
+
+// Closing balances and the state of the books, for the postconditions
+async fn closing_books(tx: &MeteredSender<BankMessage>) -> Outcome {
+    Outcome::default()
+        .with_balances(balances(tx).await.unwrap())
+        .with_violations(check_invariants(tx).await.unwrap().len())
+}
+
+async fn run_read_only_example(cfg: Config) {
+    println!("\n=== Read-only Mode Example (Runtime Mode Switch) ===");
+    let start = Instant::now();
+    let (mode_tx, mode_rx) = watch::channel(ServiceMode::ReadWrite);
+
+    // Every manager watches the same mode switch
+    let mut managers = vec![];
+    let mut senders = vec![];
+    for account in ["Alice", "Bob"] {
+        let (tx, rx) = channel_with_metrics("bank", 32);
+        let mut accounts = HashMap::new();
+        accounts.insert(account.to_string(), 100);
+        managers.push(tokio::spawn(run_bank_manager_with_mode(rx, accounts, cfg.work_delay / 4, mode_rx.clone())));
+        senders.push((account, tx));
+    }
+
+    for mode in [ServiceMode::ReadWrite, ServiceMode::ReadOnly, ServiceMode::ReadWrite] {
+        mode_tx.send_replace(mode);
+        // Give the managers a moment to pick up the change
+        sleep(Duration::from_millis(10)).await;
+        log(start, &format!("Mode is now {:?}", mode));
+
+        for (account, tx) in &senders {
+            match deposit(tx, account, 10).await {
+                Ok(balance) => log(start, &format!("Deposit to {} accepted - Balance: {}", account, balance)),
+                Err(e) => log(start, &format!("Deposit to {} rejected - {}", account, e)),
+            }
+            match balance(tx, account).await {
+                Ok(balance) => log(start, &format!("Balance of {} is {}", account, balance)),
+                Err(e) => log(start, &format!("Balance of {} failed - {}", account, e)),
+            }
+        }
+    }
+
+    drop(senders);
+    for manager in managers {
+        manager.await.unwrap();
+    }
+}
+
+async fn run_watchdog_example(cfg: Config) {
+    println!("\n=== Invariant Watchdog Example (Checks During Live Traffic) ===");
+    let start = Instant::now();
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 100)
+        .account("Bob", 50)
+        .manager_delay(cfg.work_delay / 10)
+        .watchdog(cfg.watchdog_every)
+        .build()
+        .await;
+    let tx = ctx.bank();
+
+    let mut alarms = subscribe(tx, ALARM_TOPIC).await.unwrap();
+    let alarm_listener = tokio::spawn(async move {
+        while let Some(envelope) = alarms.recv().await {
+            log(start, &format!("#{} {}", envelope.seq, envelope.event));
+        }
+    });
+
+    // Nothing validates deposit amounts, so one bad request overdraws Bob
+    for (account, amount) in [("Alice", 20), ("Bob", 10), ("Bob", -200), ("Alice", 5)] {
+        match deposit(tx, account, amount).await {
+            Ok(balance) => log(start, &format!("Deposited {} to {} - Balance: {}", amount, account, balance)),
+            Err(e) => log(start, &format!("Deposit to {} rejected - {}", account, e)),
+        }
+        sleep(Duration::from_millis(150)).await;
+    }
+
+    ctx.shutdown().await;
+    alarm_listener.await.unwrap();
+}
+
+// Opening balances as they might arrive from another system, bad rows included
+const SAMPLE_IMPORT: &str = "account,balance
+Carol,250
+Dave,75
+Erin,-10
+Carol,30
+Frank,abc
+Alice,500
+Grace,0
+Heidi,120
+";
+
+async fn run_bulk_import_example(cfg: Config) {
+    println!("\n=== Bulk Import Example (Bounded Concurrency, Per-row Errors) ===");
+    let (tx, rx) = channel_with_metrics("bank", 32);
+
+    // Alice already has an account, so importing her again is refused
+    let mut accounts = HashMap::new();
+    accounts.insert("Alice".to_string(), 100);
+    let manager = tokio::spawn(run_bank_manager(rx, accounts, cfg.work_delay / 10));
+
+    let report = import::import(&tx, import::parse(SAMPLE_IMPORT), cfg.clients).await;
+    import::print_report(&report);
+
+    for account in ["Carol", "Heidi"] {
+        match balance(&tx, account).await {
+            Ok(balance) => println!("{} opened with {}", account, balance),
+            Err(e) => println!("{} missing - {}", account, e),
+        }
+    }
+
+    drop(tx);
+    manager.await.unwrap();
+}
+
+async fn run_quota_example(cfg: Config) {
+    println!("\n=== Quota Example (Sliding-window Limits per Client) ===");
+    let start = Instant::now();
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 100)
+        .manager_delay(cfg.work_delay / 20)
+        .quotas(QuotaLimits { per_minute: 5, ..QuotaLimits::default() })
+        .build()
+        .await;
+    let quotas = ctx.quotas();
+    let batch = ctx.quota_gate("batch-job");
+    let mobile = ctx.quota_gate("mobile-app");
+
+    // The batch job bursts past its per-minute allowance; the app is unaffected
+    for i in 1..=7 {
+        match batch.deposit("Alice", 1).await {
+            Ok(balance) => log(start, &format!("batch-job deposit {} accepted - Balance: {}", i, balance)),
+            Err(e) => log(start, &format!("batch-job deposit {} refused - {}", i, e)),
+        }
+    }
+    match mobile.deposit("Alice", 10).await {
+        Ok(balance) => log(start, &format!("mobile-app deposit accepted - Balance: {}", balance)),
+        Err(e) => log(start, &format!("mobile-app deposit refused - {}", e)),
+    }
+
+    for client in ["batch-job", "mobile-app"] {
+        log(start, &format!("Usage for {}: {}", client, quota::usage(quotas, client).await.unwrap()));
+    }
+
+    // An operator clears the batch job's history
+    quota::reset(quotas, "batch-job").await.unwrap();
+    log(start, &format!("Reset batch-job - usage now {}", quota::usage(quotas, "batch-job").await.unwrap()));
+    match batch.deposit("Alice", 1).await {
+        Ok(balance) => log(start, &format!("batch-job deposit accepted again - Balance: {}", balance)),
+        Err(e) => log(start, &format!("batch-job deposit refused - {}", e)),
+    }
+
+    drop((batch, mobile));
+    ctx.shutdown().await;
+}
+
+async fn run_retention_example(cfg: Config) {
+    println!("\n=== Retention Example (Compacting Old Journal Entries) ===");
+    let start = Instant::now();
+
+    // Keep at most 25 entries, whichever limit bites first
+    let policy = RetentionPolicy {
+        max_entries: Some(25),
+        max_age: Some(Duration::from_secs(60)),
+        max_bytes: Some(64 * 1024),
+    };
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 100)
+        .account("Bob", 50)
+        .manager_delay(Duration::from_millis(1))
+        .retention(policy, cfg.sweep_every)
+        .build()
+        .await;
+    let tx = ctx.bank();
+
+    for i in 0..200 {
+        let account = if i % 3 == 0 { "Bob" } else { "Alice" };
+        deposit(tx, account, 1).await.unwrap();
+    }
+    for account in ["Alice", "Bob"] {
+        log(start, &format!("{} ends with {}", account, balance(tx, account).await.unwrap()));
+    }
+
+    // One last pass so the report reflects the final state
+    let (resp_tx, resp_rx) = oneshot::channel();
+    tx.send(BankMessage::Compact { policy, respond_to: resp_tx }).await.unwrap();
+    let compaction = resp_rx.await.unwrap();
+    log(start, &format!("Journal holds {} entries, ~{} bytes", compaction.entries, compaction.bytes));
+
+    ctx.shutdown().await;
+}
+
+async fn run_soft_delete_example(cfg: Config) -> Outcome {
+    println!("\n=== Soft-delete Example (Close, Restore, Purge) ===");
+    let start = Instant::now();
+
+    // Closed accounts can be restored for 300ms, then the purger removes them
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 100)
+        .account("Bob", 0)
+        .account("Carol", 0)
+        .manager_delay(Duration::from_millis(5))
+        .purge(Duration::from_millis(300), cfg.sweep_every)
+        .build()
+        .await;
+    let tx = ctx.bank();
+
+    let mut lifecycle = subscribe(tx, "account.*").await.unwrap();
+    let listener = tokio::spawn(async move {
+        while let Some(envelope) = lifecycle.recv().await {
+            log(start, &format!("event {} - {}", envelope.topic, envelope.event));
+        }
+    });
+
+    log(start, &format!("Open accounts: {:?}", list_accounts(tx).await.unwrap()));
+
+    for account in ["Alice", "Bob", "Carol"] {
+        match close_account(tx, account).await {
+            Ok(()) => log(start, &format!("Closed {}", account)),
+            Err(e) => log(start, &format!("Closing {} refused - {}", account, e)),
+        }
+    }
+    log(start, &format!("Open accounts: {:?}", list_accounts(tx).await.unwrap()));
+
+    if let Err(e) = deposit(tx, "Bob", 10).await {
+        log(start, &format!("Deposit to Bob refused - {}", e));
+    }
+    match restore_account(tx, "Bob").await {
+        Ok(()) => log(start, "Restored Bob within the grace window"),
+        Err(e) => log(start, &format!("Restoring Bob refused - {}", e)),
+    }
+
+    // Carol stays closed past the grace window
+    sleep(Duration::from_millis(500)).await;
+    match restore_account(tx, "Carol").await {
+        Ok(()) => log(start, "Restored Carol"),
+        Err(e) => log(start, &format!("Restoring Carol refused - {}", e)),
+    }
+    log(start, &format!("Open accounts: {:?}", list_accounts(tx).await.unwrap()));
+
+    let outcome = closing_books(ctx.bank()).await;
+    ctx.shutdown().await;
+    listener.await.unwrap();
+    outcome
+}
+
+async fn run_read_consistency_example(cfg: Config) {
+    println!("\n=== Read Consistency Example (Strong, Cached, Stale) ===");
+    let start = Instant::now();
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 100)
+        .manager_delay(cfg.work_delay / 10)
+        .cached_reads(cfg.refresh_every)
+        .build()
+        .await;
+    let reader = ctx.reader();
+
+    // A steady stream of deposits keeps the manager's queue busy
+    let writer_tx = ctx.bank().clone();
+    let writer = tokio::spawn(async move {
+        for _ in 0..20 {
+            let _ = deposit(&writer_tx, "Alice", 1).await;
+        }
+    });
+
+    let levels = [
+        ReadConsistency::Strong,
+        ReadConsistency::Cached,
+        ReadConsistency::Stale(cfg.stale_after),
+    ];
+    for round in 0..cfg.read_rounds {
+        for consistency in levels {
+            let started = Instant::now();
+            match reader.balance("Alice", consistency).await {
+                Ok(balance) => log(start, &format!(
+                    "round {} {:<12} Balance: {:>3} in {:>10?}",
+                    round, format!("{:?}", consistency), balance, started.elapsed()
+                )),
+                Err(e) => log(start, &format!("round {} {:?} failed - {}", round, consistency, e)),
+            }
+        }
+        sleep(cfg.work_delay / 2).await;
+    }
+
+    writer.await.unwrap();
+    ctx.shutdown().await;
+}
+
+async fn run_snapshot_writer_example(cfg: Config) {
+    println!("\n=== Snapshot Writer Example (Serializing Off the Manager) ===");
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 100)
+        .account("Bob", 50)
+        .manager_delay(Duration::ZERO)
+        .build()
+        .await;
+    let tx = ctx.bank();
+
+    // Enough history that writing it out takes a while
+    for i in 0..20_000 {
+        deposit(tx, if i % 2 == 0 { "Alice" } else { "Bob" }, 1).await.unwrap();
+    }
+
+    let runs = [
+        (SnapshotMode::Inline, SnapshotFormat::Json),
+        (SnapshotMode::Background, SnapshotFormat::Json),
+        // Never compressed, then compressed past the default threshold
+        (SnapshotMode::Background, SnapshotFormat::Binary { compress_over: usize::MAX }),
+        (SnapshotMode::Background, SnapshotFormat::BINARY),
+    ];
+    for (run, (mode, format)) in runs.into_iter().enumerate() {
+        let path = std::env::temp_dir().join(format!("demo-ledger-snapshot-{}", run));
+        // Keep reading balances the whole time and note the slowest answer
+        let pinger_tx = tx.clone();
+        let pinger = tokio::spawn(async move {
+            let mut slowest = Duration::ZERO;
+            let until = Instant::now() + Duration::from_millis(300);
+            while Instant::now() < until {
+                let started = Instant::now();
+                balance(&pinger_tx, "Alice").await.unwrap();
+                slowest = slowest.max(started.elapsed());
+                sleep(Duration::from_millis(1)).await;
+            }
+            slowest
+        });
+
+        sleep(Duration::from_millis(20)).await;
+        let (resp_tx, resp_rx) = oneshot::channel();
+        tx.send(BankMessage::Snapshot { path: path.clone(), mode, format, respond_to: resp_tx }).await.unwrap();
+        let result = resp_rx.await.unwrap();
+        let slowest = pinger.await.unwrap();
+
+        match result {
+            Ok(report) => println!(
+                "{:?} {:?}: {} entries, {} bytes to {} in {:?} ({:.0} entries/s, manager busy {:?}) - slowest balance read {:?}",
+                mode,
+                format,
+                report.entries,
+                report.bytes,
+                report.path.display(),
+                report.total_time,
+                report.entries as f64 / report.total_time.as_secs_f64(),
+                report.manager_time,
+                slowest
+            ),
+            Err(e) => println!("{:?} {:?}: snapshot failed - {}", mode, format, e),
+        }
+
+        // Stream it back without holding every record at once
+        let reading = Instant::now();
+        let (mut balances, mut entries) = (0, 0);
+        let read = read_snapshot(&path, format).and_then(|records| {
+            for record in records {
+                match record? {
+                    Record::Balance { .. } => balances += 1,
+                    Record::Entry(_) => entries += 1,
+                }
+            }
+            Ok(())
+        });
+        match read {
+            Ok(()) => println!(
+                "  read back {} balances and {} entries in {:?} ({:.0} entries/s)",
+                balances,
+                entries,
+                reading.elapsed(),
+                entries as f64 / reading.elapsed().as_secs_f64()
+            ),
+            Err(e) => println!("  reading back failed - {}", e),
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+
+    ctx.shutdown().await;
+}
+
+async fn run_metadata_example(cfg: Config) -> Outcome {
+    println!("\n=== Account Metadata Example (Last-write-wins vs Merge) ===");
+    let start = Instant::now();
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 100)
+        .manager_delay(cfg.work_delay / 20)
+        .build()
+        .await;
+    let tx = ctx.bank();
+
+    define_metadata_key(tx, "nickname", MergePolicy::LastWriteWins).await.unwrap();
+    define_metadata_key(tx, "tags", MergePolicy::Merge).await.unwrap();
+
+    // Two devices edited Alice's notes while offline. The laptop's edits are
+    // older but reach the manager last.
+    let now = chrono::Local::now().timestamp_millis();
+    let phone = Stamp { millis: now - 1_000, writer: "phone".to_string() };
+    let laptop = Stamp { millis: now - 5_000, writer: "laptop".to_string() };
+    let writes = [
+        (&phone, "nickname", "Al"),
+        (&phone, "tags", "vip"),
+        (&laptop, "nickname", "Alice S."),
+        (&laptop, "tags", "travel"),
+    ];
+    for (stamp, key, value) in writes {
+        match set_metadata(tx, "Alice", key, value, stamp.clone()).await {
+            Ok(values) => log(start, &format!("{} set {} = {:?} -> now {:?}", stamp.writer, key, value, values)),
+            Err(e) => log(start, &format!("{} write to {} refused - {}", stamp.writer, key, e)),
+        }
+    }
+
+    // The nickname keeps the newer phone edit; the tags keep both
+    match metadata(tx, "Alice").await {
+        Ok(notes) => {
+            for (key, values) in notes {
+                log(start, &format!("Alice {} = {:?}", key, values));
+            }
+        },
+        Err(e) => log(start, &format!("Reading metadata failed - {}", e)),
+    }
+
+    let outcome = closing_books(ctx.bank()).await;
+    ctx.shutdown().await;
+    outcome
+}
+
+async fn run_savepoints_example(cfg: Config) -> Outcome {
+    println!("\n=== Savepoints Example (Partial Rollback in a Batch) ===");
+    let start = Instant::now();
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 100)
+        .account("Bob", 50)
+        .account("Carol", 0)
+        .manager_delay(cfg.work_delay / 20)
+        .build()
+        .await;
+    let tx = ctx.bank();
+
+    let credit = |account: &str, amount| BatchOp::Deposit { account: account.to_string(), amount };
+    let move_funds = |from: &str, to: &str, amount| BatchOp::Transfer { from: from.to_string(), to: to.to_string(), amount };
+
+    // The oversized payout and the deposit to an unknown account each fall
+    // back to the nearest savepoint; the rest of the batch still posts
+    let ops = [
+        credit("Alice", 10),
+        BatchOp::Savepoint("payroll".to_string()),
+        move_funds("Alice", "Bob", 30),
+        move_funds("Alice", "Carol", 500),
+        move_funds("Alice", "Carol", 20),
+        BatchOp::Savepoint("fees".to_string()),
+        credit("Bob", 5),
+        credit("Dave", 5),
+        BatchOp::Savepoint("bonus".to_string()),
+        credit("Carol", 15),
+        BatchOp::RollbackTo("bonus".to_string()),
+    ];
+    match batch(tx, &ops).await {
+        Ok(report) => {
+            for rollback in &report.rollbacks {
+                log(start, rollback);
+            }
+            log(start, &format!("Batch committed {} operation(s)", report.committed));
+        },
+        Err(e) => log(start, &format!("Batch rejected - {}", e)),
+    }
+
+    // Without a savepoint a single failure rejects the whole batch
+    let ops = [credit("Bob", 100), move_funds("Bob", "Carol", 1_000)];
+    match batch(tx, &ops).await {
+        Ok(report) => log(start, &format!("Batch committed {} operation(s)", report.committed)),
+        Err(e) => log(start, &format!("Batch without savepoints rejected - {}", e)),
+    }
+
+    let mut books: Vec<_> = balances(tx).await.unwrap().into_iter().collect();
+    books.sort();
+    for (account, balance) in books {
+        log(start, &format!("{}: {}", account, balance));
+    }
+
+    let outcome = closing_books(ctx.bank()).await;
+    ctx.shutdown().await;
+    outcome
+}
+
+async fn run_holds_example(cfg: Config) -> Outcome {
+    println!("\n=== Holds Example (Authorize, Capture, Void, Expire) ===");
+    let start = Instant::now();
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 100)
+        .account("Shop", 0)
+        .manager_delay(cfg.work_delay / 20)
+        .hold_expiry(cfg.sweep_every)
+        .build()
+        .await;
+    let tx = ctx.bank();
+    let mut expiries = subscribe(tx, "account.*.hold-expired").await.unwrap();
+
+    let report = |label: &str, balance: i32, available: i32| {
+        log(start, &format!("{}: Alice balance {}, available {}", label, balance, available));
+    };
+    let ttl = Duration::from_millis(200);
+
+    // Three holds against Alice: one captured, one voided, one left to lapse
+    let mut holds = vec![];
+    for amount in [30, 20, 40] {
+        match authorize(tx, "Alice", "Shop", amount, ttl).await {
+            Ok(hold) => holds.push(hold),
+            Err(e) => log(start, &format!("Authorizing {} failed - {}", amount, e)),
+        }
+    }
+    report("Authorized", balance(tx, "Alice").await.unwrap(), available_balance(tx, "Alice").await.unwrap());
+
+    // Only 10 is available, so a bigger hold or transfer is refused
+    if let Err(e) = authorize(tx, "Alice", "Shop", 25, ttl).await {
+        log(start, &format!("Authorizing 25 more refused - {}", e));
+    }
+
+    match capture(tx, holds[0]).await {
+        Ok(balance) => log(start, &format!("Captured hold {} - Alice balance {}", holds[0], balance)),
+        Err(e) => log(start, &format!("Capture failed - {}", e)),
+    }
+    match void(tx, holds[1]).await {
+        Ok(()) => log(start, &format!("Voided hold {}", holds[1])),
+        Err(e) => log(start, &format!("Void failed - {}", e)),
+    }
+    report("Settled two", balance(tx, "Alice").await.unwrap(), available_balance(tx, "Alice").await.unwrap());
+
+    // The last hold outlives its TTL and the expirer hands the funds back
+    if let Ok(Some(envelope)) = timeout(ttl * 2, expiries.recv()).await {
+        log(start, &format!("Event: {}", envelope.event));
+    }
+    if let Err(e) = capture(tx, holds[2]).await {
+        log(start, &format!("Capturing hold {} too late - {}", holds[2], e));
+    }
+    report("After expiry", balance(tx, "Alice").await.unwrap(), available_balance(tx, "Alice").await.unwrap());
+
+    drop(expiries);
+    let outcome = closing_books(ctx.bank()).await;
+    ctx.shutdown().await;
+    outcome
+}
+
+async fn run_multi_currency_example(cfg: Config) -> Outcome {
+    println!("\n=== Multi-currency Example (Per-currency Balances and Conversion) ===");
+    let start = Instant::now();
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 100)
+        .account("Bob", 50)
+        .manager_delay(cfg.work_delay / 20)
+        .build()
+        .await;
+    let tx = ctx.bank();
+    let mut events = subscribe(tx, "*").await.unwrap();
+
+    add_currency(tx, "Alice", Currency::EUR).await.unwrap();
+    add_currency(tx, "Bob", Currency::GBP).await.unwrap();
+    deposit_in(tx, "Alice", 80, Currency::EUR).await.unwrap();
+
+    // Bob holds no euros, so a plain euro transfer is refused...
+    if let Err(e) = transfer_in(tx, "Alice", "Bob", 20, Currency::EUR, None).await {
+        log(start, &format!("Sending Bob 20 EUR refused - {}", e));
+    }
+    // ...unless Alice asks for it to be converted into one of his currencies
+    for target in [Currency::USD, Currency::GBP] {
+        match transfer_in(tx, "Alice", "Bob", 20, Currency::EUR, Some(target)).await {
+            Ok(credited) => log(start, &format!("Sent Bob 20 EUR as {} {}", credited, target)),
+            Err(e) => log(start, &format!("Converting to {} failed - {}", target, e)),
+        }
+    }
+
+    for account in ["Alice", "Bob"] {
+        if let Ok(held) = holdings(tx, account).await {
+            let held: Vec<String> = held.iter().map(|(currency, amount)| format!("{} {}", amount, currency)).collect();
+            log(start, &format!("{} holds {}", account, held.join(", ")));
+        }
+    }
+    while let Ok(envelope) = events.try_recv() {
+        log(start, &format!("Event: {}", envelope.event));
+    }
+
+    drop(events);
+    let outcome = closing_books(ctx.bank()).await;
+    ctx.shutdown().await;
+    outcome
+}
+
+async fn run_clock_skew_example(cfg: Config) -> Outcome {
+    println!("\n=== Clock Skew Example (Hybrid Logical Clock Ordering) ===");
+    let start = Instant::now();
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 100)
+        .manager_delay(cfg.work_delay / 20)
+        .build()
+        .await;
+    let tx = ctx.bank();
+
+    // The ledger's wall clock is stepped back, then forward, between deposits
+    for jump in [0, -5, 2] {
+        if jump != 0 {
+            let reading = skew_clock(tx, chrono::Duration::seconds(jump)).await.unwrap();
+            log(start, &format!("Clock jumped {}s - hybrid clock reads {}", jump, reading));
+        }
+        for _ in 0..2 {
+            deposit(tx, "Alice", 10).await.unwrap();
+        }
+    }
+
+    // Wall-clock times go backwards across the jump; hybrid stamps never do
+    let entries = journal(tx, 6).await.unwrap();
+    for entry in &entries {
+        log(start, &format!("#{} wall {} hlc {}", entry.seq, entry.recorded_at.format("%H:%M:%S%.3f"), entry.stamp));
+    }
+    let wall_ordered = entries.windows(2).all(|pair| pair[0].recorded_at <= pair[1].recorded_at);
+    let stamps_ordered = entries.windows(2).all(|pair| pair[0].stamp < pair[1].stamp);
+    log(start, &format!("Ordered by wall clock: {}, by hybrid clock: {}", wall_ordered, stamps_ordered));
+    log(start, &format!("Invariant violations: {}", check_invariants(tx).await.unwrap().len()));
+
+    // An auditor whose clock runs 10s behind merges the ledger's stamps, so
+    // its notes still order after the entries they audit
+    let mut auditor = HybridClock::new();
+    auditor.skew_by(chrono::Duration::seconds(-10));
+    let own = auditor.now();
+    if let Some(last) = entries.last() {
+        let note = auditor.observe(last.stamp);
+        log(start, &format!("Auditor's clock reads {}, its note on #{} is stamped {}", own, last.seq, note));
+    }
+
+    let outcome = closing_books(ctx.bank()).await;
+    ctx.shutdown().await;
+    outcome
+}
+
+async fn run_hash_chain_example(cfg: Config) -> Outcome {
+    println!("\n=== Hash Chain Example (Tamper-evident Receipts) ===");
+    let start = Instant::now();
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 100)
+        .account("Bob", 50)
+        .manager_delay(cfg.work_delay / 20)
+        .build()
+        .await;
+    let tx = ctx.bank();
+
+    for (account, amount) in [("Alice", 10), ("Bob", 20), ("Alice", 30)] {
+        deposit(tx, account, amount).await.unwrap();
+    }
+    let entries = journal(tx, 5).await.unwrap();
+    for entry in &entries {
+        log(start, &format!("#{} {:.12} <- {:.12}  {}", entry.seq, entry.hash, entry.prev_hash, entry.memo));
+    }
+
+    // A receipt for Bob's deposit checks out against the current head...
+    let transaction = entries[entries.len() - 2].id;
+    match receipt(tx, transaction).await {
+        Ok(mut receipt) => {
+            log(start, &format!("Receipt for {} up to head {:.12}: valid = {}", transaction, receipt.head, receipt.verify()));
+
+            // ...but not once the entry it carries has been altered
+            receipt.entry.postings[1].amount = 2_000;
+            log(start, &format!("Same receipt claiming a deposit of 2000: valid = {}", receipt.verify()));
+        },
+        Err(e) => log(start, &format!("No receipt - {}", e)),
+    }
+
+    log(start, &format!("Invariant violations: {}", check_invariants(tx).await.unwrap().len()));
+    let outcome = closing_books(ctx.bank()).await;
+    ctx.shutdown().await;
+    outcome
+}
+
+async fn run_api_keys_example(cfg: Config) -> Outcome {
+    println!("\n=== API Keys Example (Scopes, Revocation and Usage) ===");
+    let start = Instant::now();
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 100)
+        .account("Bob", 50)
+        .manager_delay(cfg.work_delay / 20)
+        .api_keys()
+        .build()
+        .await;
+
+    // The back office may do anything; the kiosk may only read and deposit
+    // into Alice's account
+    let back_office = keys::create(ctx.keys(), "back-office", Scope {
+        operations: vec![Operation::Read, Operation::Deposit, Operation::Transfer],
+        accounts: None,
+    }).await.unwrap();
+    let kiosk_key = keys::create(ctx.keys(), "kiosk", Scope {
+        operations: vec![Operation::Read, Operation::Deposit],
+        accounts: Some(vec!["Alice".to_string()]),
+    }).await.unwrap();
+    let admin = ctx.keyed_client(&back_office);
+    let kiosk = ctx.keyed_client(&kiosk_key);
+
+    let report = |who: &str, what: &str, result: Result<i32, BankError>| match result {
+        Ok(balance) => log(start, &format!("{} {} - Balance: {}", who, what, balance)),
+        Err(e) => log(start, &format!("{} {} refused - {}", who, what, e)),
+    };
+    report("kiosk", "deposit 10 to Alice", kiosk.deposit("Alice", 10).await);
+    report("kiosk", "deposit 10 to Bob", kiosk.deposit("Bob", 10).await);
+    report("kiosk", "transfer 5 Alice -> Bob", kiosk.transfer("Alice", "Bob", 5).await);
+    report("back-office", "transfer 5 Alice -> Bob", admin.transfer("Alice", "Bob", 5).await);
+
+    // Revocation applies from the next request on
+    let revoked = keys::revoke(ctx.keys(), &kiosk_key).await.unwrap();
+    log(start, &format!("Revoked kiosk key: {}", revoked));
+    report("kiosk", "read Alice", kiosk.balance("Alice").await);
+
+    for (client, usage) in keys::usage(ctx.keys()).await.unwrap() {
+        log(start, &format!("{}: {} allowed, {} denied", client, usage.allowed, usage.denied));
+    }
+
+    drop((admin, kiosk));
+    let outcome = closing_books(ctx.bank()).await;
+    ctx.shutdown().await;
+    outcome
+}
+
+async fn run_payroll_example(cfg: Config) -> Outcome {
+    println!("\n=== Payroll Example (One Debit, Many Credits, Compensation) ===");
+    let start = Instant::now();
+    let ctx = AppContext::builder(&cfg)
+        .account("Employer", 500)
+        .account("Alice", 0)
+        .account("Bob", 0)
+        .account("Carol", 0)
+        .manager_delay(cfg.work_delay / 20)
+        .build()
+        .await;
+    let tx = ctx.bank();
+    close_account(tx, "Carol").await.unwrap();
+
+    // Carol's account is closed and Dave has none
+    let credits: Vec<(String, i32)> = [("Alice", 100), ("Bob", 120), ("Carol", 80), ("Dave", 50)]
+        .into_iter()
+        .map(|(payee, amount)| (payee.to_string(), amount))
+        .collect();
+
+    match payroll(tx, "Employer", &credits, PayrollMode::AllOrNothing).await {
+        Ok(report) => log(start, &format!("All-or-nothing run paid {} payee(s)", report.paid.len())),
+        Err(e) => log(start, &format!("All-or-nothing run rejected - {}", e)),
+    }
+
+    // Best effort pays who it can; the failed credits are refunded
+    match payroll(tx, "Employer", &credits, PayrollMode::BestEffort).await {
+        Ok(report) => {
+            for payment in &report.paid {
+                log(start, &format!("Paid {} {} (tx {})", payment.to, payment.amount, payment.transaction));
+            }
+            for (payee, amount, reason) in &report.failed {
+                log(start, &format!("Could not pay {} {} - {}", payee, amount, reason));
+            }
+            log(start, &format!("Refunded {} - Employer balance: {}", report.refunded, report.balance));
+        },
+        Err(e) => log(start, &format!("Best-effort run rejected - {}", e)),
+    }
+
+    for entry in journal(tx, 4).await.unwrap() {
+        log(start, &format!("#{} {}", entry.seq, entry.memo));
+    }
+    log(start, &format!("Invariant violations: {}", check_invariants(tx).await.unwrap().len()));
+
+    let outcome = closing_books(ctx.bank()).await;
+    ctx.shutdown().await;
+    outcome
+}
+
+async fn run_supervisor_example(cfg: Config) {
+    println!("\n=== Supervisor Example (Surviving a Manager Crash) ===");
+    let start = Instant::now();
+    let accounts = HashMap::from([("Alice".to_string(), 100)]);
+    let supervisor = Supervisor::start(accounts, cfg.work_delay / 4);
+    let handle = supervisor.handle(8, cfg.work_delay);
+
+    // Deposits keep coming while the manager is killed partway through
+    let client = handle.clone();
+    let delay = cfg.work_delay / 4;
+    let deposits = tokio::spawn(async move {
+        for i in 1..=8 {
+            match client.deposit("Alice", 10).await {
+                Ok(balance) => log(start, &format!("Deposit {} - Balance: {}", i, balance)),
+                Err(e) => log(start, &format!("Deposit {} failed - {}", i, e)),
+            }
+            sleep(delay).await;
+        }
+    });
+
+    sleep(cfg.work_delay).await;
+    log(start, "Killing the manager");
+    supervisor.kill_manager().await;
+    deposits.await.unwrap();
+
+    // The restarted manager began again from the opening balances
+    if let Ok(balance) = handle.balance("Alice").await {
+        log(start, &format!("Alice after the restart: {}", balance));
+    }
+
+    // A store call panicking takes the manager down as well, this time with
+    // a crash report
+    log(start, "Making the next store call panic");
+    chaos::panic_next(Subsystem::Store);
+    if let Err(e) = handle.deposit("Alice", 10).await {
+        log(start, &format!("Deposit during the panic failed - {}", e));
+    }
+    if let Ok(balance) = handle.balance("Alice").await {
+        log(start, &format!("Alice after the second restart: {}", balance));
+    }
+
+    let restarts = supervisor.shutdown().await;
+    log(start, &format!("Supervisor stopped after {} restart(s)", restarts.len()));
+    for restart in &restarts {
+        match &restart.crash {
+            Some(crash) => log(start, &format!(
+                "Restart #{}: {} ({}), requests {:?}, report {:?}",
+                restart.number, restart.reason, crash.message, crash.recent_requests, crash.path
+            )),
+            None => log(start, &format!("Restart #{}: {}", restart.number, restart.reason)),
+        }
+    }
+    if let Err(e) = handle.deposit("Alice", 10).await {
+        log(start, &format!("Deposit after shutdown - {}", e));
+    }
+}
+
+async fn run_reconcile_example(cfg: Config) {
+    println!("\n=== Reconciliation Example (Cache, Memory and Journal Compared) ===");
+    let start = Instant::now();
+    let accounts = ["Alice", "Bob", "Carol"];
+    let policy = ReconcilePolicy { every: cfg.reconcile_every, sample: 2, heal: true };
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 100)
+        .account("Bob", 50)
+        .account("Carol", 25)
+        .manager_delay(Duration::ZERO)
+        .cached_reads(cfg.refresh_every)
+        .reconcile(policy)
+        .build()
+        .await;
+    let tx = ctx.bank();
+    let reader = ctx.reader();
+
+    // Fill the cache, then change balances behind it without telling it
+    for account in accounts {
+        reader.balance(account, ReadConsistency::Cached).await.unwrap();
+    }
+    chaos::lose_invalidations(true);
+    deposit(tx, "Alice", 10).await.unwrap();
+    transfer(tx, "Bob", "Carol", 5).await.unwrap();
+    for account in accounts {
+        let cached = reader.balance(account, ReadConsistency::Cached).await.unwrap();
+        let strong = reader.balance(account, ReadConsistency::Strong).await.unwrap();
+        log(start, &format!("{:<6} cached {:>3}, manager {:>3}", account, cached, strong));
+    }
+
+    // Two accounts a round: every account is checked within two rounds
+    sleep(cfg.reconcile_every * 3).await;
+    chaos::lose_invalidations(false);
+    for divergence in reader.divergences() {
+        log(start, &format!("Reconciler: {}", divergence));
+    }
+    for account in accounts {
+        let cached = reader.balance(account, ReadConsistency::Cached).await.unwrap();
+        log(start, &format!("{:<6} cached {:>3} after healing", account, cached));
+    }
+
+    ctx.shutdown().await;
+}
+
+// Polls the payee's home region until the balance moves off `before`
+async fn time_until_visible(topology: &Topology, account: &str, before: i32, sent: Instant) -> Duration {
+    let home = topology.home(account).expect("account has a home region");
+    while topology.balance(home, account).await == Ok(before) {
+        sleep(Duration::from_millis(1)).await;
+    }
+    sent.elapsed()
+}
+
+async fn run_regions_example(cfg: Config) {
+    println!("\n=== Multi-region Example (Async Replication vs Forwarding) ===");
+    println!("Latency east -> west {:?}, west -> east {:?}", cfg.east_west, cfg.west_east);
+    let start = Instant::now();
+    let topology = Topology::builder()
+        .region("east", &[("Alice", 100), ("Bob", 50)])
+        .region("west", &[("Carol", 100), ("Dave", 0), ("Erin", 0)])
+        .link("east", "west", cfg.east_west)
+        .link("west", "east", cfg.west_east)
+        .build(Duration::ZERO)
+        .await;
+    let total = topology.total().await.unwrap();
+
+    // Each direction, replicated and forwarded: the payer's answer time
+    // against how long until the payee's region shows the money
+    let runs = [("Alice", "Dave"), ("Carol", "Bob")];
+    for (from, to) in runs {
+        for mode in [CrossRegion::Async, CrossRegion::Forward] {
+            let home = topology.home(to).unwrap();
+            let before = topology.balance(home, to).await.unwrap();
+            let sent = Instant::now();
+            topology.transfer(from, to, 10, mode).await.unwrap();
+            let answered = sent.elapsed();
+            let visible = time_until_visible(&topology, to, before, sent).await;
+            log(start, &format!(
+                "{} -> {} {:<8} answered in {:>10?}, visible in {} after {:>10?}",
+                from, to, format!("{:?}", mode), answered, home, visible
+            ));
+        }
+    }
+
+    // Reading another region's account costs a round trip
+    let started = Instant::now();
+    let dave = topology.balance("east", "Dave").await.unwrap();
+    log(start, &format!("Dave read from east: {} in {:?}", dave, started.elapsed()));
+
+    // A replicated credit refused on arrival is refunded; until then the
+    // payer is short and the money is in neither region
+    close_account(topology.bank("west"), "Erin").await.unwrap();
+    topology.transfer("Alice", "Erin", 10, CrossRegion::Async).await.unwrap();
+    log(start, &format!("Sent 10 to closed Erin: total {} of {}", topology.total().await.unwrap(), total));
+    sleep((cfg.east_west + cfg.west_east) * 2).await;
+    let alice = topology.balance("east", "Alice").await.unwrap();
+    log(start, &format!("After the refund: Alice {}, total {} of {}", alice, topology.total().await.unwrap(), total));
+
+    let refused = topology.transfer("Alice", "Erin", 10, CrossRegion::Forward).await;
+    log(start, &format!("Forwarded to closed Erin: {:?}", refused));
+
+    topology.shutdown().await;
+}
+
+async fn run_wait_for_balance_example(cfg: Config) -> Outcome {
+    println!("\n=== Wait-for-balance Example (Blocking on Incoming Funds) ===");
+    let start = Instant::now();
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 100)
+        .account("Bob", 0)
+        .manager_delay(Duration::ZERO)
+        .build()
+        .await;
+    let tx = ctx.bank();
+
+    // Bob's supplier ships once Bob can pay 50; the warehouse gives up on
+    // 500 after a while. Neither sends anything until the manager answers.
+    let mut waits = vec![];
+    for (who, at_least, patience) in [("supplier", 50, cfg.work_delay * 10), ("warehouse", 500, cfg.work_delay * 3)] {
+        let tx = tx.clone();
+        waits.push(tokio::spawn(async move {
+            let result = wait_for_balance(&tx, "Bob", at_least, patience).await;
+            log(start, &format!("{} waiting for Bob >= {}: {:?}", who, at_least, result));
+        }));
+    }
+
+    // Funds arrive a little at a time
+    for _ in 0..3 {
+        sleep(cfg.work_delay).await;
+        let balance = transfer(tx, "Alice", "Bob", 20).await.unwrap();
+        log(start, &format!("Alice sent Bob 20, Alice now {}", balance));
+    }
+    for wait in waits {
+        wait.await.unwrap();
+    }
+
+    // Already there: answered at once
+    let result = wait_for_balance(tx, "Bob", 10, cfg.work_delay).await;
+    log(start, &format!("Waiting for Bob >= 10: {:?}", result));
+
+    let outcome = closing_books(ctx.bank()).await;
+    ctx.shutdown().await;
+    outcome
+}
+
+async fn run_shortest_first_example(cfg: Config) {
+    println!("\n=== Shortest-job-first Example (Reordering the Manager's Mailbox) ===");
+    let policies = [
+        SchedulePolicy::Fifo,
+        SchedulePolicy::ShortestFirst { lookahead: 32, max_wait: cfg.work_delay },
+    ];
+    for policy in policies {
+        let (tx, rx) = channel_with_metrics("bank", 64);
+        let accounts = HashMap::from([("Alice".to_string(), 1000), ("Bob".to_string(), 0)]);
+        let manager = tokio::spawn(run_bank_manager_scheduled(rx, accounts, cfg.work_delay / 20, policy));
+
+        // One burst of bulk batches, single deposits and reads
+        let mut requests = JoinSet::new();
+        for i in 0..30 {
+            let tx = tx.clone();
+            requests.spawn(async move {
+                let started = Instant::now();
+                let class = match i % 3 {
+                    0 => {
+                        let ops: Vec<BatchOp> =
+                            (0..10).map(|_| BatchOp::Deposit { account: "Bob".to_string(), amount: 1 }).collect();
+                        batch(&tx, &ops).await.unwrap();
+                        "bulk"
+                    },
+                    1 => {
+                        deposit(&tx, "Bob", 1).await.unwrap();
+                        "write"
+                    },
+                    _ => {
+                        balance(&tx, "Alice").await.unwrap();
+                        "read"
+                    },
+                };
+                (class, started.elapsed())
+            });
+        }
+        let mut latencies: BTreeMap<&str, Vec<Duration>> = BTreeMap::new();
+        while let Some(result) = requests.join_next().await {
+            let (class, latency) = result.unwrap();
+            latencies.entry(class).or_default().push(latency);
+        }
+        drop(tx);
+        manager.await.unwrap();
+
+        println!("{:?}", policy);
+        for (class, latencies) in &latencies {
+            let total: Duration = latencies.iter().sum();
+            let max = latencies.iter().max().copied().unwrap_or_default();
+            println!("  {:<6} mean {:>10?}  max {:>10?}", class, total / latencies.len() as u32, max);
+        }
+        let stats = schedule::take_stats();
+        if stats.picked > 0 {
+            println!(
+                "  {} of {} run ahead of earlier requests, {} promoted after waiting, longest held {:?}",
+                stats.reordered, stats.picked, stats.promoted, stats.max_held
+            );
+        }
+    }
+}
+
+async fn run_blocking_client_example(cfg: Config) {
+    println!("\n=== Blocking Client Example (Synchronous Facade) ===");
+    let delay = cfg.work_delay / 10;
+
+    // A plain thread standing in for a CLI tool or script: no async anywhere
+    tokio::task::spawn_blocking(move || {
+        let accounts = HashMap::from([("Alice".to_string(), 100), ("Bob".to_string(), 50)]);
+        let bank = BlockingBank::start(accounts, delay).expect("failed to start the runtime");
+
+        println!("Deposit 25 to Alice: {:?}", bank.deposit("Alice", 25));
+        println!("Transfer 40 Alice -> Bob: {:?}", bank.transfer("Alice", "Bob", 40));
+        println!("Transfer 500 Bob -> Alice: {:?}", bank.transfer("Bob", "Alice", 500));
+        println!("Balance of Carol: {:?}", bank.balance("Carol"));
+        let mut balances: Vec<(String, i32)> = bank.balances().unwrap().into_iter().collect();
+        balances.sort();
+        println!("Balances: {:?}", balances);
+
+        bank.shutdown();
+    })
+    .await
+    .unwrap();
+}
+
+// Waits for the follower to apply the leader's latest entry
+async fn caught_up(follower: &Follower, leader: &MeteredSender<BankMessage>) {
+    let head = journal(leader, 1).await.unwrap().last().map_or(0, |entry| entry.seq);
+    while follower.stats().applied < head {
+        sleep(Duration::from_millis(1)).await;
+    }
+}
+
+fn describe_follower(name: &str, follower: &Follower) -> String {
+    let stats = follower.stats();
+    format!(
+        "{} follower at entry {} ({} behind, {:?} after the leader, {} reconnects, {} bootstraps), Alice {:?}, Bob {:?}",
+        name,
+        stats.applied,
+        stats.behind,
+        stats.delay,
+        stats.reconnects,
+        stats.bootstraps,
+        follower.balance("Alice"),
+        follower.balance("Bob")
+    )
+}
+
+async fn run_oplog_example(cfg: Config) {
+    println!("\n=== Oplog Shipping Example (Leader and Read Replicas) ===");
+    let start = Instant::now();
+    let retry = Duration::from_millis(50);
+    let accounts = HashMap::from([("Alice".to_string(), 100), ("Bob".to_string(), 50)]);
+    let (tx, rx) = channel_with_metrics("bank", 32);
+    let manager = tokio::spawn(run_bank_manager(rx, accounts, cfg.work_delay / 10));
+
+    // Connected from the start: each commit is shipped as it happens
+    let early = Follower::spawn(tx.clone(), retry);
+    for _ in 0..20 {
+        transfer(&tx, "Alice", "Bob", 1).await.unwrap();
+    }
+    caught_up(&early, &tx).await;
+    log(start, &describe_follower("Early", &early));
+
+    // Joins late, and is sent the journal so far before the live entries
+    let late = Follower::spawn(tx.clone(), retry);
+    deposit(&tx, "Alice", 5).await.unwrap();
+    caught_up(&late, &tx).await;
+    log(start, &describe_follower("Late", &late));
+
+    // Once older entries are rolled into a summary, a new follower starts
+    // from the summary instead
+    let (resp_tx, resp_rx) = oneshot::channel();
+    let policy = RetentionPolicy { max_entries: Some(5), ..RetentionPolicy::default() };
+    tx.send(BankMessage::Compact { policy, respond_to: resp_tx }).await.unwrap();
+    let compaction = resp_rx.await.unwrap();
+    log(start, &format!("Leader compacted {} entries into a summary", compaction.compacted));
+    let fresh = Follower::spawn(tx.clone(), retry);
+    transfer(&tx, "Bob", "Alice", 3).await.unwrap();
+    caught_up(&fresh, &tx).await;
+    log(start, &describe_follower("Fresh", &fresh));
+
+    let leader = balances(&tx).await.unwrap();
+    for (name, follower) in [("Early", &early), ("Late", &late), ("Fresh", &fresh)] {
+        caught_up(follower, &tx).await;
+        log(start, &format!("{} follower matches the leader: {}", name, follower.balances() == leader));
+    }
+
+    // Followers hold senders of their own, so they go before the manager
+    for follower in [early, late, fresh] {
+        follower.shutdown();
+    }
+    drop(tx);
+    manager.await.unwrap();
+}
+
+async fn run_fees_example(cfg: Config) -> Outcome {
+    println!("\n=== Transfer Fees Example (Flat, Percentage and Tiered) ===");
+    let start = Instant::now();
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 2000)
+        .account("Bob", 0)
+        .account("Fees", 0)
+        .manager_delay(Duration::ZERO)
+        .build()
+        .await;
+    let tx = ctx.bank();
+
+    // 1 flat, plus 1% of the first 100, 0.5% up to 1000 and 0.25% above
+    let schedule = FeeSchedule {
+        account: "Fees".into(),
+        rules: vec![FeeRule::Flat(1), FeeRule::Tiered(vec![(Some(100), 100), (Some(1000), 50), (None, 25)])],
+    };
+    set_fee_schedule(tx, Some(schedule)).await.unwrap();
+    let negative = FeeSchedule { account: "Fees".into(), rules: vec![FeeRule::Flat(-5)] };
+    log(start, &format!("Negative fee schedule: {:?}", set_fee_schedule(tx, Some(negative)).await));
+
+    for amount in [50, 500, 1200, 500] {
+        match transfer_itemized(tx, "Alice", "Bob", amount).await {
+            Ok(report) => log(start, &format!("Sent {:>4} to Bob, fee {}, Alice has {}", amount, report.fee, report.balance)),
+            Err(e) => log(start, &format!("Sent {:>4} to Bob: {}", amount, e)),
+        }
+    }
+    // Plain transfers are charged the same way
+    let bob_balance = transfer(tx, "Bob", "Alice", 100).await.unwrap();
+    log(start, &format!("Bob sent 100 back and has {} left", bob_balance));
+
+    if let Some(entry) = journal(tx, 1).await.unwrap().pop() {
+        log(start, &format!("Last journal entry: {} ({} postings)", entry.memo, entry.postings.len()));
+    }
+    set_fee_schedule(tx, None).await.unwrap();
+    let report = transfer_itemized(tx, "Bob", "Alice", 10).await.unwrap();
+    log(start, &format!("Without a schedule: fee {}", report.fee));
+    log(start, &format!("Collected in fees: {}", balance(tx, "Fees").await.unwrap()));
+    log(start, &format!("Books: {:?}", check_invariants(tx).await.unwrap()));
+
+    let outcome = closing_books(ctx.bank()).await;
+    ctx.shutdown().await;
+    outcome
+}
+
+// p50 and p99 of some latencies, in place
+fn read_percentiles(latencies: &mut [Duration]) -> (Duration, Duration) {
+    latencies.sort();
+    let at = |percent: usize| latencies.get((latencies.len().max(1) - 1) * percent / 100).copied().unwrap_or_default();
+    (at(50), at(99))
+}
+
+async fn run_hedging_example(cfg: Config) {
+    println!("\n=== Hedged Reads Example (Tail Latency Across Replicas) ===");
+    let start = Instant::now();
+    let stall = (cfg.work_delay / 4).max(Duration::from_millis(1));
+    let mut replicas = vec![];
+    let mut managers = vec![];
+    for _ in 0..3 {
+        let accounts = HashMap::from([("Alice".to_string(), 100), ("Scratch".to_string(), 0)]);
+        let (tx, rx) = channel_with_metrics("bank", 32);
+        managers.push(tokio::spawn(run_bank_manager(rx, accounts, stall)));
+        replicas.push(tx);
+    }
+
+    // Every so often one replica takes a deposit and reads sent to it wait
+    // behind the work: rare enough to stay above the 95th percentile
+    let writers: Vec<MeteredSender<BankMessage>> = replicas.clone();
+    let writer = tokio::spawn(async move {
+        for replica in writers.iter().cycle() {
+            sleep(stall * 10).await;
+            deposit(replica, "Scratch", 1).await.unwrap();
+        }
+    });
+
+    let mut plain = vec![];
+    for read in 0..400 {
+        let began = Instant::now();
+        balance(&replicas[read % replicas.len()], "Alice").await.unwrap();
+        plain.push(began.elapsed());
+        sleep(stall / 20).await;
+    }
+    let (p50, p99) = read_percentiles(&mut plain);
+    log(start, &format!("One replica per read: p50 {:?}, p99 {:?}", p50, p99));
+
+    let reader = HedgedReader::new(replicas.clone(), stall / 10);
+    let mut hedged = vec![];
+    for _ in 0..400 {
+        let began = Instant::now();
+        reader.balance("Alice").await.unwrap();
+        hedged.push(began.elapsed());
+        sleep(stall / 20).await;
+    }
+    let (p50, p99) = read_percentiles(&mut hedged);
+    log(start, &format!("Hedged after {:?}: p50 {:?}, p99 {:?}", reader.hedge_delay(), p50, p99));
+    let stats = reader.stats();
+    log(
+        start,
+        &format!(
+            "{} of {} reads hedged ({:.1}%): {} answered first, {} wasted",
+            stats.hedged,
+            stats.reads,
+            stats.hedge_rate() * 100.0,
+            stats.won,
+            stats.wasted
+        ),
+    );
+
+    writer.abort();
+    let _ = writer.await;
+    drop(reader);
+    drop(replicas);
+    for manager in managers {
+        manager.await.unwrap();
+    }
+}
+
+pub fn scenarios() -> Vec<Box<dyn Scenario>> {
+    vec![
+        scenario("read-only", "Switching every manager into read-only mode at runtime", run_read_only_example),
+        scenario("watchdog", "Background invariant checks raising alarm events", run_watchdog_example),
+        scenario("bulk-import", "Importing opening balances with validation and a per-row report", run_bulk_import_example),
+        scenario("quota", "Per-client sliding-window quotas checked before the manager", run_quota_example),
+        scenario("retention", "Bounded journal with background compaction into summaries", run_retention_example),
+        checked_scenario("soft-delete", "Closing, restoring and purging accounts with a grace window", run_soft_delete_example,
+            |_| Postcondition::books(&[("Alice", 100), ("Bob", 0)])),
+        scenario("read-consistency", "Trading freshness for latency on balance reads", run_read_consistency_example),
+        scenario("snapshot-writer", "Writing a snapshot without stalling the manager", run_snapshot_writer_example),
+        checked_scenario("metadata", "Concurrent account notes under last-write-wins and merge policies", run_metadata_example,
+            |_| Postcondition::books(&[("Alice", 100)])),
+        checked_scenario("savepoints", "Batched operations rolling back to named savepoints", run_savepoints_example,
+            |_| Postcondition::books(&[("Alice", 90), ("Bob", 50), ("Carol", 20)])),
+        checked_scenario("holds", "Authorizing payments, then capturing, voiding or letting them expire", run_holds_example,
+            |_| Postcondition::books(&[("Alice", 70), ("Shop", 30)])),
+        checked_scenario("multi-currency", "Balances in several currencies with explicit conversion", run_multi_currency_example,
+            |_| Postcondition::books(&[("Alice", 100), ("Bob", 71)])),
+        checked_scenario("clock-skew", "Journal order kept by a hybrid logical clock while the wall clock jumps", run_clock_skew_example,
+            |_| Postcondition::books(&[("Alice", 160)])),
+        checked_scenario("hash-chain", "Hash-chained journal entries and verifiable receipts", run_hash_chain_example,
+            |_| Postcondition::books(&[("Alice", 140), ("Bob", 70)])),
+        checked_scenario("api-keys", "Scoped, revocable API keys checked before the manager", run_api_keys_example,
+            |_| Postcondition::books(&[("Alice", 105), ("Bob", 55)])),
+        checked_scenario("payroll", "One debit, many credits, all-or-nothing or best effort with refunds", run_payroll_example,
+            |_| Postcondition::books(&[("Employer", 280), ("Alice", 100), ("Bob", 120)])),
+        scenario("supervisor", "Clients riding out a manager crash and restart", run_supervisor_example),
+        scenario("reconcile", "Sampling accounts for cache, memory and journal divergences", run_reconcile_example),
+        scenario("regions", "Two regions with asymmetric latency: replicated vs forwarded transfers", run_regions_example),
+        checked_scenario("wait-for-balance", "Blocking on incoming funds with manager-held waiters", run_wait_for_balance_example,
+            |_| Postcondition::books(&[("Alice", 40), ("Bob", 60)])),
+        scenario("shortest-first", "Reordering the manager's mailbox by estimated cost", run_shortest_first_example),
+        scenario("blocking-client", "Calling the bank from plain synchronous code", run_blocking_client_example),
+        scenario("oplog", "Leader streaming committed entries to read-replica followers", run_oplog_example),
+        checked_scenario("fees", "Flat, percentage and tiered fees charged on transfers", run_fees_example,
+            |_| Postcondition::books(&[("Alice", 346), ("Bob", 1638), ("Fees", 16)])),
+        scenario("hedging", "Hedging slow balance reads to a second replica", run_hedging_example),
+    ]
+}
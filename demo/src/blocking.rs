@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+use std::io;
+use tokio::runtime::{Builder, Runtime};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+
+use crate::bank::{run_bank_manager, BankError, BankEvent, BankMessage};
+use crate::client;
+use crate::metrics::{channel_with_metrics, MeteredSender};
+use crate::pubsub::Envelope;
+
+// Synchronous front for callers without a runtime of their own: CLI tools,
+// plain tests, scripts. It owns a small runtime the manager runs on, and
+// every call blocks the calling thread until the manager answers. Calling
+// it from inside an async task panics, as any `block_on` there would.
+//
+//     let bank = BlockingBank::start(accounts, Duration::ZERO)?;
+//     bank.deposit("Alice", 10)?;
+pub struct BlockingBank {
+    runtime: Runtime,
+    tx: MeteredSender<BankMessage>,
+    manager: JoinHandle<()>,
+}
+
+impl BlockingBank {
+    pub fn start(accounts: HashMap<String, i32>, delay: Duration) -> io::Result<Self> {
+        let runtime = Builder::new_multi_thread().worker_threads(1).enable_all().build()?;
+        let (tx, rx) = channel_with_metrics("bank", 32);
+        let manager = runtime.spawn(run_bank_manager(rx, accounts, delay));
+        Ok(BlockingBank { runtime, tx, manager })
+    }
+
+    pub fn deposit(&self, account: &str, amount: i32) -> Result<i32, BankError> {
+        self.runtime.block_on(client::deposit(&self.tx, account, amount))
+    }
+
+    pub fn transfer(&self, from: &str, to: &str, amount: i32) -> Result<i32, BankError> {
+        self.runtime.block_on(client::transfer(&self.tx, from, to, amount))
+    }
+
+    pub fn balance(&self, account: &str) -> Result<i32, BankError> {
+        self.runtime.block_on(client::balance(&self.tx, account))
+    }
+
+    pub fn balances(&self) -> Result<HashMap<String, i32>, BankError> {
+        self.runtime.block_on(client::balances(&self.tx))
+    }
+
+    // Events are there to `try_recv` without blocking; the manager drops
+    // them for a subscriber that falls too far behind
+    pub fn subscribe(&self, pattern: &str) -> Result<mpsc::Receiver<Envelope<BankEvent>>, BankError> {
+        self.runtime.block_on(client::subscribe(&self.tx, pattern))
+    }
+
+    // Stop the manager, letting it check the books, then the runtime
+    pub fn shutdown(self) {
+        let BlockingBank { runtime, tx, manager } = self;
+        drop(tx);
+        let _ = runtime.block_on(manager);
+    }
+}
@@ -0,0 +1,76 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tokio::time::{sleep, Duration, Instant};
+
+use crate::chaos::{self, Subsystem};
+
+// With `--calibrate` every simulated delay prints what it actually took
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+// How late each simulated delay finished, since the last report
+static OVERSHOOTS: Mutex<Vec<Duration>> = Mutex::new(Vec::new());
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+// Stand-in for real work: sleeps for `expected` and records how much later
+// than that the task actually resumed - timer granularity plus however long
+// the scheduler took to poll it again. Latency injected into the store comes
+// on top and isn't counted as overshoot.
+pub async fn simulate_work(expected: Duration) {
+    chaos::inject(Subsystem::Store).await;
+    let started = Instant::now();
+    sleep(expected).await;
+    let observed = started.elapsed();
+
+    OVERSHOOTS.lock().unwrap().push(observed.saturating_sub(expected));
+    if ENABLED.load(Ordering::Relaxed) && !expected.is_zero() {
+        println!("         (expected {:?}, observed {:?})", expected, observed);
+    }
+}
+
+// Baseline on an idle runtime, so the report can separate the timer's own
+// granularity from delays caused by the scenario's load
+pub async fn probe() {
+    let mut overshoots = vec![];
+    for _ in 0..20 {
+        let started = Instant::now();
+        sleep(Duration::from_millis(5)).await;
+        overshoots.push(started.elapsed().saturating_sub(Duration::from_millis(5)));
+    }
+    print_summary("Idle baseline (20 x 5ms)", overshoots);
+}
+
+// Summarize every delay simulated since the last report and start over
+pub fn print_report() {
+    let overshoots = std::mem::take(&mut *OVERSHOOTS.lock().unwrap());
+    print_summary("Simulated work", overshoots);
+}
+
+fn print_summary(title: &str, mut overshoots: Vec<Duration>) {
+    if overshoots.is_empty() {
+        return;
+    }
+    overshoots.sort();
+
+    let count = overshoots.len();
+    let mean = overshoots.iter().sum::<Duration>() / count as u32;
+    let variance = overshoots
+        .iter()
+        .map(|overshoot| (overshoot.as_secs_f64() - mean.as_secs_f64()).powi(2))
+        .sum::<f64>()
+        / count as f64;
+    let percentile = |p: usize| overshoots[(count - 1) * p / 100];
+
+    println!("\n=== Calibration: {} ===", title);
+    println!(
+        "{} delays, overshoot mean {:?}, p50 {:?}, p99 {:?}, max {:?}, jitter (std dev) {:?}",
+        count,
+        mean,
+        percentile(50),
+        percentile(99),
+        overshoots[count - 1],
+        Duration::from_secs_f64(variance.sqrt())
+    );
+}
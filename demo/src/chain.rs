@@ -0,0 +1,85 @@
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+use crate::account_id::{AccountId, AccountName};
+use crate::ledger::{JournalEntry, Side};
+
+// SHA-256 digest linking journal entries into a tamper-evident chain
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ChainHash([u8; 32]);
+
+impl ChainHash {
+    // What the first entry in the journal links back to
+    pub const GENESIS: ChainHash = ChainHash([0; 32]);
+
+    // An entry's link: its own content hashed together with the previous link,
+    // so changing any earlier entry changes every link after it
+    pub fn link(previous: &ChainHash, body: &ChainHash) -> ChainHash {
+        let mut hasher = Sha256::new();
+        hasher.update(previous.0);
+        hasher.update(body.0);
+        ChainHash(hasher.finalize().into())
+    }
+
+    // Hash of everything recorded in an entry except its links. Accounts are
+    // hashed as displayed, so a named account hashes as its name.
+    pub fn body<A: AccountId>(entry: &JournalEntry<A>) -> ChainHash {
+        let mut hasher = Sha256::new();
+        hasher.update(entry.seq.to_be_bytes());
+        hasher.update(entry.id.to_string());
+        hasher.update(format!("{:?}", entry.kind));
+        hasher.update(entry.recorded_at.to_rfc3339());
+        hasher.update(entry.stamp.millis.to_be_bytes());
+        hasher.update(entry.stamp.logical.to_be_bytes());
+        hasher.update(&entry.memo);
+        for posting in &entry.postings {
+            hasher.update(posting.account.to_string());
+            hasher.update(posting.currency.to_string());
+            hasher.update(match posting.side {
+                Side::Debit => b"Dr",
+                Side::Credit => b"Cr",
+            });
+            hasher.update(posting.amount.to_be_bytes());
+        }
+        ChainHash(hasher.finalize().into())
+    }
+}
+
+// Full hex digest with `{}`, or a short prefix with `{:.8}`
+impl fmt::Display for ChainHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = f.precision().unwrap_or(64).min(64);
+        let hex: String = self.0.iter().map(|byte| format!("{:02x}", byte)).collect();
+        f.write_str(&hex[..digits])
+    }
+}
+
+impl fmt::Debug for ChainHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ChainHash({:.16})", self)
+    }
+}
+
+// Proof that an entry is part of the journal: the entry, the body hash of
+// every entry after it, and the newest link. Anyone who knows the head link
+// can check it without seeing the rest of the journal.
+#[derive(Debug, Clone)]
+pub struct Receipt<A: AccountId = AccountName> {
+    pub entry: JournalEntry<A>,
+    pub later: Vec<ChainHash>,
+    pub head: ChainHash,
+}
+
+impl<A: AccountId> Receipt<A> {
+    // Recompute the links from the entry to the head
+    pub fn verify(&self) -> bool {
+        let mut link = ChainHash::link(&self.entry.prev_hash, &ChainHash::body(&self.entry));
+        if link != self.entry.hash {
+            return false;
+        }
+        for body in &self.later {
+            link = ChainHash::link(&link, body);
+        }
+        link == self.head
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio::time::{sleep, Duration};
+
+// Parts of the system faults can be aimed at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Subsystem {
+    // The simulated work behind every deposit, whichever design runs it
+    Store,
+    // Sends on metered channels
+    Channel,
+    // Lookups in the client-side balance cache
+    Cache,
+}
+
+// Extra latency per subsystem: a fixed delay plus up to `jitter` more
+static LATENCY: Mutex<BTreeMap<Subsystem, (Duration, Duration)>> = Mutex::new(BTreeMap::new());
+
+// Subsystems whose next operation panics
+static PANIC_NEXT: Mutex<BTreeSet<Subsystem>> = Mutex::new(BTreeSet::new());
+
+static CHANNELS_FULL: AtomicBool = AtomicBool::new(false);
+static CACHE_MISSES: AtomicBool = AtomicBool::new(false);
+static LOST_INVALIDATIONS: AtomicBool = AtomicBool::new(false);
+
+// Faults switched on for a whole run, e.g. by a preset
+#[derive(Debug, Clone, Default)]
+pub struct ChaosSettings {
+    // Subsystem, fixed delay and jitter
+    pub latency: Vec<(Subsystem, Duration, Duration)>,
+    pub channels_full: bool,
+    pub cache_misses: bool,
+}
+
+impl ChaosSettings {
+    pub fn apply(&self) {
+        for &(subsystem, delay, jitter) in &self.latency {
+            slow(subsystem, delay, jitter);
+        }
+        fill_channels(self.channels_full);
+        force_cache_misses(self.cache_misses);
+    }
+}
+
+// Slow down every operation in `subsystem` from now on
+pub fn slow(subsystem: Subsystem, delay: Duration, jitter: Duration) {
+    LATENCY.lock().unwrap().insert(subsystem, (delay, jitter));
+}
+
+// Make the next operation in `subsystem` panic, e.g. to crash the task
+// running a store call
+pub fn panic_next(subsystem: Subsystem) {
+    PANIC_NEXT.lock().unwrap().insert(subsystem);
+}
+
+// Make non-blocking sends report every metered channel as full
+pub fn fill_channels(full: bool) {
+    CHANNELS_FULL.store(full, Ordering::Relaxed);
+}
+
+// Make every balance cache lookup miss
+pub fn force_cache_misses(forced: bool) {
+    CACHE_MISSES.store(forced, Ordering::Relaxed);
+}
+
+// Make the balance cache ignore the events that should invalidate it, as
+// if its subscription had dropped them
+pub fn lose_invalidations(lost: bool) {
+    LOST_INVALIDATIONS.store(lost, Ordering::Relaxed);
+}
+
+// Back to normal operation
+pub fn reset() {
+    LATENCY.lock().unwrap().clear();
+    PANIC_NEXT.lock().unwrap().clear();
+    fill_channels(false);
+    force_cache_misses(false);
+    lose_invalidations(false);
+}
+
+pub fn channels_full() -> bool {
+    CHANNELS_FULL.load(Ordering::Relaxed)
+}
+
+pub fn cache_misses_forced() -> bool {
+    CACHE_MISSES.load(Ordering::Relaxed)
+}
+
+pub fn invalidations_lost() -> bool {
+    LOST_INVALIDATIONS.load(Ordering::Relaxed)
+}
+
+// Called by each subsystem before it does its work
+pub async fn inject(subsystem: Subsystem) {
+    if PANIC_NEXT.lock().unwrap().remove(&subsystem) {
+        panic!("chaos: injected {:?} failure", subsystem);
+    }
+    let latency = LATENCY.lock().unwrap().get(&subsystem).copied();
+    if let Some((delay, jitter)) = latency {
+        sleep(delay + jitter.mul_f64(next_fraction())).await;
+    }
+}
+
+// Pseudo-random number in [0, 1) from a splitmix64 sequence; jitter doesn't
+// need anything better
+fn next_fraction() -> f64 {
+    static STATE: AtomicU64 = AtomicU64::new(0x9E37_79B9_7F4A_7C15);
+    let mut z = STATE.fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
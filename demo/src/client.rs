@@ -0,0 +1,176 @@
+use std::collections::{BTreeMap, HashMap};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{timeout_at, Duration, Instant};
+
+use crate::bank::{BankError, BankEvent, BankMessage, BatchOp, BatchReport, TransferReport};
+use crate::chain::Receipt;
+use crate::clock::Timestamp;
+use crate::currency::Currency;
+use crate::fees::FeeSchedule;
+use crate::ids::Id;
+use crate::ledger::{JournalEntry, PayrollMode, PayrollReport, Violation};
+use crate::metadata::{MergePolicy, Stamp};
+use crate::metrics::MeteredSender;
+use crate::oplog::Shipment;
+use crate::pubsub::Envelope;
+
+// Generates a client function for a request/response `BankMessage` variant:
+//
+//     pub fn balance(account: &str) -> Result<i32, BankError> = Balance;
+//
+// becomes an `async fn balance(tx, account)` that sends
+// `BankMessage::Balance { account: account.to_owned(), respond_to }` and
+// waits for the reply. Parameters are converted with `to_owned`, so borrowed
+// forms like `&str` can stand in for the variant's owned field types.
+//
+// Only the client side is generated. A new request still needs its
+// `BankMessage` variant, its `describe` arm and its arm in the manager,
+// all written by hand; the manager's match is exhaustive, so a variant
+// without a handler doesn't compile.
+//
+// Every call answers with a `Result`: a manager that has stopped, or drops
+// the request without answering, is `ManagerUnavailable`. Replies that can't
+// fail on their own are wrapped in `Ok`.
+macro_rules! define_message {
+    ($($(#[$attr:meta])* $vis:vis fn $name:ident($($field:ident: $ty:ty),* $(,)?) -> $reply:ty = $variant:ident;)*) => {
+        $(
+            $(#[$attr])*
+            $vis async fn $name(tx: &MeteredSender<BankMessage>, $($field: $ty),*) -> $reply {
+                let (respond_to, response) = oneshot::channel();
+                tx.send(BankMessage::$variant { $($field: $field.to_owned(),)* respond_to })
+                    .await
+                    .map_err(|_| BankError::ManagerUnavailable)?;
+                response.await.map_err(|_| BankError::ManagerUnavailable)?.into_result()
+            }
+        )*
+    };
+}
+
+// A manager's reply as the client call's result
+trait Reply {
+    type Ok;
+
+    fn into_result(self) -> Result<Self::Ok, BankError>;
+}
+
+impl<T> Reply for Result<T, BankError> {
+    type Ok = T;
+
+    fn into_result(self) -> Result<T, BankError> {
+        self
+    }
+}
+
+// Replies that are never errors
+macro_rules! infallible_reply {
+    ($($reply:ty),* $(,)?) => {
+        $(
+            impl Reply for $reply {
+                type Ok = $reply;
+
+                fn into_result(self) -> Result<$reply, BankError> {
+                    Ok(self)
+                }
+            }
+        )*
+    };
+}
+
+infallible_reply! {
+    bool,
+    i32,
+    HashMap<String, i32>,
+    Option<MergePolicy>,
+    Timestamp,
+    Vec<String>,
+    Vec<JournalEntry>,
+    Vec<Violation>,
+    mpsc::Receiver<Envelope<BankEvent>>,
+}
+
+define_message! {
+    pub fn deposit(account: &str, amount: i32) -> Result<i32, BankError> = Deposit;
+    pub fn balance(account: &str) -> Result<i32, BankError> = Balance;
+    pub fn transfer(from: &str, to: &str, amount: i32) -> Result<i32, BankError> = Transfer;
+    pub fn transfer_itemized(from: &str, to: &str, amount: i32) -> Result<TransferReport, BankError> = TransferItemized;
+    pub fn set_fee_schedule(schedule: Option<FeeSchedule>) -> Result<Option<FeeSchedule>, BankError> = SetFeeSchedule;
+    pub fn batch(ops: &[BatchOp]) -> Result<BatchReport, BankError> = Batch;
+    pub fn payroll(from: &str, credits: &[(String, i32)], mode: PayrollMode) -> Result<PayrollReport, BankError> = Payroll;
+    pub fn available_balance(account: &str) -> Result<i32, BankError> = AvailableBalance;
+    pub fn wait_for_balance(account: &str, at_least: i32, timeout: Duration) -> Result<i32, BankError> = WaitForBalance;
+    pub fn holdings(account: &str) -> Result<BTreeMap<Currency, i32>, BankError> = Holdings;
+    pub fn balances() -> Result<HashMap<String, i32>, BankError> = Balances;
+    pub fn total_balance() -> Result<i32, BankError> = TotalBalance;
+    pub fn list_accounts() -> Result<Vec<String>, BankError> = ListAccounts;
+    pub fn add_currency(account: &str, currency: Currency) -> Result<(), BankError> = AddCurrency;
+    pub fn deposit_in(account: &str, amount: i32, currency: Currency) -> Result<i32, BankError> = DepositIn;
+    pub fn transfer_in(from: &str, to: &str, amount: i32, currency: Currency, convert_to: Option<Currency>) -> Result<i32, BankError> = TransferIn;
+    pub fn open_account(account: &str, opening_balance: i32) -> Result<(), BankError> = OpenAccount;
+    pub fn close_account(account: &str) -> Result<(), BankError> = CloseAccount;
+    pub fn restore_account(account: &str) -> Result<(), BankError> = RestoreAccount;
+    pub fn set_parent(account: &str, parent: &str) -> Result<(), BankError> = SetParent;
+    pub fn subscribe(pattern: &str) -> Result<mpsc::Receiver<Envelope<BankEvent>>, BankError> = Subscribe;
+    pub fn resubscribe(pattern: &str, last_seen: u64) -> Result<mpsc::Receiver<Envelope<BankEvent>>, BankError> = Resubscribe;
+    pub fn ship_oplog(after: u64) -> Result<mpsc::Receiver<Shipment>, BankError> = ShipOplog;
+    pub fn cancel(request_id: Id) -> Result<bool, BankError> = Cancel;
+    pub fn authorize(from: &str, to: &str, amount: i32, ttl: Duration) -> Result<Id, BankError> = Authorize;
+    pub fn capture(hold: Id) -> Result<i32, BankError> = Capture;
+    pub fn void(hold: Id) -> Result<(), BankError> = Void;
+    pub fn journal(last: usize) -> Result<Vec<JournalEntry>, BankError> = Journal;
+    pub fn receipt(transaction: Id) -> Result<Receipt, BankError> = Receipt;
+    pub fn skew_clock(offset: chrono::Duration) -> Result<Timestamp, BankError> = SkewClock;
+    pub fn check_invariants() -> Result<Vec<Violation>, BankError> = CheckInvariants;
+    pub fn define_metadata_key(key: &str, policy: MergePolicy) -> Result<Option<MergePolicy>, BankError> = DefineMetadataKey;
+    pub fn set_metadata(account: &str, key: &str, value: &str, stamp: Stamp) -> Result<Vec<String>, BankError> = SetMetadata;
+    pub fn metadata(account: &str) -> Result<BTreeMap<String, Vec<String>>, BankError> = Metadata;
+}
+
+// Long-poll for an account's events after `from_seq`: answers at once with
+// what's retained past it, otherwise waits up to `wait` for the next one. The
+// wait sleeps on the subscription rather than polling the manager. An empty
+// batch means the wait ran out; poll again from the same sequence number.
+pub async fn poll_events(
+    tx: &MeteredSender<BankMessage>,
+    account: &str,
+    from_seq: u64,
+    wait: Duration,
+) -> Result<Vec<Envelope<BankEvent>>, BankError> {
+    let deadline = Instant::now() + wait;
+    let mut events = resubscribe(tx, "*", from_seq).await?;
+    let mut batch = vec![];
+    while batch.is_empty() {
+        match timeout_at(deadline, events.recv()).await {
+            Ok(Some(envelope)) if envelope.event.involves(account) => batch.push(envelope),
+            Ok(Some(_)) => {},
+            Ok(None) | Err(_) => break,
+        }
+    }
+    // Take whatever else has already arrived
+    while let Ok(envelope) = events.try_recv() {
+        if envelope.event.involves(account) {
+            batch.push(envelope);
+        }
+    }
+    Ok(batch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::channel_with_metrics;
+
+    #[tokio::test]
+    async fn a_stopped_manager_is_unavailable() {
+        let (tx, rx) = channel_with_metrics("bank", 1);
+        drop(rx);
+        assert!(matches!(balance(&tx, "Alice").await, Err(BankError::ManagerUnavailable)));
+        assert!(matches!(total_balance(&tx).await, Err(BankError::ManagerUnavailable)));
+    }
+
+    #[tokio::test]
+    async fn a_dropped_request_is_unavailable() {
+        let (tx, mut rx) = channel_with_metrics("bank", 1);
+        tokio::spawn(async move { while rx.recv().await.is_some() {} });
+        assert!(matches!(list_accounts(&tx).await, Err(BankError::ManagerUnavailable)));
+    }
+}
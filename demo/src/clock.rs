@@ -0,0 +1,164 @@
+use chrono::{DateTime, Local, TimeZone};
+use std::fmt;
+
+// Hybrid logical clock reading: wall-clock milliseconds, plus a counter that
+// orders readings taken while the wall clock stood still or went backwards
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    pub millis: i64,
+    pub logical: u32,
+}
+
+impl Timestamp {
+    pub fn wall(&self) -> DateTime<Local> {
+        Local.timestamp_millis_opt(self.millis).single().unwrap_or_else(Local::now)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}+{}", self.wall().format("%H:%M:%S%.3f"), self.logical)
+    }
+}
+
+// Hands out timestamps that never go backwards, however the wall clock
+// moves: if it jumps back, readings keep the latest time seen and count up
+pub struct HybridClock {
+    last: Timestamp,
+    // Simulated error in this node's wall clock, in milliseconds
+    skew: i64,
+}
+
+impl HybridClock {
+    pub fn new() -> Self {
+        HybridClock {
+            last: Timestamp { millis: 0, logical: 0 },
+            skew: 0,
+        }
+    }
+
+    // The wall clock as this node sees it, skew included
+    pub fn wall(&self) -> DateTime<Local> {
+        Local::now() + chrono::Duration::milliseconds(self.skew)
+    }
+
+    pub fn now(&mut self) -> Timestamp {
+        let physical = self.wall().timestamp_millis();
+        self.last = if physical > self.last.millis {
+            Timestamp { millis: physical, logical: 0 }
+        } else {
+            Timestamp { millis: self.last.millis, logical: self.last.logical + 1 }
+        };
+        self.last
+    }
+
+    // Merge a timestamp received from another node, so anything stamped here
+    // afterwards orders after it
+    pub fn observe(&mut self, remote: Timestamp) -> Timestamp {
+        let physical = self.wall().timestamp_millis();
+        let millis = physical.max(self.last.millis).max(remote.millis);
+        let logical = if millis == self.last.millis && millis == remote.millis {
+            self.last.logical.max(remote.logical) + 1
+        } else if millis == self.last.millis {
+            self.last.logical + 1
+        } else if millis == remote.millis {
+            remote.logical + 1
+        } else {
+            0
+        };
+        self.last = Timestamp { millis, logical };
+        self.last
+    }
+
+    // Move this node's wall clock, e.g. by a negative amount to simulate an
+    // NTP correction stepping it back
+    pub fn skew_by(&mut self, offset: chrono::Duration) {
+        self.skew += offset.num_milliseconds();
+    }
+}
+
+impl Default for HybridClock {
+    fn default() -> Self {
+        HybridClock::new()
+    }
+}
+
+#[cfg(test)]
+pub mod testing {
+    use tokio::time::{advance, sleep, Duration};
+
+    use crate::bank::BankMessage;
+    use crate::client::skew_clock;
+    use crate::metrics::MeteredSender;
+
+    // Moves a manager's wall clock and the runtime's timers forward
+    // together, so hold expiry and purging can be tested without waiting for
+    // them. Needs a test started with paused time.
+    pub struct TestClock<'a> {
+        bank: &'a MeteredSender<BankMessage>,
+    }
+
+    impl<'a> TestClock<'a> {
+        pub fn new(bank: &'a MeteredSender<BankMessage>) -> Self {
+            TestClock { bank }
+        }
+
+        // Returns once the background tasks woken by the jump have had
+        // their requests answered
+        pub async fn advance(&self, by: Duration) {
+            skew_clock(self.bank, chrono::Duration::from_std(by).expect("advance too far")).await.unwrap();
+            advance(by).await;
+            // Paused time only moves on once every task is idle
+            sleep(Duration::from_millis(1)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time::Duration;
+
+    use super::testing::TestClock;
+    use crate::bank::BankError;
+    use crate::client::{authorize, available_balance, capture, close_account, deposit};
+    use crate::config::Config;
+    use crate::context::AppContext;
+
+    #[tokio::test(start_paused = true)]
+    async fn holds_expire_once_their_ttl_passes() {
+        let ctx = AppContext::builder(&Config::default())
+            .account("Alice", 100)
+            .account("Bob", 0)
+            .hold_expiry(Duration::from_secs(1))
+            .build()
+            .await;
+        let clock = TestClock::new(ctx.bank());
+
+        let hold = authorize(ctx.bank(), "Alice", "Bob", 40, Duration::from_secs(30)).await.unwrap();
+        clock.advance(Duration::from_secs(29)).await;
+        assert_eq!(available_balance(ctx.bank(), "Alice").await, Ok(60));
+
+        clock.advance(Duration::from_secs(2)).await;
+        assert_eq!(available_balance(ctx.bank(), "Alice").await, Ok(100));
+        assert!(capture(ctx.bank(), hold).await.is_err());
+        ctx.shutdown().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn closed_accounts_are_purged_after_retention() {
+        let ctx = AppContext::builder(&Config::default())
+            .account("Carol", 0)
+            .purge(Duration::from_secs(3600), Duration::from_secs(60))
+            .build()
+            .await;
+        let clock = TestClock::new(ctx.bank());
+
+        close_account(ctx.bank(), "Carol").await.unwrap();
+        clock.advance(Duration::from_secs(3540)).await;
+        assert_eq!(deposit(ctx.bank(), "Carol", 10).await, Err(BankError::Rejected("Account is closed")));
+
+        clock.advance(Duration::from_secs(120)).await;
+        assert_eq!(deposit(ctx.bank(), "Carol", 10).await, Err(BankError::Rejected("Account not found")));
+        ctx.shutdown().await;
+    }
+}
@@ -0,0 +1,191 @@
+use std::ops::RangeInclusive;
+use std::path::Path;
+use tokio::time::Duration;
+
+use crate::chaos::ChaosSettings;
+use crate::ids::IdKind;
+use crate::presets;
+
+// Settings shared by every scenario, overridable from the command line
+#[derive(Debug, Clone)]
+pub struct Config {
+    // Number of concurrent clients the bank scenarios launch
+    pub clients: usize,
+    // Simulated processing time for each bank operation
+    pub work_delay: Duration,
+    // Generator for transaction, event and request IDs
+    pub ids: IdKind,
+    // Report how far simulated work overshoots its configured delay
+    pub calibrate: bool,
+    // Percentage of the skewed workload's deposits that go to the hot account
+    pub hot_share: u32,
+    // Rounds of reads the read-consistency scenario makes while writes run
+    pub read_rounds: usize,
+    // Capacity of the bank manager's mailbox
+    pub mailbox: usize,
+    // Faults injected for the whole run
+    pub chaos: ChaosSettings,
+    // Pause between scenarios in `run all`
+    pub scenario_gap: Duration,
+    // How often the invariant watchdog checks the books
+    pub watchdog_every: Duration,
+    // How often background sweeps (compaction, purging, hold expiry) run
+    pub sweep_every: Duration,
+    // How often cached reads refresh their balance snapshot
+    pub refresh_every: Duration,
+    // Staleness a stale read accepts before going to the manager
+    pub stale_after: Duration,
+    // Store calls slower than this go in the slow-call log
+    pub slow_threshold: Duration,
+    // How often the reconciler compares cached balances with the books
+    pub reconcile_every: Duration,
+    // One-way latency between the simulated regions, in each direction
+    pub east_west: Duration,
+    pub west_east: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            clients: 3,
+            work_delay: Duration::from_millis(200),
+            ids: IdKind::Sequential,
+            calibrate: false,
+            hot_share: 80,
+            read_rounds: 4,
+            mailbox: 32,
+            chaos: ChaosSettings::default(),
+            scenario_gap: Duration::from_secs(1),
+            watchdog_every: Duration::from_millis(100),
+            sweep_every: Duration::from_millis(50),
+            refresh_every: Duration::from_millis(250),
+            stale_after: Duration::from_millis(500),
+            slow_threshold: Duration::from_millis(300),
+            reconcile_every: Duration::from_millis(200),
+            east_west: Duration::from_millis(20),
+            west_east: Duration::from_millis(80),
+        }
+    }
+}
+
+impl Config {
+    // Parse `--clients N`, `--work-delay-ms MS`,
+    // `--ids sequential|snowflake|uuid`, `--calibrate` and the duration flags
+    // below on top of the defaults, or on top of the preset named by
+    // `--preset NAME` (read from `--presets FILE`, presets.json by default)
+    // wherever it appears. Durations are written like `200ms`, `2s` or `1m`.
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut preset = None;
+        let mut presets_file = presets::DEFAULT_FILE.to_string();
+        for pair in args.windows(2) {
+            match pair[0].as_str() {
+                "--preset" => preset = Some(pair[1].as_str()),
+                "--presets" => presets_file = pair[1].clone(),
+                _ => {},
+            }
+        }
+        let mut config = match preset {
+            Some(name) => presets::load(Path::new(&presets_file), name)?,
+            None => Config::default(),
+        };
+        let mut args = args.iter();
+
+        while let Some(flag) = args.next() {
+            if flag == "--calibrate" {
+                config.calibrate = true;
+                continue;
+            }
+
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value for {}", flag))?;
+
+            match flag.as_str() {
+                // Already applied
+                "--preset" | "--presets" => {},
+                "--clients" => {
+                    config.clients = value
+                        .parse()
+                        .map_err(|_| format!("Invalid client count: {}", value))?;
+                },
+                "--work-delay-ms" => {
+                    let millis = value
+                        .parse()
+                        .map_err(|_| format!("Invalid delay: {}", value))?;
+                    config.work_delay = Duration::from_millis(millis);
+                },
+                "--work-delay" => config.work_delay = parse_duration(flag, value, Duration::ZERO..=Duration::from_secs(10))?,
+                "--scenario-gap" => config.scenario_gap = parse_duration(flag, value, Duration::ZERO..=Duration::from_secs(60))?,
+                "--watchdog-every" => {
+                    config.watchdog_every = parse_duration(flag, value, Duration::from_millis(10)..=Duration::from_secs(60))?;
+                },
+                "--sweep-every" => {
+                    config.sweep_every = parse_duration(flag, value, Duration::from_millis(10)..=Duration::from_secs(60))?;
+                },
+                "--refresh-every" => {
+                    config.refresh_every = parse_duration(flag, value, Duration::from_millis(10)..=Duration::from_secs(60))?;
+                },
+                "--stale-after" => config.stale_after = parse_duration(flag, value, Duration::ZERO..=Duration::from_secs(60))?,
+                "--slow-threshold" => {
+                    config.slow_threshold = parse_duration(flag, value, Duration::from_millis(1)..=Duration::from_secs(60))?;
+                },
+                "--reconcile-every" => {
+                    config.reconcile_every = parse_duration(flag, value, Duration::from_millis(10)..=Duration::from_secs(60))?;
+                },
+                "--east-west" => config.east_west = parse_duration(flag, value, Duration::ZERO..=Duration::from_secs(10))?,
+                "--west-east" => config.west_east = parse_duration(flag, value, Duration::ZERO..=Duration::from_secs(10))?,
+                "--ids" => config.ids = value.parse()?,
+                _ => return Err(format!("Unknown option: {}", flag)),
+            }
+        }
+
+        Ok(config)
+    }
+
+    // Every setting and its value, for `demo config`
+    pub fn describe(&self) -> Vec<(&'static str, String)> {
+        let duration = |value: Duration| humantime::format_duration(value).to_string();
+        vec![
+            ("clients", self.clients.to_string()),
+            ("work-delay", duration(self.work_delay)),
+            ("ids", format!("{:?}", self.ids)),
+            ("calibrate", self.calibrate.to_string()),
+            ("hot-share", format!("{}%", self.hot_share)),
+            ("read-rounds", self.read_rounds.to_string()),
+            ("mailbox", self.mailbox.to_string()),
+            ("chaos", format!("{:?}", self.chaos)),
+            ("scenario-gap", duration(self.scenario_gap)),
+            ("watchdog-every", duration(self.watchdog_every)),
+            ("sweep-every", duration(self.sweep_every)),
+            ("refresh-every", duration(self.refresh_every)),
+            ("stale-after", duration(self.stale_after)),
+            ("slow-threshold", duration(self.slow_threshold)),
+            ("reconcile-every", duration(self.reconcile_every)),
+            ("east-west", duration(self.east_west)),
+            ("west-east", duration(self.west_east)),
+        ]
+    }
+}
+
+// A duration like `200ms`, `2s` or `1m` for `flag`, refused outside `range`
+fn parse_duration(flag: &str, value: &str, range: RangeInclusive<Duration>) -> Result<Duration, String> {
+    let duration = humantime::parse_duration(value).map_err(|e| format!("Invalid duration for {}: {} ({})", flag, value, e))?;
+    if !range.contains(&duration) {
+        return Err(format!(
+            "{} must be between {} and {}, got {}",
+            flag,
+            humantime::format_duration(*range.start()),
+            humantime::format_duration(*range.end()),
+            value
+        ));
+    }
+    Ok(duration)
+}
+
+// Runtime switch shared with every manager through a watch channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceMode {
+    ReadWrite,
+    // Balances and queries keep working, mutations are rejected
+    ReadOnly,
+}
@@ -0,0 +1,58 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tokio::time::Duration;
+
+// How hard one account was fought over
+#[derive(Debug, Default)]
+struct AccountContention {
+    operations: u64,
+    total_wait: Duration,
+    max_wait: Duration,
+    // Operations that found the account (or its lock) busy
+    conflicts: u64,
+}
+
+static ACCOUNTS: Mutex<BTreeMap<String, AccountContention>> = Mutex::new(BTreeMap::new());
+
+// Record one operation on `account`, how long it waited for the lock or in
+// the queue, and whether it had to wait behind another operation
+pub fn record(account: &str, wait: Duration, conflicted: bool) {
+    let mut accounts = ACCOUNTS.lock().unwrap();
+    let stats = accounts.entry(account.to_string()).or_default();
+    stats.operations += 1;
+    stats.total_wait += wait;
+    stats.max_wait = stats.max_wait.max(wait);
+    if conflicted {
+        stats.conflicts += 1;
+    }
+}
+
+// Print the busiest accounts recorded since the last report and start over
+pub fn print_report(title: &str) {
+    let accounts = std::mem::take(&mut *ACCOUNTS.lock().unwrap());
+    if accounts.is_empty() {
+        return;
+    }
+
+    let total: u64 = accounts.values().map(|stats| stats.operations).sum();
+    let mut busiest: Vec<_> = accounts.into_iter().collect();
+    busiest.sort_by_key(|(_, stats)| Reverse(stats.operations));
+
+    println!("\n=== Contention: {} ===", title);
+    println!("{:<12} {:>6} {:>7} {:>12} {:>12} {:>10}", "account", "ops", "share", "avg wait", "max wait", "conflicts");
+    for (account, stats) in busiest.iter().take(10) {
+        println!(
+            "{:<12} {:>6} {:>6.1}% {:>12?} {:>12?} {:>10}",
+            account,
+            stats.operations,
+            stats.operations as f64 * 100.0 / total as f64,
+            stats.total_wait / stats.operations as u32,
+            stats.max_wait,
+            stats.conflicts
+        );
+    }
+    if busiest.len() > 10 {
+        println!("... and {} more accounts", busiest.len() - 10);
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+
+use crate::bank::{run_bank_manager, spawn_compactor, spawn_hold_expirer, spawn_purger, spawn_watchdog, BankMessage};
+use crate::config::Config;
+use crate::keys::{spawn_key_manager, KeyMessage, KeyedClient};
+use crate::ledger::RetentionPolicy;
+use crate::metrics::{channel_with_metrics, MeteredSender};
+use crate::quota::{spawn_quota_manager, QuotaGate, QuotaLimits, QuotaMessage};
+use crate::reads::{BalanceReader, ReconcilePolicy};
+
+// Owns every subsystem a scenario needs, wired together in dependency order:
+// the manager first, then the background tasks and services that talk to it.
+// `shutdown` takes them down in reverse.
+//
+//     let ctx = AppContext::builder(&cfg).account("Alice", 100).quotas(limits).build().await;
+pub struct AppContext {
+    bank: MeteredSender<BankMessage>,
+    manager: JoinHandle<()>,
+    quotas: Option<MeteredSender<QuotaMessage>>,
+    keys: Option<MeteredSender<KeyMessage>>,
+    reader: Option<BalanceReader>,
+    // Watchdog, compactor, purger, hold expirer and reconciler; they stop once the manager's senders are gone
+    background: Vec<JoinHandle<()>>,
+}
+
+pub struct AppContextBuilder {
+    accounts: HashMap<String, i32>,
+    manager_delay: Duration,
+    mailbox: usize,
+    quotas: Option<QuotaLimits>,
+    api_keys: bool,
+    watchdog: Option<Duration>,
+    retention: Option<(RetentionPolicy, Duration)>,
+    purge: Option<(Duration, Duration)>,
+    hold_expiry: Option<Duration>,
+    reads: Option<Duration>,
+    reconcile: Option<ReconcilePolicy>,
+}
+
+impl AppContext {
+    pub fn builder(config: &Config) -> AppContextBuilder {
+        AppContextBuilder {
+            accounts: HashMap::new(),
+            manager_delay: config.work_delay,
+            mailbox: config.mailbox,
+            quotas: None,
+            api_keys: false,
+            watchdog: None,
+            retention: None,
+            purge: None,
+            hold_expiry: None,
+            reads: None,
+            reconcile: None,
+        }
+    }
+
+    pub fn bank(&self) -> &MeteredSender<BankMessage> {
+        &self.bank
+    }
+
+    // Panics if the context was built without `quotas`
+    pub fn quota_gate(&self, client: &str) -> QuotaGate {
+        QuotaGate::new(client, self.quotas(), &self.bank)
+    }
+
+    pub fn quotas(&self) -> &MeteredSender<QuotaMessage> {
+        self.quotas.as_ref().expect("context built without quotas")
+    }
+
+    // Panics if the context was built without `api_keys`
+    pub fn keyed_client(&self, key: &str) -> KeyedClient {
+        KeyedClient::new(key, self.keys(), &self.bank)
+    }
+
+    pub fn keys(&self) -> &MeteredSender<KeyMessage> {
+        self.keys.as_ref().expect("context built without API keys")
+    }
+
+    // Panics if the context was built without `cached_reads`
+    pub fn reader(&self) -> &BalanceReader {
+        self.reader.as_ref().expect("context built without cached reads")
+    }
+
+    // Stop services in reverse start order. The manager exits once the
+    // last sender is gone, so clients must have dropped their clones.
+    pub async fn shutdown(self) {
+        drop(self.reader);
+        drop(self.keys);
+        drop(self.quotas);
+        drop(self.bank);
+        for task in self.background {
+            task.await.unwrap();
+        }
+        self.manager.await.unwrap();
+    }
+}
+
+impl AppContextBuilder {
+    pub fn account(mut self, account: &str, balance: i32) -> Self {
+        self.accounts.insert(account.to_string(), balance);
+        self
+    }
+
+    // Defaults to the configured work delay
+    pub fn manager_delay(mut self, delay: Duration) -> Self {
+        self.manager_delay = delay;
+        self
+    }
+
+    pub fn quotas(mut self, limits: QuotaLimits) -> Self {
+        self.quotas = Some(limits);
+        self
+    }
+
+    pub fn api_keys(mut self) -> Self {
+        self.api_keys = true;
+        self
+    }
+
+    pub fn watchdog(mut self, interval: Duration) -> Self {
+        self.watchdog = Some(interval);
+        self
+    }
+
+    pub fn retention(mut self, policy: RetentionPolicy, interval: Duration) -> Self {
+        self.retention = Some((policy, interval));
+        self
+    }
+
+    pub fn purge(mut self, retention: Duration, interval: Duration) -> Self {
+        self.purge = Some((retention, interval));
+        self
+    }
+
+    pub fn hold_expiry(mut self, interval: Duration) -> Self {
+        self.hold_expiry = Some(interval);
+        self
+    }
+
+    pub fn cached_reads(mut self, refresh_every: Duration) -> Self {
+        self.reads = Some(refresh_every);
+        self
+    }
+
+    // Needs `cached_reads`: it's the reader's cache being reconciled
+    pub fn reconcile(mut self, policy: ReconcilePolicy) -> Self {
+        self.reconcile = Some(policy);
+        self
+    }
+
+    pub async fn build(self) -> AppContext {
+        let (bank, rx) = channel_with_metrics("bank", self.mailbox);
+        let manager = tokio::spawn(run_bank_manager(rx, self.accounts, self.manager_delay));
+
+        let mut background = vec![];
+        if let Some(interval) = self.watchdog {
+            background.push(spawn_watchdog(&bank, interval));
+        }
+        if let Some((policy, interval)) = self.retention {
+            background.push(spawn_compactor(&bank, policy, interval));
+        }
+        if let Some((retention, interval)) = self.purge {
+            background.push(spawn_purger(&bank, retention, interval));
+        }
+        if let Some(interval) = self.hold_expiry {
+            background.push(spawn_hold_expirer(&bank, interval));
+        }
+
+        let quotas = self.quotas.map(spawn_quota_manager);
+        let keys = self.api_keys.then(spawn_key_manager);
+        let reader = match self.reads {
+            Some(refresh_every) => Some(BalanceReader::new(&bank, refresh_every).await.expect("the manager was just started")),
+            None => None,
+        };
+        if let Some(policy) = self.reconcile {
+            let reader = reader.as_ref().expect("reconciliation needs cached reads");
+            background.push(reader.spawn_reconciler(policy));
+        }
+
+        AppContext {
+            bank,
+            manager,
+            quotas,
+            keys,
+            reader,
+            background,
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use chrono::Local;
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::fs;
+use std::future::Future;
+use std::panic;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+// Requests remembered per task, the newest being the one in progress
+const RECENT_REQUESTS: usize = 16;
+
+struct TaskInfo {
+    name: &'static str,
+    recent: RefCell<VecDeque<u64>>,
+}
+
+tokio::task_local! {
+    static TASK: TaskInfo;
+}
+
+// What the panic hook wrote down, and where
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    pub task: String,
+    pub message: String,
+    pub location: String,
+    pub recent_requests: Vec<u64>,
+    // None if the file couldn't be written
+    pub path: Option<PathBuf>,
+}
+
+// Reports not yet picked up, e.g. by a supervisor restarting the task
+static REPORTS: Mutex<Vec<CrashReport>> = Mutex::new(Vec::new());
+
+// Write a crash report for every panic into `dir`, then carry on with the
+// hook that was there before, so the usual message still goes to stderr
+pub fn install(dir: PathBuf) {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let message = match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+            (Some(message), _) => message.to_string(),
+            (_, Some(message)) => message.clone(),
+            _ => "(no message)".to_string(),
+        };
+        let location = info.location().map_or("unknown".to_string(), |l| format!("{}:{}", l.file(), l.line()));
+        // Outside a named task, or in the middle of recording a request
+        let (task, recent_requests) = TASK
+            .try_with(|task| {
+                let recent = task.recent.try_borrow().map(|recent| recent.iter().copied().collect()).unwrap_or_default();
+                (task.name.to_string(), recent)
+            })
+            .unwrap_or_else(|_| ("unnamed".to_string(), vec![]));
+
+        let mut text = String::new();
+        let _ = writeln!(text, "time:     {}", Local::now().to_rfc3339());
+        let _ = writeln!(text, "task:     {}", task);
+        let _ = writeln!(text, "thread:   {}", thread::current().name().unwrap_or("unnamed"));
+        let _ = writeln!(text, "message:  {}", message);
+        let _ = writeln!(text, "location: {}", location);
+        let _ = writeln!(text, "requests: {:?}", recent_requests);
+        let _ = writeln!(text, "\n{}", Backtrace::force_capture());
+
+        let path = dir.join(format!("crash-{}-{}.txt", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed)));
+        let path = fs::create_dir_all(&dir).and_then(|_| fs::write(&path, text)).ok().map(|_| path);
+        REPORTS.lock().unwrap().push(CrashReport { task, message, location, recent_requests, path });
+
+        previous(info);
+    }));
+}
+
+// Run `future` as a named task, so a panic in it is reported under that name
+pub async fn named<F: Future>(name: &'static str, future: F) -> F::Output {
+    TASK.scope(TaskInfo { name, recent: RefCell::new(VecDeque::new()) }, future).await
+}
+
+// Note the request the current task is starting on
+pub fn record_request(request: u64) {
+    let _ = TASK.try_with(|task| {
+        let mut recent = task.recent.borrow_mut();
+        if recent.len() == RECENT_REQUESTS {
+            recent.pop_front();
+        }
+        recent.push_back(request);
+    });
+}
+
+// The latest unclaimed report from the task with this name
+pub fn take_report(task: &str) -> Option<CrashReport> {
+    let mut reports = REPORTS.lock().unwrap();
+    let index = reports.iter().rposition(|report| report.task == task)?;
+    Some(reports.remove(index))
+}
@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+// Three-letter currency code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    pub const USD: Currency = Currency(*b"USD");
+    pub const EUR: Currency = Currency(*b"EUR");
+    pub const GBP: Currency = Currency(*b"GBP");
+    // Every account holds this one; operations that don't name a currency use it
+    pub const BASE: Currency = Currency::USD;
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(std::str::from_utf8(&self.0).unwrap_or("???"))
+    }
+}
+
+impl FromStr for Currency {
+    type Err = String;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        match code.as_bytes() {
+            &[a, b, c] if code.bytes().all(|byte| byte.is_ascii_uppercase()) => Ok(Currency([a, b, c])),
+            _ => Err(format!("Not a currency code: {}", code)),
+        }
+    }
+}
+
+// Fixed exchange rates, each currency's worth in thousandths of the base
+// currency. Good enough for a demo; a real bank would stream these.
+pub struct Rates(HashMap<Currency, i64>);
+
+impl Rates {
+    // Converted amounts are rounded down, so conversion never creates money
+    pub fn convert(&self, amount: i32, from: Currency, to: Currency) -> Option<i32> {
+        let from_rate = self.0.get(&from)?;
+        let to_rate = self.0.get(&to)?;
+        i32::try_from(i64::from(amount) * from_rate / to_rate).ok()
+    }
+}
+
+impl Default for Rates {
+    fn default() -> Self {
+        Rates(HashMap::from([(Currency::USD, 1_000), (Currency::EUR, 1_080), (Currency::GBP, 1_270)]))
+    }
+}
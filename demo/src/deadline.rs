@@ -0,0 +1,70 @@
+use std::fmt;
+use std::future::Future;
+use tokio::time::{timeout_at, Duration, Instant};
+
+tokio::task_local! {
+    static CURRENT: Deadline;
+}
+
+// A named point in time that work in its scope has to finish by. Scopes
+// nest: a step inside one gets its own budget, cut short to whatever the
+// enclosing scope has left, instead of an independent timeout that could
+// outlive its caller's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    // The scope whose deadline this is
+    pub level: &'static str,
+    pub at: Instant,
+}
+
+// Which scope's deadline fired, and which step it cut off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeadlineExceeded {
+    pub level: &'static str,
+    pub during: &'static str,
+}
+
+impl fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.level == self.during {
+            write!(f, "{} timed out", self.during)
+        } else {
+            write!(f, "{} deadline passed during {}", self.level, self.during)
+        }
+    }
+}
+
+impl Deadline {
+    pub fn after(level: &'static str, budget: Duration) -> Self {
+        Deadline { level, at: Instant::now() + budget }
+    }
+
+    // Run `future` in this deadline's scope without enforcing it, e.g. to
+    // carry it into a spawned task, which doesn't inherit the spawner's
+    pub async fn enter<F: Future>(self, future: F) -> F::Output {
+        CURRENT.scope(self, future).await
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.at.saturating_duration_since(Instant::now())
+    }
+}
+
+// The innermost deadline the current task is working under
+pub fn current() -> Option<Deadline> {
+    CURRENT.try_with(|deadline| *deadline).ok()
+}
+
+// Run `step` with `budget`, or with what's left of the enclosing deadline if
+// that's less. The error names whichever of the two fired.
+pub async fn within<F: Future>(step: &'static str, budget: Duration, future: F) -> Result<F::Output, DeadlineExceeded> {
+    let own = Deadline::after(step, budget);
+    let deadline = match current() {
+        Some(enclosing) if enclosing.at < own.at => enclosing,
+        _ => own,
+    };
+    CURRENT
+        .scope(deadline, timeout_at(deadline.at, future))
+        .await
+        .map_err(|_| DeadlineExceeded { level: deadline.level, during: step })
+}
@@ -0,0 +1,151 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::task::JoinSet;
+use tokio::time::{Duration, Instant};
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+// A bank design the differential runner can drive and read back
+pub trait Implementation: Send + Sync + 'static {
+    fn name(&self) -> &'static str;
+    fn deposit<'a>(&'a self, account: &'a str, amount: i32) -> BoxFuture<'a, Result<i32, String>>;
+    fn balances(&self) -> BoxFuture<'_, BTreeMap<String, i32>>;
+}
+
+// What one implementation made of the script
+pub struct Run {
+    pub name: &'static str,
+    // Accepted or refused, by position in the script. Balances after each
+    // step are left out: they depend on how the clients interleaved.
+    pub outcomes: Vec<Result<(), String>>,
+    // Sorted, fastest first
+    pub latencies: Vec<Duration>,
+    pub balances: BTreeMap<String, i32>,
+    pub elapsed: Duration,
+}
+
+impl Run {
+    fn percentile(&self, percent: usize) -> Duration {
+        match self.latencies.len() {
+            0 => Duration::ZERO,
+            len => self.latencies[(len - 1) * percent / 100],
+        }
+    }
+}
+
+// Plays the script with `clients` concurrent clients, client `c` taking
+// every `clients`-th step in order
+pub async fn run(implementation: Arc<dyn Implementation>, script: Arc<Vec<(String, i32)>>, clients: usize) -> Run {
+    let clients = clients.max(1);
+    let started = Instant::now();
+    let mut tasks = JoinSet::new();
+    for client in 0..clients {
+        let (implementation, script) = (Arc::clone(&implementation), Arc::clone(&script));
+        tasks.spawn(async move {
+            let mut results = vec![];
+            for step in (client..script.len()).step_by(clients) {
+                let (account, amount) = &script[step];
+                let began = Instant::now();
+                let outcome = implementation.deposit(account, *amount).await.map(|_| ());
+                results.push((step, outcome, began.elapsed()));
+            }
+            results
+        });
+    }
+
+    let mut outcomes = vec![Ok(()); script.len()];
+    let mut latencies = Vec::with_capacity(script.len());
+    while let Some(results) = tasks.join_next().await {
+        for (step, outcome, latency) in results.expect("client task panicked") {
+            outcomes[step] = outcome;
+            latencies.push(latency);
+        }
+    }
+    latencies.sort();
+
+    Run {
+        name: implementation.name(),
+        outcomes,
+        latencies,
+        balances: implementation.balances().await,
+        elapsed: started.elapsed(),
+    }
+}
+
+// The same script played against a reference and a candidate
+pub struct Comparison {
+    pub script: Arc<Vec<(String, i32)>>,
+    pub reference: Run,
+    pub candidate: Run,
+}
+
+// Runs both implementations at the same time, so neither gets a quieter
+// machine than the other
+pub async fn compare(
+    reference: Arc<dyn Implementation>,
+    candidate: Arc<dyn Implementation>,
+    script: Vec<(String, i32)>,
+    clients: usize,
+) -> Comparison {
+    let script = Arc::new(script);
+    let (reference, candidate) = tokio::join!(
+        run(reference, Arc::clone(&script), clients),
+        run(candidate, Arc::clone(&script), clients),
+    );
+    Comparison { script, reference, candidate }
+}
+
+impl Comparison {
+    // Accounts whose final balances differ, with each side's balance
+    pub fn balance_diffs(&self) -> Vec<(String, Option<i32>, Option<i32>)> {
+        let accounts: BTreeSet<&String> = self.reference.balances.keys().chain(self.candidate.balances.keys()).collect();
+        accounts
+            .into_iter()
+            .map(|account| (account.clone(), self.reference.balances.get(account).copied(), self.candidate.balances.get(account).copied()))
+            .filter(|(_, reference, candidate)| reference != candidate)
+            .collect()
+    }
+
+    // Steps one side accepted and the other refused, or refused differently
+    pub fn outcome_diffs(&self) -> Vec<usize> {
+        (0..self.script.len())
+            .filter(|&step| self.reference.outcomes[step] != self.candidate.outcomes[step])
+            .collect()
+    }
+
+    pub fn matches(&self) -> bool {
+        self.balance_diffs().is_empty() && self.outcome_diffs().is_empty()
+    }
+
+    pub fn print(&self) {
+        let (reference, candidate) = (&self.reference, &self.candidate);
+        println!("{:<14} {:>12} {:>12}", "", reference.name, candidate.name);
+        println!("{:<14} {:>12?} {:>12?}", "total time", reference.elapsed, candidate.elapsed);
+        for (label, percent) in [("p50 latency", 50), ("p90 latency", 90), ("p99 latency", 99), ("max latency", 100)] {
+            println!("{:<14} {:>12?} {:>12?}", label, reference.percentile(percent), candidate.percentile(percent));
+        }
+
+        let balance_diffs = self.balance_diffs();
+        if balance_diffs.is_empty() {
+            println!("Final balances: identical");
+        }
+        for (account, expected, found) in balance_diffs {
+            println!("Final balance of {}: {:?} vs {:?}", account, expected, found);
+        }
+
+        let outcome_diffs = self.outcome_diffs();
+        if outcome_diffs.is_empty() {
+            println!("Outcomes: identical for all {} steps", self.script.len());
+        }
+        for step in outcome_diffs {
+            let (account, amount) = &self.script[step];
+            println!(
+                "Step {} ({} +{}): {:?} vs {:?}",
+                step, account, amount, reference.outcomes[step], candidate.outcomes[step]
+            );
+        }
+        println!("Verdict: {}", if self.matches() { "candidate matches the reference" } else { "candidate differs" });
+    }
+}
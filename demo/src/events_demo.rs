@@ -0,0 +1,456 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration, Instant};
+
+use crate::bank::{run_bank_manager, BankEvent, BankMessage};
+use crate::client::{add_currency, balances, deposit, deposit_in, journal, poll_events, resubscribe, set_parent, subscribe, transfer};
+use crate::config::Config;
+use crate::context::AppContext;
+use crate::currency::Currency;
+use crate::metrics::{channel_with_metrics, MeteredSender};
+use crate::notifications::{FileSink, Notification, Notifications, Notifier, Sink};
+use crate::projection::{run_checkpointed, run_projection, CheckpointStore, DeadLetters, Projection, RetryPolicy};
+use crate::pubsub::Envelope;
+use crate::rollup::RollupView;
+use crate::scenario::{scenario, Scenario};
+
+async fn run_topic_router_example(_cfg: Config) {
+    println!("\n=== Topic Router Example (Pattern Subscriptions) ===");
+    let (tx, rx) = channel_with_metrics("bank", 32);
+
+    let mut accounts = HashMap::new();
+    accounts.insert("Alice".to_string(), 100);
+    accounts.insert("Bob".to_string(), 50);
+    let manager = tokio::spawn(run_bank_manager(rx, accounts, Duration::from_millis(10)));
+
+    // Each subscriber only hears about the topics it asked for
+    let mut subscribers = vec![];
+    for pattern in ["account.alice.*", "transfers.*", "account.*.deposit"] {
+        let mut events = subscribe(&tx, pattern).await.unwrap();
+        subscribers.push(tokio::spawn(async move {
+            while let Some(envelope) = events.recv().await {
+                println!("[{:<17}] #{} {} {} - {}", pattern, envelope.seq, envelope.id, envelope.topic, envelope.event);
+            }
+        }));
+    }
+
+    // Fire and forget: nobody waits for the replies
+    let operations = [
+        BankMessage::Deposit { account: "Alice".to_string(), amount: 50, respond_to: oneshot::channel().0 },
+        BankMessage::Deposit { account: "Bob".to_string(), amount: 20, respond_to: oneshot::channel().0 },
+        BankMessage::Transfer { from: "Bob".to_string(), to: "Alice".to_string(), amount: 30, respond_to: oneshot::channel().0 },
+    ];
+    for operation in operations {
+        tx.send(operation).await.unwrap();
+    }
+
+    // Closing the manager closes every subscription
+    drop(tx);
+    manager.await.unwrap();
+    for subscriber in subscribers {
+        subscriber.await.unwrap();
+    }
+}
+
+async fn run_catch_up_example(cfg: Config) {
+    println!("\n=== Catch-up Example (Resubscribing Without Gaps) ===");
+    let (tx, rx) = channel_with_metrics("bank", 32);
+
+    let mut accounts = HashMap::new();
+    accounts.insert("Alice".to_string(), 100);
+    accounts.insert("Bob".to_string(), 50);
+    let manager = tokio::spawn(run_bank_manager(rx, accounts, Duration::from_millis(5)));
+
+    // Deposits keep flowing the whole time, including while we reconnect
+    let producer_tx = tx.clone();
+    let delay = cfg.work_delay / 10;
+    let producer = tokio::spawn(async move {
+        for i in 0..12 {
+            let account = if i % 2 == 0 { "Alice" } else { "Bob" };
+            let (resp_tx, resp_rx) = oneshot::channel();
+            producer_tx.send(BankMessage::Deposit { account: account.to_string(), amount: 5, respond_to: resp_tx }).await.unwrap();
+            let _ = resp_rx.await;
+            sleep(delay).await;
+        }
+    });
+
+    let pattern = "account.*.deposit";
+    let mut seen = vec![];
+    let mut events = subscribe(&tx, pattern).await.unwrap();
+    while seen.len() < 3 {
+        let envelope = events.recv().await.unwrap();
+        println!("[live    ] #{} {}", envelope.seq, envelope.event);
+        seen.push(envelope.seq);
+    }
+
+    // Disconnect, miss a few deposits, then come back
+    drop(events);
+    let last_seen = *seen.last().unwrap();
+    println!("Disconnected after #{}", last_seen);
+    sleep(delay * 4).await;
+
+    let mut events = resubscribe(&tx, pattern, last_seen).await.unwrap();
+    println!("Reconnected, asking for everything after #{}", last_seen);
+    drop(tx);
+
+    while let Some(envelope) = events.recv().await {
+        println!("[resumed ] #{} {}", envelope.seq, envelope.event);
+        seen.push(envelope.seq);
+    }
+
+    producer.await.unwrap();
+    manager.await.unwrap();
+
+    // Only deposits are published here, so the sequence numbers are contiguous
+    let contiguous = seen.windows(2).all(|pair| pair[1] == pair[0] + 1);
+    if contiguous {
+        println!("Received #{}..=#{} with no gaps or duplicates", seen[0], seen[seen.len() - 1]);
+    } else {
+        println!("Sequence numbers were not contiguous: {:?}", seen);
+    }
+}
+
+// Read model of the latest balance per account and currency, backed by a
+// store that's down for its first few writes
+struct BalanceView {
+    balances: HashMap<(String, Currency), (u64, i32)>,
+    currencies: Vec<Currency>,
+    outage: u32,
+}
+
+impl Projection<BankEvent> for BalanceView {
+    fn apply(&mut self, envelope: &Envelope<BankEvent>) -> Result<(), String> {
+        let BankEvent::Deposited { account, currency, balance, .. } = &envelope.event else {
+            return Ok(());
+        };
+        // Fails on every attempt: a poison event
+        if !self.currencies.contains(currency) {
+            return Err(format!("no column for {} balances", currency));
+        }
+        if self.outage > 0 {
+            self.outage -= 1;
+            return Err("read store unavailable".to_string());
+        }
+        // A retried event can land after newer ones; keep the newest balance
+        let latest = self.balances.entry((account.clone(), *currency)).or_insert((0, 0));
+        if envelope.seq > latest.0 {
+            *latest = (envelope.seq, *balance);
+        }
+        Ok(())
+    }
+}
+
+async fn run_dead_letter_example(cfg: Config) {
+    println!("\n=== Dead Letter Example (Retrying Failed Projection Updates) ===");
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 100)
+        .account("Bob", 50)
+        .manager_delay(cfg.work_delay / 20)
+        .build()
+        .await;
+    let tx = ctx.bank();
+
+    let view = BalanceView { balances: HashMap::new(), currencies: vec![Currency::BASE], outage: 2 };
+    let policy = RetryPolicy { capacity: 8, max_attempts: 3, retry_every: cfg.work_delay / 10 };
+    let dead_letters = DeadLetters::default();
+    let events = subscribe(tx, "account.*.deposit").await.unwrap();
+    let projection = tokio::spawn(run_projection("balance-view".to_string(), view, events, policy, dead_letters.clone()));
+
+    add_currency(tx, "Alice", Currency::EUR).await.unwrap();
+    deposit(tx, "Alice", 10).await.unwrap();
+    deposit(tx, "Bob", 20).await.unwrap();
+    deposit(tx, "Alice", 5).await.unwrap();
+    deposit_in(tx, "Alice", 30, Currency::EUR).await.unwrap();
+    deposit(tx, "Bob", 5).await.unwrap();
+
+    // Closing the manager closes the subscription; the projection then works
+    // off its retry queue before returning
+    ctx.shutdown().await;
+    let mut view = projection.await.unwrap();
+
+    for letter in dead_letters.list() {
+        println!("Dead letter: #{} {} ({} attempts) - {}", letter.envelope.seq, letter.envelope.event, letter.attempts, letter.error);
+    }
+
+    // Once the projection learns about euros, the dead letters can be replayed
+    view.currencies.push(Currency::EUR);
+    for letter in dead_letters.drain() {
+        match view.apply(&letter.envelope) {
+            Ok(()) => println!("Replayed #{}", letter.envelope.seq),
+            Err(e) => println!("Replaying #{} failed again - {}", letter.envelope.seq, e),
+        }
+    }
+
+    let mut balances: Vec<_> = view.balances.into_iter().collect();
+    balances.sort();
+    for ((account, currency), (seq, balance)) in balances {
+        println!("{} {}: {} (as of #{})", account, currency, balance, seq);
+    }
+}
+
+// File sink whose mail server is down for its first few sends
+struct FlakySink {
+    file: FileSink,
+    outage: u32,
+}
+
+impl Sink for FlakySink {
+    fn send(&mut self, notification: &Notification) -> Result<(), String> {
+        if self.outage > 0 {
+            self.outage -= 1;
+            return Err("mail server unavailable".to_string());
+        }
+        self.file.send(notification)
+    }
+}
+
+async fn run_notification_example(cfg: Config) {
+    println!("\n=== Notification Example (Exactly Once per Committed Transaction) ===");
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 1000)
+        .account("Bob", 100)
+        .account("Carol", 0)
+        .manager_delay(cfg.work_delay / 20)
+        .build()
+        .await;
+    let tx = ctx.bank();
+
+    let path = std::env::temp_dir().join("bank-notifications.log");
+    let _ = std::fs::remove_file(&path);
+    let notifications = Notifications::new(FlakySink { file: FileSink::new(path.clone()), outage: 1 });
+    let policy = RetryPolicy { capacity: 8, max_attempts: 5, retry_every: cfg.work_delay / 10 };
+    let pattern = "transfers.*";
+
+    let events = subscribe(tx, pattern).await.unwrap();
+    let notifier = Notifier::new(250, notifications.clone());
+    let projection = tokio::spawn(run_projection("notifier".to_string(), notifier, events, policy, DeadLetters::default()));
+
+    transfer(tx, "Alice", "Bob", 500).await.unwrap();
+    transfer(tx, "Alice", "Bob", 20).await.unwrap();
+    // Refused, so never committed and never notified
+    let _ = transfer(tx, "Carol", "Alice", 400).await;
+    transfer(tx, "Bob", "Carol", 300).await.unwrap();
+    sleep(cfg.work_delay / 2).await;
+
+    // The notifier dies before saving a checkpoint; a payment goes through
+    // while it's down
+    projection.abort();
+    println!("Notifier crashed, {} notification(s) sent so far", notifications.sent().len());
+    transfer(tx, "Bob", "Alice", 250).await.unwrap();
+
+    // Restarting from the beginning redelivers everything still retained
+    let events = resubscribe(tx, pattern, 0).await.unwrap();
+    let notifier = Notifier::new(250, notifications.clone());
+    let projection = tokio::spawn(run_projection("notifier".to_string(), notifier, events, policy, DeadLetters::default()));
+
+    ctx.shutdown().await;
+    projection.await.unwrap();
+
+    // Admin: the customer lost the first email
+    let first = notifications.sent()[0].notification.transaction;
+    notifications.resend(first).unwrap();
+
+    for sent in notifications.sent() {
+        let notification = &sent.notification;
+        println!("#{} {}: {} - delivered {}x", sent.seq, notification.account, notification.message, sent.deliveries);
+    }
+    println!("Skipped {} redelivered event(s) that were already notified", notifications.duplicates());
+    let lines = std::fs::read_to_string(&path).map(|text| text.lines().count()).unwrap_or(0);
+    println!("{} line(s) in {}", lines, path.display());
+}
+
+fn print_rollup(view: &RollupView, account: &str, depth: usize) {
+    println!(
+        "{:indent$}{:<12} own {:>4}  rolled up {:>4}",
+        "",
+        account,
+        view.own(account).unwrap_or(0),
+        view.rolled_up(account).unwrap_or(0),
+        indent = depth * 2
+    );
+    for child in view.children(account) {
+        print_rollup(view, child, depth + 1);
+    }
+}
+
+async fn run_sub_account_example(cfg: Config) {
+    println!("\n=== Sub-account Example (Roll-up Balances From Events) ===");
+    let ctx = AppContext::builder(&cfg)
+        .account("Household", 500)
+        .account("Savings", 200)
+        .account("Checking", 100)
+        .account("Kids", 50)
+        .account("Bob", 300)
+        .manager_delay(cfg.work_delay / 20)
+        .build()
+        .await;
+    let tx = ctx.bank();
+
+    // Nothing else is writing yet, so the balances line up with the
+    // subscription
+    let events = subscribe(tx, "*").await.unwrap();
+    let view = RollupView::new(balances(tx).await.unwrap());
+    let policy = RetryPolicy { capacity: 8, max_attempts: 3, retry_every: cfg.work_delay / 10 };
+    let projection = tokio::spawn(run_projection("rollup".to_string(), view, events, policy, DeadLetters::default()));
+
+    for (account, parent) in [("Savings", "Household"), ("Checking", "Household"), ("Kids", "Checking")] {
+        set_parent(tx, account, parent).await.unwrap();
+    }
+    match set_parent(tx, "Household", "Kids").await {
+        Ok(()) => println!("Household is now under Kids"),
+        Err(e) => println!("Household under Kids refused: {:?}", e),
+    }
+
+    transfer(tx, "Household", "Savings", 100).await.unwrap();
+    transfer(tx, "Checking", "Kids", 20).await.unwrap();
+    transfer(tx, "Bob", "Kids", 40).await.unwrap();
+    transfer(tx, "Savings", "Bob", 60).await.unwrap();
+    deposit(tx, "Checking", 25).await.unwrap();
+    for entry in journal(tx, 5).await.unwrap() {
+        println!("{:?}: {}", entry.kind, entry.memo);
+    }
+    let ledger = balances(tx).await.unwrap();
+
+    ctx.shutdown().await;
+    let view = projection.await.unwrap();
+    for root in view.roots() {
+        print_rollup(&view, root, 0);
+    }
+
+    let family: i32 = ["Household", "Savings", "Checking", "Kids"].iter().map(|account| ledger[*account]).sum();
+    println!("Household family per the ledger: {}, per the roll-up: {}", family, view.rolled_up("Household").unwrap_or(0));
+}
+
+async fn run_long_poll_example(cfg: Config) {
+    println!("\n=== Long-poll Example (Waiting for Events Past a Sequence Number) ===");
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 100)
+        .account("Bob", 50)
+        .manager_delay(cfg.work_delay / 20)
+        .build()
+        .await;
+    let tx = ctx.bank().clone();
+    let wait = cfg.work_delay;
+
+    deposit(&tx, "Alice", 10).await.unwrap();
+    deposit(&tx, "Alice", 20).await.unwrap();
+
+    // Bob's deposit doesn't end Alice's poll; the payment to her does
+    let producer_tx = tx.clone();
+    let producer = tokio::spawn(async move {
+        sleep(wait * 3 / 2).await;
+        deposit(&producer_tx, "Bob", 5).await.unwrap();
+        sleep(wait / 4).await;
+        transfer(&producer_tx, "Bob", "Alice", 15).await.unwrap();
+    });
+
+    let mut cursor = 0;
+    for _ in 0..4 {
+        let started = Instant::now();
+        let batch = poll_events(&tx, "Alice", cursor, wait).await.unwrap();
+        let seqs: Vec<u64> = batch.iter().map(|envelope| envelope.seq).collect();
+        println!("Poll from #{} answered after {:>9?} with {:?}", cursor, started.elapsed(), seqs);
+        for envelope in &batch {
+            println!("  #{} {}", envelope.seq, envelope.event);
+        }
+        if let Some(last) = batch.last() {
+            cursor = last.seq;
+        }
+    }
+
+    producer.await.unwrap();
+    drop(tx);
+    ctx.shutdown().await;
+}
+
+// Deposits counted per account, noting any event seen twice or skipped
+#[derive(Default, Serialize, Deserialize)]
+struct DepositTally {
+    counts: BTreeMap<String, u32>,
+    last_seq: u64,
+    duplicates: u32,
+    gaps: u32,
+}
+
+impl Projection<BankEvent> for DepositTally {
+    fn apply(&mut self, envelope: &Envelope<BankEvent>) -> Result<(), String> {
+        // Only deposits are published here, so sequence numbers are contiguous
+        if envelope.seq <= self.last_seq {
+            self.duplicates += 1;
+            return Ok(());
+        }
+        if envelope.seq != self.last_seq + 1 {
+            self.gaps += 1;
+        }
+        self.last_seq = envelope.seq;
+        if let BankEvent::Deposited { account, .. } = &envelope.event {
+            *self.counts.entry(account.clone()).or_insert(0) += 1;
+        }
+        Ok(())
+    }
+}
+
+// Start the tally from its last checkpoint, or from scratch
+async fn resume_tally(tx: &MeteredSender<BankMessage>, store: &CheckpointStore, policy: RetryPolicy) -> JoinHandle<DepositTally> {
+    let (seq, tally): (u64, DepositTally) = store.load("deposit-tally").unwrap().unwrap_or_default();
+    println!("Tally starting from #{} with {} deposit(s) counted", seq, tally.counts.values().sum::<u32>());
+    let events = resubscribe(tx, "account.*.deposit", seq).await.unwrap();
+    tokio::spawn(run_checkpointed("deposit-tally".to_string(), tally, seq, events, policy, DeadLetters::default(), store.clone()))
+}
+
+async fn run_checkpoint_example(cfg: Config) {
+    println!("\n=== Checkpoint Example (Restarting a Projection Mid-stream) ===");
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 100)
+        .account("Bob", 50)
+        .manager_delay(cfg.work_delay / 20)
+        .build()
+        .await;
+    let tx = ctx.bank().clone();
+    let store = CheckpointStore::new(std::env::temp_dir(), 5);
+    store.remove("deposit-tally");
+    let policy = RetryPolicy { capacity: 8, max_attempts: 3, retry_every: cfg.work_delay / 10 };
+
+    // Deposits keep flowing while the tally is killed and restarted
+    let producer_tx = tx.clone();
+    let delay = cfg.work_delay / 20;
+    let producer = tokio::spawn(async move {
+        for i in 0..40 {
+            let account = if i % 2 == 0 { "Alice" } else { "Bob" };
+            deposit(&producer_tx, account, 1).await.unwrap();
+            sleep(delay).await;
+        }
+    });
+
+    let tally = resume_tally(&tx, &store, policy).await;
+    sleep(cfg.work_delay).await;
+    // Whatever it applied since its last checkpoint is lost with it
+    tally.abort();
+    println!("Killed the tally mid-stream");
+    sleep(cfg.work_delay / 2).await;
+    let tally = resume_tally(&tx, &store, policy).await;
+
+    producer.await.unwrap();
+    drop(tx);
+    ctx.shutdown().await;
+    let tally = tally.await.unwrap();
+
+    for (account, count) in &tally.counts {
+        println!("{}: {} of 20 deposits counted", account, count);
+    }
+    println!("Up to #{} with {} duplicate(s) and {} gap(s)", tally.last_seq, tally.duplicates, tally.gaps);
+}
+
+pub fn scenarios() -> Vec<Box<dyn Scenario>> {
+    vec![
+        scenario("topic-router", "Events fanned out to subscribers by topic pattern", run_topic_router_example),
+        scenario("catch-up", "A reconnecting subscriber replaying missed events before live ones", run_catch_up_example),
+        scenario("dead-letters", "Failed projection updates retried, then dead-lettered", run_dead_letter_example),
+        scenario("notifications", "Large payment notifications, once per committed transaction", run_notification_example),
+        scenario("sub-accounts", "Parent accounts rolling up their sub-accounts' balances", run_sub_account_example),
+        scenario("long-poll", "Polling an account's events, held open until one arrives", run_long_poll_example),
+        scenario("checkpoints", "A projection killed mid-stream resuming from its checkpoint", run_checkpoint_example),
+    ]
+}
@@ -0,0 +1,112 @@
+use std::fmt;
+
+use crate::account_id::{AccountId, AccountName};
+
+// How one part of a transfer fee is worked out from the amount sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FeeRule {
+    Flat(i32),
+    // In hundredths of a percent of the amount, rounded up
+    Percentage { basis_points: i32 },
+    // Bands of (upper bound, basis points) in ascending order, each charged
+    // on the part of the amount inside it, like tax brackets. A band without
+    // a bound takes everything above the one before.
+    Tiered(Vec<(Option<i32>, i32)>),
+}
+
+impl FeeRule {
+    fn label(&self) -> String {
+        match self {
+            FeeRule::Flat(_) => "flat".to_string(),
+            FeeRule::Percentage { basis_points } => format!("{}.{:02}%", basis_points / 100, basis_points % 100),
+            FeeRule::Tiered(_) => "tiered".to_string(),
+        }
+    }
+
+    fn charge(&self, amount: i32) -> i64 {
+        match self {
+            FeeRule::Flat(fee) => *fee as i64,
+            FeeRule::Percentage { basis_points } => percent_of(amount as i64, *basis_points),
+            FeeRule::Tiered(bands) => {
+                let (mut fee, mut lower) = (0, 0);
+                for &(upper, basis_points) in bands {
+                    let top = upper.map_or(amount, |upper| upper.min(amount)) as i64;
+                    if top > lower {
+                        fee += percent_of(top - lower, basis_points);
+                    }
+                    match upper {
+                        Some(upper) if upper < amount => lower = upper as i64,
+                        _ => break,
+                    }
+                }
+                fee
+            },
+        }
+    }
+}
+
+fn percent_of(amount: i64, basis_points: i32) -> i64 {
+    (amount * basis_points as i64 + 9_999) / 10_000
+}
+
+// What transfers cost and where the fees go. The fees account is an
+// ordinary open account, so what's been collected is just its balance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeSchedule<A: AccountId = AccountName> {
+    pub account: A,
+    // Charged together; the fee is their sum
+    pub rules: Vec<FeeRule>,
+}
+
+impl<A: AccountId> FeeSchedule<A> {
+    pub fn validate(&self) -> Result<(), &'static str> {
+        for rule in &self.rules {
+            let ascending = match rule {
+                FeeRule::Flat(fee) if *fee < 0 => return Err("Fees can't be negative"),
+                FeeRule::Percentage { basis_points } if *basis_points < 0 => return Err("Fees can't be negative"),
+                FeeRule::Tiered(bands) => {
+                    if bands.iter().any(|(_, basis_points)| *basis_points < 0) {
+                        return Err("Fees can't be negative");
+                    }
+                    let bounds: Vec<Option<i32>> = bands.iter().map(|(upper, _)| *upper).collect();
+                    // Unbounded sorts last, so only the last band may be
+                    bounds.windows(2).all(|pair| pair[0].is_some() && pair[1].is_none_or(|upper| Some(upper) > pair[0]))
+                },
+                _ => true,
+            };
+            if !ascending {
+                return Err("Fee bands must be in ascending order");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn quote(&self, amount: i32) -> FeeBreakdown {
+        let items: Vec<(String, i32)> = self
+            .rules
+            .iter()
+            .map(|rule| (rule.label(), rule.charge(amount).min(i32::MAX as i64) as i32))
+            .filter(|(_, fee)| *fee > 0)
+            .collect();
+        let total = items.iter().fold(0i32, |total, (_, fee)| total.saturating_add(*fee));
+        FeeBreakdown { items, total }
+    }
+}
+
+// A transfer's fee, rule by rule
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FeeBreakdown {
+    pub items: Vec<(String, i32)>,
+    pub total: i32,
+}
+
+impl fmt::Display for FeeBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.total)?;
+        if !self.items.is_empty() {
+            let items: Vec<String> = self.items.iter().map(|(label, fee)| format!("{} {}", label, fee)).collect();
+            write!(f, " ({})", items.join(" + "))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+
+use crate::bank::{BankError, BankEvent};
+use crate::blocking::BlockingBank;
+use crate::pubsub::Envelope;
+
+// C API over the bank core, for embedding from C, Python (ctypes/cffi) and
+// the like. `include/demo_bank.h` is generated from this file:
+//
+//     cbindgen --config cbindgen.toml --output include/demo_bank.h
+//
+// Ownership rules:
+// - A service comes from `bank_service_new` and goes back to
+//   `bank_service_free`, exactly once.
+// - Strings passed in are borrowed for the call only and must be valid
+//   UTF-8 and NUL-terminated.
+// - Strings handed out by `bank_poll_event` belong to the caller, who
+//   frees them with `bank_string_free`, never with `free`.
+// - `bank_status_message` returns static strings that are never freed.
+// A service may be used from one thread at a time, and never from inside
+// a Tokio runtime. Panics abort rather than unwind into the caller.
+// Exported items are commented with `///` so cbindgen copies the comments
+// into the header.
+
+/// Returned by every call that can fail
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BankStatus {
+    Ok = 0,
+    /// A null pointer, or a string that isn't UTF-8
+    InvalidArgument = 1,
+    AccountNotFound = 2,
+    /// Refused by the ledger, e.g. insufficient funds
+    Rejected = 3,
+    ReadOnly = 4,
+    Unavailable = 5,
+    /// `bank_poll_event` found nothing waiting
+    NoEvent = 6,
+    Failed = 7,
+}
+
+impl From<BankError> for BankStatus {
+    fn from(error: BankError) -> Self {
+        match error {
+            BankError::Rejected("Account not found") => BankStatus::AccountNotFound,
+            BankError::Rejected(_) => BankStatus::Rejected,
+            BankError::ReadOnly => BankStatus::ReadOnly,
+            BankError::ManagerUnavailable => BankStatus::Unavailable,
+            _ => BankStatus::Failed,
+        }
+    }
+}
+
+/// Opaque to C
+pub struct BankService {
+    bank: BlockingBank,
+    events: mpsc::Receiver<Envelope<BankEvent>>,
+}
+
+// Borrow a C string for the length of a call
+unsafe fn borrowed<'a>(string: *const c_char) -> Result<&'a str, BankStatus> {
+    if string.is_null() {
+        return Err(BankStatus::InvalidArgument);
+    }
+    CStr::from_ptr(string).to_str().map_err(|_| BankStatus::InvalidArgument)
+}
+
+/// Start a service with `count` accounts: `accounts[i]` opens with
+/// `balances[i]`. Returns null if an argument is invalid or the service's
+/// runtime can't start.
+///
+/// # Safety
+/// `accounts` and `balances` must each point to `count` valid elements
+#[no_mangle]
+pub unsafe extern "C" fn bank_service_new(
+    accounts: *const *const c_char,
+    balances: *const i32,
+    count: usize,
+) -> *mut BankService {
+    if count > 0 && (accounts.is_null() || balances.is_null()) {
+        return ptr::null_mut();
+    }
+    let mut opening = HashMap::new();
+    for i in 0..count {
+        let Ok(account) = borrowed(*accounts.add(i)) else {
+            return ptr::null_mut();
+        };
+        opening.insert(account.to_string(), *balances.add(i));
+    }
+
+    let Ok(bank) = BlockingBank::start(opening, Duration::ZERO) else {
+        return ptr::null_mut();
+    };
+    let Ok(events) = bank.subscribe("*") else {
+        bank.shutdown();
+        return ptr::null_mut();
+    };
+    Box::into_raw(Box::new(BankService { bank, events }))
+}
+
+/// Stop the service, which checks its books first. Null is ignored.
+///
+/// # Safety
+/// `service` must come from `bank_service_new` and not be used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn bank_service_free(service: *mut BankService) {
+    if service.is_null() {
+        return;
+    }
+    let service = Box::from_raw(service);
+    drop(service.events);
+    service.bank.shutdown();
+}
+
+/// On success the new balance goes in `balance`, if that isn't null
+///
+/// # Safety
+/// `service` must be live, `account` a valid C string, `balance` null or
+/// writable
+#[no_mangle]
+pub unsafe extern "C" fn bank_deposit(
+    service: *mut BankService,
+    account: *const c_char,
+    amount: i32,
+    balance: *mut i32,
+) -> BankStatus {
+    let Some(service) = service.as_ref() else {
+        return BankStatus::InvalidArgument;
+    };
+    let account = match borrowed(account) {
+        Ok(account) => account,
+        Err(status) => return status,
+    };
+    respond(service.bank.deposit(account, amount), balance)
+}
+
+/// # Safety
+/// As for `bank_deposit`
+#[no_mangle]
+pub unsafe extern "C" fn bank_get_balance(
+    service: *mut BankService,
+    account: *const c_char,
+    balance: *mut i32,
+) -> BankStatus {
+    let Some(service) = service.as_ref() else {
+        return BankStatus::InvalidArgument;
+    };
+    let account = match borrowed(account) {
+        Ok(account) => account,
+        Err(status) => return status,
+    };
+    respond(service.bank.balance(account), balance)
+}
+
+unsafe fn respond(result: Result<i32, BankError>, out: *mut i32) -> BankStatus {
+    match result {
+        Ok(value) => {
+            if let Some(out) = out.as_mut() {
+                *out = value;
+            }
+            BankStatus::Ok
+        },
+        Err(error) => error.into(),
+    }
+}
+
+/// Take the next event without waiting, as a line of the form
+/// "<seq>\t<topic>\t<event>". Answers NoEvent when nothing is waiting. The
+/// string is the caller's, to free with `bank_string_free`.
+///
+/// # Safety
+/// `service` must be live and `event` writable
+#[no_mangle]
+pub unsafe extern "C" fn bank_poll_event(service: *mut BankService, event: *mut *mut c_char) -> BankStatus {
+    let (Some(service), false) = (service.as_mut(), event.is_null()) else {
+        return BankStatus::InvalidArgument;
+    };
+    let Ok(envelope) = service.events.try_recv() else {
+        return BankStatus::NoEvent;
+    };
+    let line = format!("{}\t{}\t{:?}", envelope.seq, envelope.topic, envelope.event);
+    // Names came in as C strings, so this only guards against NULs from elsewhere
+    *event = CString::new(line.replace('\0', " ")).expect("NULs replaced").into_raw();
+    BankStatus::Ok
+}
+
+/// Null is ignored
+///
+/// # Safety
+/// `string` must come from this library and not be used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn bank_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// A static description of a status; never freed
+#[no_mangle]
+pub extern "C" fn bank_status_message(status: BankStatus) -> *const c_char {
+    let message: &'static CStr = match status {
+        BankStatus::Ok => c"ok",
+        BankStatus::InvalidArgument => c"invalid argument",
+        BankStatus::AccountNotFound => c"account not found",
+        BankStatus::Rejected => c"rejected by the ledger",
+        BankStatus::ReadOnly => c"bank is in read-only mode",
+        BankStatus::Unavailable => c"bank manager unavailable",
+        BankStatus::NoEvent => c"no event waiting",
+        BankStatus::Failed => c"request failed",
+    };
+    message.as_ptr()
+}
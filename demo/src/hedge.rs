@@ -0,0 +1,119 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tokio::time::{sleep, Duration, Instant};
+
+use crate::bank::{BankError, BankMessage};
+use crate::client;
+use crate::metrics::{self, MeteredSender};
+
+// Read latencies kept for working out the hedge delay
+const WINDOW: usize = 128;
+// Fewer than this and the initial delay is used instead
+const MIN_SAMPLES: usize = 20;
+const HEDGE_PERCENTILE: usize = 95;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HedgeStats {
+    pub reads: u64,
+    // Reads that sent a second request after the first was slow to answer
+    pub hedged: u64,
+    // Hedges that answered first
+    pub won: u64,
+    // Hedges the first request beat anyway: work done for nothing
+    pub wasted: u64,
+}
+
+impl HedgeStats {
+    pub fn hedge_rate(&self) -> f64 {
+        self.hedged as f64 / self.reads.max(1) as f64
+    }
+}
+
+#[derive(Default)]
+struct Recorded {
+    latencies: VecDeque<Duration>,
+    stats: HedgeStats,
+}
+
+// Balance reads spread over replicas holding the same books. A read that
+// hasn't been answered by the 95th percentile of recent reads is sent to
+// the next replica as well, and whichever answers first is used, so one
+// replica stuck behind slow work doesn't set the tail latency. The other
+// request still runs; that's the cost, counted as wasted.
+pub struct HedgedReader {
+    replicas: Vec<MeteredSender<BankMessage>>,
+    // Hedge delay until enough reads have been seen
+    initial: Duration,
+    next: AtomicUsize,
+    recorded: Mutex<Recorded>,
+}
+
+impl HedgedReader {
+    // Panics without replicas
+    pub fn new(replicas: Vec<MeteredSender<BankMessage>>, initial: Duration) -> Self {
+        assert!(!replicas.is_empty(), "hedged reads need a replica");
+        HedgedReader {
+            replicas,
+            initial,
+            next: AtomicUsize::new(0),
+            recorded: Mutex::new(Recorded::default()),
+        }
+    }
+
+    // How long a read waits before it's hedged
+    pub fn hedge_delay(&self) -> Duration {
+        let recorded = self.recorded.lock().unwrap();
+        if recorded.latencies.len() < MIN_SAMPLES {
+            return self.initial;
+        }
+        let mut latencies: Vec<Duration> = recorded.latencies.iter().copied().collect();
+        latencies.sort();
+        latencies[(latencies.len() - 1) * HEDGE_PERCENTILE / 100]
+    }
+
+    pub async fn balance(&self, account: &str) -> Result<i32, BankError> {
+        let first = self.next.fetch_add(1, Ordering::Relaxed) % self.replicas.len();
+        let second = (first + 1) % self.replicas.len();
+        let started = Instant::now();
+        let delay = self.hedge_delay();
+
+        let primary = client::balance(&self.replicas[first], account);
+        tokio::pin!(primary);
+        let (result, hedge) = tokio::select! {
+            result = &mut primary => (result, None),
+            _ = sleep(delay), if second != first => {
+                tokio::select! {
+                    result = &mut primary => (result, Some(false)),
+                    result = client::balance(&self.replicas[second], account) => (result, Some(true)),
+                }
+            },
+        };
+        self.record(started.elapsed(), hedge);
+        result
+    }
+
+    pub fn stats(&self) -> HedgeStats {
+        self.recorded.lock().unwrap().stats
+    }
+
+    // `hedge` is whether the hedge won, for hedged reads
+    fn record(&self, latency: Duration, hedge: Option<bool>) {
+        let mut recorded = self.recorded.lock().unwrap();
+        if recorded.latencies.len() == WINDOW {
+            recorded.latencies.pop_front();
+        }
+        recorded.latencies.push_back(latency);
+
+        let stats = &mut recorded.stats;
+        stats.reads += 1;
+        match hedge {
+            Some(true) => stats.won += 1,
+            Some(false) => stats.wasted += 1,
+            None => {},
+        }
+        stats.hedged = stats.won + stats.wasted;
+        metrics::set_gauge("hedged_reads", stats.hedged);
+        metrics::set_gauge("hedges_wasted", stats.wasted);
+    }
+}
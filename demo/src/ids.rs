@@ -0,0 +1,149 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+// Identifier for transactions, events and requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Id {
+    Uuid(Uuid),
+    Numeric(u64),
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Id::Uuid(uuid) => write!(f, "{}", uuid),
+            Id::Numeric(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+pub trait IdGenerator: Send + Sync {
+    fn next_id(&self) -> Id;
+}
+
+// Time-ordered random UUIDs, unique without any coordination
+pub struct UuidV7Ids;
+
+impl IdGenerator for UuidV7Ids {
+    fn next_id(&self) -> Id {
+        Id::Uuid(Uuid::now_v7())
+    }
+}
+
+// 64-bit IDs laid out as 41 bits of milliseconds since 2024-01-01,
+// 10 bits of node ID and a 12 bit per-millisecond sequence
+pub struct SnowflakeIds {
+    node: u64,
+    // (millisecond, sequence) of the last ID handed out
+    last: Mutex<(u64, u64)>,
+}
+
+impl SnowflakeIds {
+    const EPOCH_MILLIS: u64 = 1_704_067_200_000;
+    const NODE_BITS: u64 = 10;
+    const SEQUENCE_BITS: u64 = 12;
+
+    pub fn new(node: u16) -> Self {
+        SnowflakeIds {
+            node: u64::from(node) & ((1 << Self::NODE_BITS) - 1),
+            last: Mutex::new((0, 0)),
+        }
+    }
+
+    fn now_millis() -> u64 {
+        let since_unix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        (since_unix.as_millis() as u64).saturating_sub(Self::EPOCH_MILLIS)
+    }
+}
+
+impl IdGenerator for SnowflakeIds {
+    fn next_id(&self) -> Id {
+        let mut last = self.last.lock().unwrap();
+        let mut millis = Self::now_millis().max(last.0);
+        let mut sequence = if millis == last.0 { last.1 + 1 } else { 0 };
+
+        // Sequence exhausted for this millisecond: wait for the next one
+        while sequence >= 1 << Self::SEQUENCE_BITS {
+            std::hint::spin_loop();
+            let now = Self::now_millis();
+            if now > millis {
+                millis = now;
+                sequence = 0;
+            }
+        }
+
+        *last = (millis, sequence);
+        Id::Numeric(
+            millis << (Self::NODE_BITS + Self::SEQUENCE_BITS) | self.node << Self::SEQUENCE_BITS | sequence,
+        )
+    }
+}
+
+// 1, 2, 3, ... - predictable output for tests and teaching
+pub struct SequentialIds {
+    next: AtomicU64,
+}
+
+impl SequentialIds {
+    pub fn new() -> Self {
+        SequentialIds { next: AtomicU64::new(1) }
+    }
+}
+
+impl Default for SequentialIds {
+    fn default() -> Self {
+        SequentialIds::new()
+    }
+}
+
+impl IdGenerator for SequentialIds {
+    fn next_id(&self) -> Id {
+        Id::Numeric(self.next.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdKind {
+    Sequential,
+    Snowflake,
+    UuidV7,
+}
+
+impl IdKind {
+    pub fn generator(self) -> Box<dyn IdGenerator> {
+        match self {
+            IdKind::Sequential => Box::new(SequentialIds::new()),
+            IdKind::Snowflake => Box::new(SnowflakeIds::new(1)),
+            IdKind::UuidV7 => Box::new(UuidV7Ids),
+        }
+    }
+}
+
+impl FromStr for IdKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sequential" => Ok(IdKind::Sequential),
+            "snowflake" => Ok(IdKind::Snowflake),
+            "uuid" => Ok(IdKind::UuidV7),
+            _ => Err(format!("Unknown ID kind: {} (expected sequential, snowflake or uuid)", s)),
+        }
+    }
+}
+
+// Process-wide generator shared by the ledger, the event router and clients
+static GENERATOR: OnceLock<Box<dyn IdGenerator>> = OnceLock::new();
+
+// Select the generator once at startup; later calls are ignored
+pub fn install(generator: Box<dyn IdGenerator>) {
+    let _ = GENERATOR.set(generator);
+}
+
+pub fn next_id() -> Id {
+    GENERATOR.get_or_init(|| Box::new(SequentialIds::new())).next_id()
+}
@@ -0,0 +1,180 @@
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio::time::Duration;
+
+use crate::bank::{run_bank_manager, BankMessage};
+use crate::client;
+use crate::metrics::{channel_with_metrics, MeteredSender};
+
+#[derive(Debug, Deserialize)]
+pub struct ImportRow {
+    pub account: String,
+    pub balance: i32,
+}
+
+// A row that was not imported, with its line (CSV) or position (JSON)
+#[derive(Debug)]
+pub struct RowError {
+    pub line: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub errors: Vec<RowError>,
+}
+
+type ParsedRow = (usize, Result<ImportRow, String>);
+
+// Accepts CSV (`account,balance` per line, header optional) or a JSON array
+// of `{"account": ..., "balance": ...}` objects
+pub fn parse(input: &str) -> Vec<ParsedRow> {
+    if input.trim_start().starts_with('[') {
+        parse_json(input)
+    } else {
+        parse_csv(input)
+    }
+}
+
+fn parse_csv(input: &str) -> Vec<ParsedRow> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .filter(|(number, line)| !(*number == 1 && line.eq_ignore_ascii_case("account,balance")))
+        .map(|(number, line)| (number, parse_csv_row(line)))
+        .collect()
+}
+
+fn parse_csv_row(line: &str) -> Result<ImportRow, String> {
+    let (account, balance) = line
+        .split_once(',')
+        .ok_or_else(|| "expected account,balance".to_string())?;
+    let account = account.trim();
+    if account.is_empty() {
+        return Err("missing account name".to_string());
+    }
+    let balance = balance
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid balance {:?}", balance.trim()))?;
+
+    Ok(ImportRow { account: account.to_string(), balance })
+}
+
+// Each element is converted on its own, so one bad row doesn't take the
+// rest of the file with it. Only a document that isn't an array at all is
+// a single error.
+fn parse_json(input: &str) -> Vec<ParsedRow> {
+    match serde_json::from_str::<Vec<serde_json::Value>>(input) {
+        Ok(values) => values
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| (i + 1, serde_json::from_value(value).map_err(|e| format!("invalid row: {}", e))))
+            .collect(),
+        Err(e) => vec![(e.line(), Err(format!("invalid JSON: {}", e)))],
+    }
+}
+
+// Open every valid row as an account with at most `concurrency` requests in
+// flight, printing progress as rows complete. Rows are checked for negative
+// balances and duplicates within the input before anything is sent; the
+// manager rejects accounts that already exist.
+pub async fn import(
+    tx: &MeteredSender<BankMessage>,
+    rows: Vec<ParsedRow>,
+    concurrency: usize,
+) -> ImportReport {
+    let total = rows.len();
+    let step = (total / 10).max(1);
+    let completed = Arc::new(AtomicUsize::new(0));
+    let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+
+    let mut report = ImportReport::default();
+    let mut seen = HashSet::new();
+    let mut pending = JoinSet::new();
+
+    for (line, row) in rows {
+        let row = row.and_then(|row| {
+            if row.balance < 0 {
+                Err(format!("negative opening balance {} for {}", row.balance, row.account))
+            } else if !seen.insert(row.account.clone()) {
+                Err(format!("duplicate account {}", row.account))
+            } else {
+                Ok(row)
+            }
+        });
+        let row = match row {
+            Ok(row) => row,
+            Err(reason) => {
+                report.errors.push(RowError { line, reason });
+                completed.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+        };
+
+        // Wait for a free slot before sending the next row
+        let permit = Arc::clone(&permits).acquire_owned().await.unwrap();
+        let tx = tx.clone();
+        let completed = Arc::clone(&completed);
+        pending.spawn(async move {
+            let _permit = permit;
+            let result = client::open_account(&tx, &row.account, row.balance).await;
+
+            let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
+            if done.is_multiple_of(step) || done == total {
+                println!("Imported {}/{} rows", done, total);
+            }
+            (line, result)
+        });
+    }
+
+    while let Some(joined) = pending.join_next().await {
+        match joined.unwrap() {
+            (_, Ok(())) => report.imported += 1,
+            (line, Err(e)) => report.errors.push(RowError { line, reason: e.to_string() }),
+        }
+    }
+    report.errors.sort_by_key(|error| error.line);
+    report
+}
+
+pub fn print_report(report: &ImportReport) {
+    println!("Imported {} accounts, {} rows rejected", report.imported, report.errors.len());
+    for error in &report.errors {
+        println!("  line {:>4}: {}", error.line, error.reason);
+    }
+}
+
+// `demo import`: load accounts into a fresh manager and report the result
+pub async fn run_import(input: &str, concurrency: usize) {
+    let (tx, rx) = channel_with_metrics("bank", 32);
+    let manager = tokio::spawn(run_bank_manager(rx, HashMap::new(), Duration::ZERO));
+
+    let report = import(&tx, parse(input), concurrency).await;
+    print_report(&report);
+
+    println!("Total opening balance: {}", client::total_balance(&tx).await.unwrap());
+
+    drop(tx);
+    manager.await.unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_json_rows_are_reported_on_their_own() {
+        let rows = parse(r#"[{"account": "Alice", "balance": 100}, {"account": "Bob"}, {"account": "Carol", "balance": 5}]"#);
+        let accepted: Vec<usize> = rows.iter().filter(|(_, row)| row.is_ok()).map(|(line, _)| *line).collect();
+        assert_eq!(accepted, vec![1, 3]);
+        assert!(matches!(&rows[1], (2, Err(reason)) if reason.contains("balance")));
+    }
+}
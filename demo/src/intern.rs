@@ -0,0 +1,36 @@
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+// One shared allocation per distinct account name. Names are never freed, so
+// the set grows with the number of accounts ever seen, not with traffic.
+static NAMES: Mutex<BTreeSet<Arc<str>>> = Mutex::new(BTreeSet::new());
+
+// Lookups answered with an existing allocation
+static REUSED: AtomicU64 = AtomicU64::new(0);
+
+pub fn intern(name: &str) -> Arc<str> {
+    let mut names = NAMES.lock().unwrap();
+    if let Some(existing) = names.get(name) {
+        REUSED.fetch_add(1, Ordering::Relaxed);
+        return Arc::clone(existing);
+    }
+    let interned: Arc<str> = Arc::from(name);
+    names.insert(Arc::clone(&interned));
+    interned
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct InternStats {
+    // Distinct names, i.e. allocations made
+    pub names: usize,
+    // Allocations avoided
+    pub reused: u64,
+}
+
+pub fn stats() -> InternStats {
+    InternStats {
+        names: NAMES.lock().unwrap().len(),
+        reused: REUSED.load(Ordering::Relaxed),
+    }
+}
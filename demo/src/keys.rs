@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::bank::{BankError, BankMessage};
+use crate::client;
+use crate::metrics::{channel_with_metrics, MeteredSender};
+
+// Kinds of request a key can be allowed to make
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Read,
+    Deposit,
+    Transfer,
+}
+
+// What a key may do, and to which accounts. No account list means any.
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub operations: Vec<Operation>,
+    pub accounts: Option<Vec<String>>,
+}
+
+impl Scope {
+    fn allows(&self, operation: Operation, account: &str) -> bool {
+        let account_allowed = match &self.accounts {
+            Some(accounts) => accounts.iter().any(|a| a == account),
+            None => true,
+        };
+        self.operations.contains(&operation) && account_allowed
+    }
+}
+
+// Requests made with a key since it was issued
+#[derive(Debug, Clone, Default)]
+pub struct KeyUsage {
+    pub allowed: u64,
+    pub denied: u64,
+}
+
+struct ApiKey {
+    client: String,
+    scope: Scope,
+    revoked: bool,
+    usage: KeyUsage,
+}
+
+#[derive(Debug)]
+pub enum KeyMessage {
+    // Admin: issue a key for a client; answers with the key
+    Create {
+        client: String,
+        scope: Scope,
+        respond_to: oneshot::Sender<String>
+    },
+    // Admin: stop accepting a key; answers false for unknown keys
+    Revoke {
+        key: String,
+        respond_to: oneshot::Sender<bool>
+    },
+    // Check one operation on one account, counting it in the key's usage
+    Authorize {
+        key: String,
+        operation: Operation,
+        account: String,
+        respond_to: oneshot::Sender<Result<(), BankError>>
+    },
+    // Admin: usage of every key, by client, sorted by client name
+    Usage {
+        respond_to: oneshot::Sender<Vec<(String, KeyUsage)>>
+    },
+}
+
+// Key actor: owns every key, so a revocation takes effect for the very
+// next request
+pub async fn run_key_manager(mut rx: mpsc::Receiver<KeyMessage>) {
+    let mut keys: HashMap<String, ApiKey> = HashMap::new();
+
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            KeyMessage::Create { client, scope, respond_to } => {
+                // Mostly random bits, unlike the configurable transaction IDs
+                let key = format!("key_{}", uuid::Uuid::now_v7().simple());
+                keys.insert(key.clone(), ApiKey { client, scope, revoked: false, usage: KeyUsage::default() });
+                let _ = respond_to.send(key);
+            },
+            KeyMessage::Revoke { key, respond_to } => {
+                let found = match keys.get_mut(&key) {
+                    Some(api_key) => {
+                        api_key.revoked = true;
+                        true
+                    },
+                    None => false,
+                };
+                let _ = respond_to.send(found);
+            },
+            KeyMessage::Authorize { key, operation, account, respond_to } => {
+                let result = match keys.get_mut(&key) {
+                    None => Err(BankError::Unauthorized("Unknown API key")),
+                    Some(api_key) => {
+                        let result = if api_key.revoked {
+                            Err(BankError::Unauthorized("API key revoked"))
+                        } else if !api_key.scope.allows(operation, &account) {
+                            Err(BankError::Unauthorized("Outside the key's scope"))
+                        } else {
+                            Ok(())
+                        };
+                        match result {
+                            Ok(()) => api_key.usage.allowed += 1,
+                            Err(_) => api_key.usage.denied += 1,
+                        }
+                        result
+                    }
+                };
+                let _ = respond_to.send(result);
+            },
+            KeyMessage::Usage { respond_to } => {
+                let mut usage: Vec<(String, KeyUsage)> = keys
+                    .values()
+                    .map(|api_key| (api_key.client.clone(), api_key.usage.clone()))
+                    .collect();
+                usage.sort_by_key(|(client, _)| client.clone());
+                let _ = respond_to.send(usage);
+            },
+        }
+    }
+}
+
+pub fn spawn_key_manager() -> MeteredSender<KeyMessage> {
+    let (tx, rx) = channel_with_metrics("api-keys", 32);
+    tokio::spawn(run_key_manager(rx));
+    tx
+}
+
+// Client-side handle holding one key: like `QuotaGate`, every request is
+// checked with the key actor before it reaches the bank manager
+#[derive(Clone)]
+pub struct KeyedClient {
+    key: String,
+    keys: MeteredSender<KeyMessage>,
+    bank: MeteredSender<BankMessage>,
+}
+
+impl KeyedClient {
+    pub fn new(key: &str, keys: &MeteredSender<KeyMessage>, bank: &MeteredSender<BankMessage>) -> Self {
+        KeyedClient {
+            key: key.to_string(),
+            keys: keys.clone(),
+            bank: bank.clone(),
+        }
+    }
+
+    pub async fn balance(&self, account: &str) -> Result<i32, BankError> {
+        self.authorize(Operation::Read, account).await?;
+        client::balance(&self.bank, account).await
+    }
+
+    pub async fn deposit(&self, account: &str, amount: i32) -> Result<i32, BankError> {
+        self.authorize(Operation::Deposit, account).await?;
+        client::deposit(&self.bank, account, amount).await
+    }
+
+    // Scoped by the paying account
+    pub async fn transfer(&self, from: &str, to: &str, amount: i32) -> Result<i32, BankError> {
+        self.authorize(Operation::Transfer, from).await?;
+        client::transfer(&self.bank, from, to, amount).await
+    }
+
+    async fn authorize(&self, operation: Operation, account: &str) -> Result<(), BankError> {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        self.keys.send(KeyMessage::Authorize {
+            key: self.key.clone(),
+            operation,
+            account: account.to_string(),
+            respond_to: resp_tx,
+        }).await.map_err(|_| BankError::ManagerUnavailable)?;
+        resp_rx.await.map_err(|_| BankError::ManagerUnavailable)?
+    }
+}
+
+// Operator calls. A key manager that has stopped, or dropped the request,
+// is unavailable, the same as a stopped bank manager.
+pub async fn create(keys: &MeteredSender<KeyMessage>, client: &str, scope: Scope) -> Result<String, BankError> {
+    let (resp_tx, resp_rx) = oneshot::channel();
+    keys.send(KeyMessage::Create { client: client.to_string(), scope, respond_to: resp_tx })
+        .await
+        .map_err(|_| BankError::ManagerUnavailable)?;
+    resp_rx.await.map_err(|_| BankError::ManagerUnavailable)
+}
+
+pub async fn revoke(keys: &MeteredSender<KeyMessage>, key: &str) -> Result<bool, BankError> {
+    let (resp_tx, resp_rx) = oneshot::channel();
+    keys.send(KeyMessage::Revoke { key: key.to_string(), respond_to: resp_tx })
+        .await
+        .map_err(|_| BankError::ManagerUnavailable)?;
+    resp_rx.await.map_err(|_| BankError::ManagerUnavailable)
+}
+
+pub async fn usage(keys: &MeteredSender<KeyMessage>) -> Result<Vec<(String, KeyUsage)>, BankError> {
+    let (resp_tx, resp_rx) = oneshot::channel();
+    keys.send(KeyMessage::Usage { respond_to: resp_tx }).await.map_err(|_| BankError::ManagerUnavailable)?;
+    resp_rx.await.map_err(|_| BankError::ManagerUnavailable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn a_stopped_key_manager_is_unavailable() {
+        let (keys, rx) = channel_with_metrics("api-keys", 1);
+        drop(rx);
+        let scope = Scope { operations: vec![Operation::Read], accounts: None };
+        assert_eq!(create(&keys, "kiosk", scope).await, Err(BankError::ManagerUnavailable));
+        assert_eq!(revoke(&keys, "key").await, Err(BankError::ManagerUnavailable));
+        assert!(matches!(usage(&keys).await, Err(BankError::ManagerUnavailable)));
+    }
+}
@@ -0,0 +1,1323 @@
+use chrono::{DateTime, Local};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::mem::size_of;
+use std::time::Duration;
+
+use crate::account_id::{AccountId, AccountKey, AccountName, Internal};
+use crate::chain::{ChainHash, Receipt};
+use crate::clock::{HybridClock, Timestamp};
+use crate::currency::{Currency, Rates};
+use crate::fees::{FeeBreakdown, FeeSchedule};
+use crate::ids::{self, Id};
+
+// Contra account standing in for money entering and leaving the bank
+pub const CASH_ACCOUNT: &str = "Cash";
+// Contra account on the other side of currency conversions, one balance per
+// currency, so converting keeps every currency's books balanced
+pub const FX_ACCOUNT: &str = "FX";
+// Holds a best-effort payroll's money between the debit and the credits;
+// back at zero once the run finishes
+pub const CLEARING_ACCOUNT: &str = "Clearing";
+
+// The bank's own accounts, never listed or operated on by customers
+pub fn is_internal(account: &str) -> bool {
+    account == CASH_ACCOUNT || account == FX_ACCOUNT || account == CLEARING_ACCOUNT
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Debit,
+    Credit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    Opening,
+    Deposit,
+    Transfer,
+    // Transfer converted from one currency into another
+    Exchange,
+    // Transfer between a parent account and one of its children: money
+    // moves inside the family, and the parent's roll-up doesn't change
+    Move,
+    // Net effect of older entries rolled up by compaction
+    Summary,
+}
+
+#[derive(Debug, Clone)]
+pub struct Posting<A: AccountId = AccountName> {
+    pub account: A,
+    pub currency: Currency,
+    pub side: Side,
+    pub amount: i32,
+}
+
+impl<A: AccountId> Posting<A> {
+    // Postings are in the base currency unless moved with `in_currency`
+    pub fn debit<Q: AccountKey<A> + ?Sized>(account: &Q, amount: i32) -> Self {
+        Posting { account: account.to_id(), currency: Currency::BASE, side: Side::Debit, amount }
+    }
+
+    pub fn credit<Q: AccountKey<A> + ?Sized>(account: &Q, amount: i32) -> Self {
+        Posting { account: account.to_id(), currency: Currency::BASE, side: Side::Credit, amount }
+    }
+
+    pub fn in_currency(mut self, currency: Currency) -> Self {
+        self.currency = currency;
+        self
+    }
+
+    // Customer balances are credit-normal: credits add, debits subtract
+    pub fn signed_amount(&self) -> i32 {
+        match self.side {
+            Side::Debit => -self.amount,
+            Side::Credit => self.amount,
+        }
+    }
+}
+
+// One business event, recorded as postings whose debits equal their credits
+#[derive(Debug, Clone)]
+pub struct JournalEntry<A: AccountId = AccountName> {
+    // Position in the journal, numbered from 1 without gaps
+    pub seq: u64,
+    // Transaction ID from the configured generator
+    pub id: Id,
+    pub kind: EntryKind,
+    // Wall-clock time as the ledger saw it; can go backwards if the clock does
+    pub recorded_at: DateTime<Local>,
+    // Hybrid logical clock reading, strictly increasing along the journal
+    pub stamp: Timestamp,
+    pub memo: String,
+    pub postings: Vec<Posting<A>>,
+    // Link of the entry before it, and this entry's own link
+    pub prev_hash: ChainHash,
+    pub hash: ChainHash,
+}
+
+impl<A: AccountId> JournalEntry<A> {
+    // Debits equal credits in every currency the entry touches
+    fn is_balanced(&self) -> bool {
+        let mut net: BTreeMap<Currency, i32> = BTreeMap::new();
+        for posting in &self.postings {
+            *net.entry(posting.currency).or_insert(0) += posting.signed_amount();
+        }
+        net.values().all(|&amount| amount == 0)
+    }
+
+    // Rough heap plus inline size, good enough to watch growth. Account IDs
+    // are counted inline; names are interned, so they don't grow with the
+    // journal.
+    fn footprint(&self) -> usize {
+        size_of::<Self>() + self.memo.capacity() + self.postings.len() * size_of::<Posting<A>>()
+    }
+}
+
+// Limits on how much history the journal keeps. Entries beyond any limit
+// are rolled, oldest first, into a single summary entry at the front.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetentionPolicy {
+    pub max_entries: Option<usize>,
+    pub max_age: Option<Duration>,
+    pub max_bytes: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Compaction {
+    // Entries folded into the summary by this pass
+    pub compacted: usize,
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+// What a payroll run does when a payee can't be credited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayrollMode {
+    // Reject the whole run; nothing is posted
+    AllOrNothing,
+    // Pay everyone who can be paid and refund the rest to the payer
+    BestEffort,
+}
+
+#[derive(Debug, Clone)]
+pub struct Payment<A: AccountId = AccountName> {
+    pub to: A,
+    pub amount: i32,
+    pub transaction: Id,
+}
+
+#[derive(Debug, Clone)]
+pub struct PayrollReport<A: AccountId = AccountName> {
+    pub paid: Vec<Payment<A>>,
+    // Payees that couldn't be credited, with the amount and the reason
+    pub failed: Vec<(A, i32, &'static str)>,
+    // Returned to the payer for the failed credits
+    pub refunded: i32,
+    // The payer's balance afterwards
+    pub balance: i32,
+}
+
+// Funds set aside by an authorization. Nothing is posted until the hold is
+// captured; until then it only lowers the account's available balance.
+#[derive(Debug, Clone)]
+pub struct Hold<A: AccountId = AccountName> {
+    pub id: Id,
+    pub from: A,
+    pub to: A,
+    pub amount: i32,
+    pub expires_at: DateTime<Local>,
+}
+
+// A broken accounting invariant, reported by `Ledger::violations`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    UnbalancedEntry { seq: u64 },
+    OutOfOrder { seq: u64 },
+    BrokenChain { seq: u64 },
+    SequenceGap { expected: u64, found: u64 },
+    BalanceMismatch { account: String, currency: Currency, balance: i32, journal: i32 },
+    NegativeBalance { account: String, currency: Currency, balance: i32 },
+    NonZeroTotal { currency: Currency, total: i32 },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::UnbalancedEntry { seq } => write!(f, "journal entry {} is unbalanced", seq),
+            Violation::OutOfOrder { seq } => write!(f, "journal entry {} is stamped before the one preceding it", seq),
+            Violation::BrokenChain { seq } => write!(f, "hash chain broken at journal entry {}", seq),
+            Violation::SequenceGap { expected, found } => {
+                write!(f, "expected journal entry {} but found {}", expected, found)
+            },
+            Violation::BalanceMismatch { account, currency, balance, journal } => {
+                write!(f, "{} has balance {} {} but the journal says {}", account, balance, currency, journal)
+            },
+            Violation::NegativeBalance { account, currency, balance } => {
+                write!(f, "{} has a negative balance of {} {}", account, balance, currency)
+            },
+            Violation::NonZeroTotal { currency, total } => {
+                write!(f, "{} accounts sum to {} instead of 0", currency, total)
+            },
+        }
+    }
+}
+
+// Where an account is in its life. Purged accounts are gone entirely, so
+// they read as Missing again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountStatus {
+    Missing,
+    // The ledger's own accounts: customers can't open, use or close them
+    Reserved,
+    Open,
+    // Soft-deleted, and can be restored until it's purged
+    Closed,
+}
+
+// What can be done to an account, as far as its status is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lifecycle {
+    Open,
+    // Read it or post to it
+    Use,
+    Close,
+    Restore,
+    Purge,
+}
+
+impl AccountStatus {
+    pub const ALL: [AccountStatus; 4] =
+        [AccountStatus::Missing, AccountStatus::Reserved, AccountStatus::Open, AccountStatus::Closed];
+
+    // The account lifecycle: the status `operation` leaves an account in, or
+    // why it's refused. Every ledger method that acts on an account checks
+    // here first; other rules, like closing only empty accounts, come after.
+    pub fn apply(self, operation: Lifecycle) -> Result<AccountStatus, &'static str> {
+        use AccountStatus::*;
+        match (self, operation) {
+            (Missing, Lifecycle::Open) => Ok(Open),
+            (Missing, _) => Err("Account not found"),
+            (Reserved, Lifecycle::Open) => Err("Account name is reserved"),
+            (Reserved, _) => Err("Account not found"),
+            (Open | Closed, Lifecycle::Open) => Err("Account already exists"),
+            (Open, Lifecycle::Use) => Ok(Open),
+            (Open, Lifecycle::Close) => Ok(Closed),
+            (Open, Lifecycle::Restore | Lifecycle::Purge) => Err("Account is not closed"),
+            (Closed, Lifecycle::Use | Lifecycle::Close) => Err("Account is closed"),
+            (Closed, Lifecycle::Restore) => Ok(Open),
+            (Closed, Lifecycle::Purge) => Ok(Missing),
+        }
+    }
+}
+
+impl Lifecycle {
+    pub const ALL: [Lifecycle; 5] =
+        [Lifecycle::Open, Lifecycle::Use, Lifecycle::Close, Lifecycle::Restore, Lifecycle::Purge];
+}
+
+// Double-entry books: balances are only ever changed by posting journal
+// entries, so money can be moved between accounts but never created.
+// Accounts are keyed by `A`, interned names unless the embedder brings its
+// own account numbers or UUIDs.
+pub struct Ledger<A: AccountId = AccountName> {
+    // Every currency an account holds, the base currency always among them
+    balances: HashMap<A, BTreeMap<Currency, i32>>,
+    journal: Vec<JournalEntry<A>>,
+    // Soft-deleted accounts and when they were closed. Their journal entries
+    // are kept; they can be restored until they are purged.
+    closed: HashMap<A, DateTime<Local>>,
+    // Authorized but not yet captured payments
+    holds: HashMap<Id, Hold<A>>,
+    // Parent of each sub-account. Roll-up balances are left to read models,
+    // so posting never walks the tree.
+    parents: HashMap<A, A>,
+    // Charged on base-currency transfers when set
+    fees: Option<FeeSchedule<A>>,
+    clock: HybridClock,
+}
+
+impl<A: AccountId> Ledger<A> {
+    pub fn new() -> Self {
+        let mut balances = HashMap::new();
+        balances.insert(A::internal(Internal::Cash), BTreeMap::from([(Currency::BASE, 0)]));
+        Ledger {
+            balances,
+            journal: vec![],
+            closed: HashMap::new(),
+            holds: HashMap::new(),
+            parents: HashMap::new(),
+            fees: None,
+            clock: HybridClock::new(),
+        }
+    }
+
+    pub fn with_opening_balances<K: AccountKey<A>>(accounts: HashMap<K, i32>) -> Self {
+        let mut ledger = Ledger::new();
+        for (account, balance) in accounts {
+            ledger.open_account(&account, balance).expect("opening balances name each account once");
+        }
+        ledger
+    }
+
+    pub fn open_account<Q: AccountKey<A> + ?Sized>(
+        &mut self,
+        account: &Q,
+        opening_balance: i32,
+    ) -> Result<(), &'static str> {
+        if opening_balance < 0 {
+            return Err("Opening balance must not be negative");
+        }
+        self.status(account).apply(Lifecycle::Open)?;
+        self.balances.entry(account.to_id()).or_insert_with(|| BTreeMap::from([(Currency::BASE, 0)]));
+        if opening_balance != 0 {
+            self.post(
+                EntryKind::Opening,
+                format!("Opening balance for {}", account),
+                vec![
+                    Posting::debit(&A::internal(Internal::Cash), opening_balance),
+                    Posting::credit(account, opening_balance),
+                ],
+            );
+        }
+        Ok(())
+    }
+
+    // Opening balance in a currency the account already holds, posted the
+    // way `open_account` posts one in the base currency
+    pub fn open_balance_in<Q: AccountKey<A> + ?Sized>(
+        &mut self,
+        account: &Q,
+        amount: i32,
+        currency: Currency,
+    ) -> Result<(), &'static str> {
+        if amount < 0 {
+            return Err("Opening balance must not be negative");
+        }
+        self.customer_balance(account, currency)?;
+        self.post(
+            EntryKind::Opening,
+            format!("Opening {} balance for {}", currency, account),
+            vec![
+                Posting::debit(&A::internal(Internal::Cash), amount).in_currency(currency),
+                Posting::credit(account, amount).in_currency(currency),
+            ],
+        );
+        Ok(())
+    }
+
+    // Balance in the base currency
+    pub fn balance<Q: AccountKey<A> + ?Sized>(&self, account: &Q) -> Option<i32> {
+        self.customer_balance(account, Currency::BASE).ok()
+    }
+
+    // Base-currency balance replayed from the journal's postings; should
+    // always equal `balance`
+    pub fn journal_balance<Q: AccountKey<A> + ?Sized>(&self, account: &Q) -> Option<i32> {
+        self.customer_balance(account, Currency::BASE).ok()?;
+        let postings = self.journal.iter().flat_map(|entry| &entry.postings);
+        Some(
+            postings
+                .filter(|posting| account.is(&posting.account) && posting.currency == Currency::BASE)
+                .map(Posting::signed_amount)
+                .sum(),
+        )
+    }
+
+    // Every currency the account holds and its balance in each
+    pub fn holdings<Q: AccountKey<A> + ?Sized>(&self, account: &Q) -> Option<BTreeMap<Currency, i32>> {
+        self.customer_balance(account, Currency::BASE).ok()?;
+        account.find(&self.balances).cloned()
+    }
+
+    // Balance minus everything on hold, i.e. what can still be spent
+    pub fn available<Q: AccountKey<A> + ?Sized>(&self, account: &Q) -> Option<i32> {
+        self.available_balance(account, Currency::BASE).ok()
+    }
+
+    // Let the account hold another currency, starting from zero
+    pub fn add_currency<Q: AccountKey<A> + ?Sized>(&mut self, account: &Q, currency: Currency) -> Result<(), &'static str> {
+        self.customer_balance(account, Currency::BASE)?;
+        let holdings = account.find_mut(&mut self.balances).expect("account checked above");
+        if holdings.contains_key(&currency) {
+            return Err("Currency already held");
+        }
+        holdings.insert(currency, 0);
+        Ok(())
+    }
+
+    // Open customer accounts, sorted
+    pub fn accounts(&self) -> Vec<A> {
+        let mut accounts: Vec<A> = self
+            .balances
+            .keys()
+            .filter(|account| !account.is_internal() && !self.closed.contains_key(*account))
+            .cloned()
+            .collect();
+        accounts.sort();
+        accounts
+    }
+
+    pub fn status<Q: AccountKey<A> + ?Sized>(&self, account: &Q) -> AccountStatus {
+        if account.is_reserved() {
+            AccountStatus::Reserved
+        } else if account.find(&self.closed).is_some() {
+            AccountStatus::Closed
+        } else if account.find(&self.balances).is_some() {
+            AccountStatus::Open
+        } else {
+            AccountStatus::Missing
+        }
+    }
+
+    // Only empty accounts can be closed, so purging one later never takes
+    // money off the books
+    pub fn close_account<Q: AccountKey<A> + ?Sized>(&mut self, account: &Q) -> Result<(), &'static str> {
+        self.status(account).apply(Lifecycle::Close)?;
+        let holdings = account.find(&self.balances).expect("account checked above");
+        if holdings.values().any(|&balance| balance != 0) {
+            return Err("Balance must be zero to close");
+        }
+        if self.holds.values().any(|hold| account.is(&hold.from) || account.is(&hold.to)) {
+            return Err("Account has pending holds");
+        }
+        self.closed.insert(account.to_id(), self.clock.wall());
+        Ok(())
+    }
+
+    pub fn restore_account<Q: AccountKey<A> + ?Sized>(&mut self, account: &Q) -> Result<(), &'static str> {
+        self.status(account).apply(Lifecycle::Restore)?;
+        self.closed.remove(&account.to_id());
+        Ok(())
+    }
+
+    // Permanently remove accounts closed longer than `retention` ago. Their
+    // past postings stay in the journal.
+    pub fn purge_closed(&mut self, retention: Duration) -> Vec<A> {
+        let cutoff = chrono::Duration::from_std(retention)
+            .ok()
+            .and_then(|retention| self.clock.wall().checked_sub_signed(retention));
+        let Some(cutoff) = cutoff else { return vec![] };
+
+        let mut purged: Vec<A> = self
+            .closed
+            .iter()
+            .filter(|(account, closed_at)| **closed_at <= cutoff && self.status(*account).apply(Lifecycle::Purge).is_ok())
+            .map(|(account, _)| account.clone())
+            .collect();
+        purged.sort();
+        for account in &purged {
+            self.closed.remove(account);
+            self.balances.remove(account);
+            // Its children become top-level accounts
+            self.parents.remove(account);
+            self.parents.retain(|_, parent| parent != account);
+        }
+        purged
+    }
+
+    // Make `account` a sub-account of `parent`, moving it out from under any
+    // parent it had
+    pub fn set_parent<Q: AccountKey<A> + ?Sized, R: AccountKey<A> + ?Sized>(
+        &mut self,
+        account: &Q,
+        parent: &R,
+    ) -> Result<(), &'static str> {
+        self.customer_balance(account, Currency::BASE)?;
+        self.customer_balance(parent, Currency::BASE)?;
+        if parent.is(&account.to_id()) {
+            return Err("Account would be its own ancestor");
+        }
+        let mut ancestor = self.parent(parent);
+        while let Some(current) = ancestor {
+            if account.is(current) {
+                return Err("Account would be its own ancestor");
+            }
+            ancestor = self.parent(current);
+        }
+        self.parents.insert(account.to_id(), parent.to_id());
+        Ok(())
+    }
+
+    pub fn parent<Q: AccountKey<A> + ?Sized>(&self, account: &Q) -> Option<&A> {
+        account.find(&self.parents)
+    }
+
+    // Sum of every customer account in the base currency, i.e. what the
+    // bank owes its customers in it
+    pub fn total_balance(&self) -> i32 {
+        self.balances
+            .iter()
+            .filter(|(account, _)| !account.is_internal())
+            .filter_map(|(_, holdings)| holdings.get(&Currency::BASE))
+            .sum()
+    }
+
+    pub fn entries(&self) -> &[JournalEntry<A>] {
+        &self.journal
+    }
+
+    // ID of the most recently posted entry. Only called after a successful
+    // operation, which always posts one.
+    pub fn last_transaction(&self) -> Id {
+        self.journal.last().expect("journal has entries").id
+    }
+
+    pub fn deposit<Q: AccountKey<A> + ?Sized>(&mut self, account: &Q, amount: i32) -> Result<i32, &'static str> {
+        self.deposit_in(account, amount, Currency::BASE)
+    }
+
+    // The account must already hold the currency
+    pub fn deposit_in<Q: AccountKey<A> + ?Sized>(
+        &mut self,
+        account: &Q,
+        amount: i32,
+        currency: Currency,
+    ) -> Result<i32, &'static str> {
+        if amount <= 0 {
+            return Err("Amount must be positive");
+        }
+        self.customer_balance(account, currency)?;
+        self.post(
+            EntryKind::Deposit,
+            format!("Deposit to {}", account),
+            vec![
+                Posting::debit(&A::internal(Internal::Cash), amount).in_currency(currency),
+                Posting::credit(account, amount).in_currency(currency),
+            ],
+        );
+        self.customer_balance(account, currency)
+    }
+
+    pub fn transfer<Q: AccountKey<A> + ?Sized, R: AccountKey<A> + ?Sized>(
+        &mut self,
+        from: &Q,
+        to: &R,
+        amount: i32,
+    ) -> Result<i32, &'static str> {
+        self.transfer_in(from, to, amount, Currency::BASE)
+    }
+
+    // Both sides must hold the currency; use `exchange` to convert
+    pub fn transfer_in<Q: AccountKey<A> + ?Sized, R: AccountKey<A> + ?Sized>(
+        &mut self,
+        from: &Q,
+        to: &R,
+        amount: i32,
+        currency: Currency,
+    ) -> Result<i32, &'static str> {
+        self.itemized_transfer(from, to, amount, currency).map(|(balance, _)| balance)
+    }
+
+    // Transfer, charging the payer the fee on top of the amount. The fee is
+    // posted in the same entry as the transfer, so one can't happen without
+    // the other. Answers with the payer's balance and the fee charged.
+    pub fn itemized_transfer<Q: AccountKey<A> + ?Sized, R: AccountKey<A> + ?Sized>(
+        &mut self,
+        from: &Q,
+        to: &R,
+        amount: i32,
+        currency: Currency,
+    ) -> Result<(i32, FeeBreakdown), &'static str> {
+        if amount <= 0 {
+            return Err("Amount must be positive");
+        }
+        let fee = if currency == Currency::BASE { self.fee_for(from, to, amount) } else { FeeBreakdown::default() };
+        let charged = amount.checked_add(fee.total).ok_or("Amount too large")?;
+        if self.available_balance(from, currency)? < charged {
+            return Err("Insufficient funds");
+        }
+        self.customer_balance(to, currency)?;
+        let (kind, mut memo) = if self.is_move(from, to) {
+            (EntryKind::Move, format!("Move from {} to {}", from, to))
+        } else {
+            (EntryKind::Transfer, format!("Transfer from {} to {}", from, to))
+        };
+        let mut postings = vec![
+            Posting::debit(from, amount).in_currency(currency),
+            Posting::credit(to, amount).in_currency(currency),
+        ];
+        if let Some(fees) = self.fee_account().filter(|_| fee.total > 0) {
+            self.customer_balance(fees, currency)?;
+            memo.push_str(&format!(", fee {}", fee));
+            postings.push(Posting::debit(from, fee.total));
+            postings.push(Posting::credit(fees, fee.total));
+        }
+        self.post(kind, memo, postings);
+        Ok((self.customer_balance(from, currency)?, fee))
+    }
+
+    // Replace the fee schedule, answering with the one it replaces. The fees
+    // account must be open.
+    pub fn set_fee_schedule(&mut self, schedule: Option<FeeSchedule<A>>) -> Result<Option<FeeSchedule<A>>, &'static str> {
+        if let Some(schedule) = &schedule {
+            schedule.validate()?;
+            self.customer_balance(&schedule.account, Currency::BASE)?;
+        }
+        Ok(std::mem::replace(&mut self.fees, schedule))
+    }
+
+    // Fee on a base-currency transfer. Moves within a family and payments to
+    // or from the fees account itself are free, as are exchanges and payroll.
+    pub fn fee_for<Q: AccountKey<A> + ?Sized, R: AccountKey<A> + ?Sized>(
+        &self,
+        from: &Q,
+        to: &R,
+        amount: i32,
+    ) -> FeeBreakdown {
+        match &self.fees {
+            Some(fees) if !self.is_move(from, to) && !from.is(&fees.account) && !to.is(&fees.account) => fees.quote(amount),
+            _ => FeeBreakdown::default(),
+        }
+    }
+
+    pub fn fee_account(&self) -> Option<&A> {
+        self.fees.as_ref().map(|fees| &fees.account)
+    }
+
+    fn is_move<Q: AccountKey<A> + ?Sized, R: AccountKey<A> + ?Sized>(&self, from: &Q, to: &R) -> bool {
+        self.parent(from).is_some_and(|parent| to.is(parent)) || self.parent(to).is_some_and(|parent| from.is(parent))
+    }
+
+    // Send `amount` in `currency` and credit the payee the converted amount
+    // in `to_currency`, which is returned. The FX account takes the other
+    // side of both legs.
+    pub fn exchange<Q: AccountKey<A> + ?Sized, R: AccountKey<A> + ?Sized>(
+        &mut self,
+        from: &Q,
+        to: &R,
+        amount: i32,
+        currency: Currency,
+        to_currency: Currency,
+        rates: &Rates,
+    ) -> Result<i32, &'static str> {
+        if amount <= 0 {
+            return Err("Amount must be positive");
+        }
+        if self.available_balance(from, currency)? < amount {
+            return Err("Insufficient funds");
+        }
+        self.customer_balance(to, to_currency)?;
+        let converted = rates.convert(amount, currency, to_currency).ok_or("No exchange rate")?;
+        self.post(
+            EntryKind::Exchange,
+            format!("Exchange of {} {} from {} to {} {} for {}", amount, currency, from, converted, to_currency, to),
+            vec![
+                Posting::debit(from, amount).in_currency(currency),
+                Posting::credit(&A::internal(Internal::Fx), amount).in_currency(currency),
+                Posting::debit(&A::internal(Internal::Fx), converted).in_currency(to_currency),
+                Posting::credit(to, converted).in_currency(to_currency),
+            ],
+        );
+        Ok(converted)
+    }
+
+    // Debit `from` once and credit every payee. All-or-nothing checks every
+    // payee first and posts one entry with all the legs, so it can't half
+    // happen. Best-effort moves the total into the clearing account, pays
+    // each payee out of it, then compensates: whatever couldn't be paid goes
+    // back to the payer in a refund entry, leaving clearing at zero.
+    pub fn payroll<Q: AccountKey<A> + ?Sized, K: AccountKey<A>>(
+        &mut self,
+        from: &Q,
+        credits: &[(K, i32)],
+        mode: PayrollMode,
+    ) -> Result<PayrollReport<A>, &'static str> {
+        if credits.is_empty() {
+            return Err("No payees");
+        }
+        if credits.iter().any(|(_, amount)| *amount <= 0) {
+            return Err("Amount must be positive");
+        }
+        let total = credits
+            .iter()
+            .try_fold(0i32, |total, (_, amount)| total.checked_add(*amount))
+            .ok_or("Payroll total too large")?;
+        if self.available_balance(from, Currency::BASE)? < total {
+            return Err("Insufficient funds");
+        }
+
+        let mut paid = vec![];
+        let mut failed = vec![];
+        match mode {
+            PayrollMode::AllOrNothing => {
+                for (to, _) in credits {
+                    self.customer_balance(to, Currency::BASE)?;
+                }
+                let mut postings = vec![Posting::debit(from, total)];
+                postings.extend(credits.iter().map(|(to, amount)| Posting::credit(to, *amount)));
+                self.post(EntryKind::Transfer, format!("Payroll from {} to {} payee(s)", from, credits.len()), postings);
+                let transaction = self.last_transaction();
+                paid.extend(credits.iter().map(|(to, amount)| Payment { to: to.to_id(), amount: *amount, transaction }));
+            },
+            PayrollMode::BestEffort => {
+                let clearing = A::internal(Internal::Clearing);
+                self.post(
+                    EntryKind::Transfer,
+                    format!("Payroll from {} into clearing", from),
+                    vec![Posting::debit(from, total), Posting::credit(&clearing, total)],
+                );
+                for (to, amount) in credits {
+                    if let Err(reason) = self.customer_balance(to, Currency::BASE) {
+                        failed.push((to.to_id(), *amount, reason));
+                        continue;
+                    }
+                    self.post(
+                        EntryKind::Transfer,
+                        format!("Payroll from {} to {}", from, to),
+                        vec![Posting::debit(&clearing, *amount), Posting::credit(to, *amount)],
+                    );
+                    paid.push(Payment { to: to.to_id(), amount: *amount, transaction: self.last_transaction() });
+                }
+            },
+        }
+
+        let refunded: i32 = failed.iter().map(|(_, amount, _)| amount).sum();
+        if refunded > 0 {
+            self.post(
+                EntryKind::Transfer,
+                format!("Refund of {} failed payroll credit(s) to {}", failed.len(), from),
+                vec![Posting::debit(&A::internal(Internal::Clearing), refunded), Posting::credit(from, refunded)],
+            );
+        }
+        Ok(PayrollReport { paid, failed, refunded, balance: self.customer_balance(from, Currency::BASE)? })
+    }
+
+    // Reserve `amount` for a payment from `from` to `to`. The hold lapses
+    // unless captured or voided within `ttl`.
+    pub fn authorize<Q: AccountKey<A> + ?Sized, R: AccountKey<A> + ?Sized>(
+        &mut self,
+        from: &Q,
+        to: &R,
+        amount: i32,
+        ttl: Duration,
+    ) -> Result<Id, &'static str> {
+        if amount <= 0 {
+            return Err("Amount must be positive");
+        }
+        if self.available_balance(from, Currency::BASE)? < amount {
+            return Err("Insufficient funds");
+        }
+        self.customer_balance(to, Currency::BASE)?;
+        let expires_at = chrono::Duration::from_std(ttl)
+            .ok()
+            .and_then(|ttl| self.clock.wall().checked_add_signed(ttl))
+            .ok_or("Hold lifetime too long")?;
+
+        let id = ids::next_id();
+        self.holds.insert(id, Hold { id, from: from.to_id(), to: to.to_id(), amount, expires_at });
+        Ok(id)
+    }
+
+    // Post the held payment. An expired hold can no longer be captured,
+    // even if the expiry sweep hasn't removed it yet.
+    pub fn capture(&mut self, hold: Id) -> Result<Hold<A>, &'static str> {
+        let held = self.holds.get(&hold).ok_or("No such hold")?;
+        if held.expires_at <= self.clock.wall() {
+            return Err("Hold expired");
+        }
+        self.customer_balance(&held.to, Currency::BASE)?;
+
+        let hold = self.holds.remove(&hold).expect("hold checked above");
+        self.post(
+            EntryKind::Transfer,
+            format!("Capture of hold {} from {} to {}", hold.id, hold.from, hold.to),
+            vec![Posting::debit(&hold.from, hold.amount), Posting::credit(&hold.to, hold.amount)],
+        );
+        Ok(hold)
+    }
+
+    // Release a hold without posting anything
+    pub fn void(&mut self, hold: Id) -> Result<Hold<A>, &'static str> {
+        self.holds.remove(&hold).ok_or("No such hold")
+    }
+
+    // Release every hold past its expiry, in the order they expired
+    pub fn expire_holds(&mut self) -> Vec<Hold<A>> {
+        let now = self.clock.wall();
+        let mut expired: Vec<Hold<A>> = self.holds.values().filter(|hold| hold.expires_at <= now).cloned().collect();
+        expired.sort_by_key(|hold| hold.expires_at);
+        for hold in &expired {
+            self.holds.remove(&hold.id);
+        }
+        expired
+    }
+
+    // Start staging a multi-operation transaction against the current books
+    pub fn begin(&self) -> Transaction<'_, A> {
+        Transaction {
+            ledger: self,
+            staged: vec![],
+            balances: HashMap::new(),
+            savepoints: vec![],
+        }
+    }
+
+    // Recompute every link in the hash chain, returning the first entry that
+    // doesn't match. A compaction summary is a checkpoint: it carries the
+    // link of the last entry it replaced, which is taken as given.
+    pub fn verify_chain(&self) -> Result<(), u64> {
+        let mut previous = ChainHash::GENESIS;
+        for entry in &self.journal {
+            if entry.kind != EntryKind::Summary {
+                let link = ChainHash::link(&entry.prev_hash, &ChainHash::body(entry));
+                if entry.prev_hash != previous || entry.hash != link {
+                    return Err(entry.seq);
+                }
+            }
+            previous = entry.hash;
+        }
+        Ok(())
+    }
+
+    // Proof that the entry for `transaction` is in the chain up to the head
+    pub fn receipt(&self, transaction: Id) -> Option<Receipt<A>> {
+        let position = self
+            .journal
+            .iter()
+            .position(|entry| entry.id == transaction && entry.kind != EntryKind::Summary)?;
+        Some(Receipt {
+            entry: self.journal[position].clone(),
+            later: self.journal[position + 1..].iter().map(ChainHash::body).collect(),
+            head: self.journal.last()?.hash,
+        })
+    }
+
+    // Wall-clock time as the ledger currently reads it, skew included
+    pub fn wall_clock(&self) -> chrono::DateTime<chrono::Local> {
+        self.clock.wall()
+    }
+
+    // Simulate the ledger's wall clock jumping by `offset`; returns the
+    // clock's next reading
+    pub fn skew_clock(&mut self, offset: chrono::Duration) -> Timestamp {
+        self.clock.skew_by(offset);
+        self.clock.now()
+    }
+
+    pub fn check_invariants(&self) -> Result<(), String> {
+        match self.violations().first() {
+            Some(violation) => Err(violation.to_string()),
+            None => Ok(()),
+        }
+    }
+
+    // Approximate memory held by the journal and balances. Account IDs are
+    // counted inline only: names are interned, so their text is shared.
+    pub fn footprint(&self) -> usize {
+        let balances: usize = self
+            .balances
+            .values()
+            .map(|holdings| size_of::<A>() + holdings.len() * size_of::<(Currency, i32)>())
+            .sum();
+        balances + self.journal.iter().map(JournalEntry::footprint).sum::<usize>()
+    }
+
+    // Apply the retention policy: the oldest entries over any limit are
+    // replaced by one summary entry holding each account's net movement, so
+    // balances still replay from the journal and every entry still balances
+    pub fn compact(&mut self, policy: &RetentionPolicy) -> Compaction {
+        let mut remove = 0;
+
+        // The new summary takes one slot of the allowance
+        if let Some(max_entries) = policy.max_entries.map(|max| max.max(1)) {
+            if self.journal.len() > max_entries {
+                remove = self.journal.len() - max_entries + 1;
+            }
+        }
+        // An age reaching back past the clock's range covers nothing
+        let cutoff = policy
+            .max_age
+            .and_then(|age| chrono::Duration::from_std(age).ok())
+            .and_then(|age| self.clock.wall().checked_sub_signed(age));
+        if let Some(cutoff) = cutoff {
+            remove = remove.max(self.journal.iter().take_while(|entry| entry.recorded_at < cutoff).count());
+        }
+        if let Some(max_bytes) = policy.max_bytes {
+            let mut bytes = self.footprint();
+            let mut over = 0;
+            for entry in &self.journal {
+                if bytes <= max_bytes {
+                    break;
+                }
+                bytes -= entry.footprint();
+                over += 1;
+            }
+            remove = remove.max(over);
+        }
+
+        // Folding a lone summary into itself changes nothing
+        let only_summary = remove == 1 && self.journal[0].kind == EntryKind::Summary;
+        if remove > 0 && !only_summary {
+            let folded: Vec<JournalEntry<A>> = self.journal.drain(..remove).collect();
+            let last = folded.last().expect("at least one entry folded");
+
+            let mut net: BTreeMap<(&A, Currency), i32> = BTreeMap::new();
+            for posting in folded.iter().flat_map(|entry| &entry.postings) {
+                *net.entry((&posting.account, posting.currency)).or_insert(0) += posting.signed_amount();
+            }
+            let postings = net
+                .into_iter()
+                .filter(|(_, amount)| *amount != 0)
+                .map(|((account, currency), amount)| {
+                    let posting = if amount > 0 {
+                        Posting::credit(account, amount)
+                    } else {
+                        Posting::debit(account, -amount)
+                    };
+                    posting.in_currency(currency)
+                })
+                .collect();
+
+            // Takes the sequence number of the last entry it replaces, so
+            // numbering continues without a gap after it
+            let summary = JournalEntry {
+                seq: last.seq,
+                id: ids::next_id(),
+                kind: EntryKind::Summary,
+                recorded_at: last.recorded_at,
+                stamp: last.stamp,
+                memo: format!("Summary of entries up to {}", last.seq),
+                postings,
+                prev_hash: folded[0].prev_hash,
+                hash: last.hash,
+            };
+            self.journal.insert(0, summary);
+        } else {
+            remove = 0;
+        }
+
+        Compaction {
+            compacted: remove,
+            entries: self.journal.len(),
+            bytes: self.footprint(),
+        }
+    }
+
+    // Verify the books: entries are numbered without gaps and each one
+    // balances, replaying the journal reproduces the balances, no customer
+    // is overdrawn, and all accounts (cash included) sum to zero
+    pub fn violations(&self) -> Vec<Violation> {
+        let mut violations = vec![];
+
+        // After compaction numbering resumes from the summary's sequence number
+        let first = match self.journal.first() {
+            Some(entry) if entry.kind == EntryKind::Summary => entry.seq,
+            _ => 1,
+        };
+        for (expected, entry) in (first..).zip(&self.journal) {
+            if entry.seq != expected {
+                violations.push(Violation::SequenceGap { expected, found: entry.seq });
+            }
+            if !entry.is_balanced() {
+                violations.push(Violation::UnbalancedEntry { seq: entry.seq });
+            }
+        }
+        if let Err(seq) = self.verify_chain() {
+            violations.push(Violation::BrokenChain { seq });
+        }
+        for pair in self.journal.windows(2) {
+            if pair[1].stamp <= pair[0].stamp {
+                violations.push(Violation::OutOfOrder { seq: pair[1].seq });
+            }
+        }
+
+        let mut replayed: HashMap<(&A, Currency), i32> = HashMap::new();
+        for posting in self.journal.iter().flat_map(|entry| &entry.postings) {
+            *replayed.entry((&posting.account, posting.currency)).or_insert(0) += posting.signed_amount();
+        }
+        let mut totals: BTreeMap<Currency, i32> = BTreeMap::new();
+        for (account, holdings) in &self.balances {
+            for (&currency, &balance) in holdings {
+                let journal = replayed.get(&(account, currency)).copied().unwrap_or(0);
+                if journal != balance {
+                    violations.push(Violation::BalanceMismatch { account: account.to_string(), currency, balance, journal });
+                }
+                if balance < 0 && !account.is_internal() {
+                    violations.push(Violation::NegativeBalance { account: account.to_string(), currency, balance });
+                }
+                *totals.entry(currency).or_insert(0) += balance;
+            }
+        }
+
+        for (currency, total) in totals {
+            if total != 0 {
+                violations.push(Violation::NonZeroTotal { currency, total });
+            }
+        }
+
+        violations
+    }
+
+    fn customer_balance<Q: AccountKey<A> + ?Sized>(&self, account: &Q, currency: Currency) -> Result<i32, &'static str> {
+        self.status(account).apply(Lifecycle::Use)?;
+        let holdings = account.find(&self.balances).expect("account checked above");
+        holdings.get(&currency).copied().ok_or("Account does not hold that currency")
+    }
+
+    // Holds are always in the base currency
+    fn available_balance<Q: AccountKey<A> + ?Sized>(&self, account: &Q, currency: Currency) -> Result<i32, &'static str> {
+        let held: i32 = self
+            .holds
+            .values()
+            .filter(|hold| account.is(&hold.from) && currency == Currency::BASE)
+            .map(|hold| hold.amount)
+            .sum();
+        Ok(self.customer_balance(account, currency)? - held)
+    }
+
+    fn post(&mut self, kind: EntryKind, memo: String, postings: Vec<Posting<A>>) {
+        let prev_hash = self.journal.last().map_or(ChainHash::GENESIS, |entry| entry.hash);
+        let mut entry = JournalEntry {
+            seq: self.journal.last().map_or(1, |entry| entry.seq + 1),
+            id: ids::next_id(),
+            kind,
+            recorded_at: self.clock.wall(),
+            stamp: self.clock.now(),
+            memo,
+            postings,
+            prev_hash,
+            hash: ChainHash::GENESIS,
+        };
+        entry.hash = ChainHash::link(&prev_hash, &ChainHash::body(&entry));
+        debug_assert!(entry.is_balanced(), "unbalanced journal entry: {:?}", entry);
+
+        for posting in &entry.postings {
+            let holdings = self.balances.entry(posting.account.clone()).or_default();
+            *holdings.entry(posting.currency).or_insert(0) += posting.signed_amount();
+        }
+        self.journal.push(entry);
+    }
+}
+
+impl<A: AccountId> Default for Ledger<A> {
+    fn default() -> Self {
+        Ledger::new()
+    }
+}
+
+// An operation accepted into a transaction, posted only on commit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Staged<A: AccountId = AccountName> {
+    Deposit { account: A, amount: i32 },
+    Transfer { from: A, to: A, amount: i32 },
+}
+
+struct Savepoint<A: AccountId> {
+    name: String,
+    staged: usize,
+    balances: HashMap<A, i32>,
+}
+
+// Operations validated against the ledger plus everything staged before
+// them. Nothing touches the ledger until the caller posts `commit()`'s
+// operations, so rolling back only has to forget staged work.
+pub struct Transaction<'a, A: AccountId = AccountName> {
+    ledger: &'a Ledger<A>,
+    staged: Vec<Staged<A>>,
+    // Available balances as they would be after the staged operations
+    balances: HashMap<A, i32>,
+    savepoints: Vec<Savepoint<A>>,
+}
+
+impl<A: AccountId> Transaction<'_, A> {
+    fn balance<Q: AccountKey<A> + ?Sized>(&self, account: &Q) -> Result<i32, &'static str> {
+        match account.find(&self.balances) {
+            Some(&balance) => Ok(balance),
+            None => self.ledger.available_balance(account, Currency::BASE),
+        }
+    }
+
+    pub fn deposit<Q: AccountKey<A> + ?Sized>(&mut self, account: &Q, amount: i32) -> Result<i32, &'static str> {
+        if amount <= 0 {
+            return Err("Amount must be positive");
+        }
+        let balance = self.balance(account)? + amount;
+        self.balances.insert(account.to_id(), balance);
+        self.staged.push(Staged::Deposit { account: account.to_id(), amount });
+        Ok(balance)
+    }
+
+    // Staged with its fee, so the batch can still be posted in full
+    pub fn transfer<Q: AccountKey<A> + ?Sized, R: AccountKey<A> + ?Sized>(
+        &mut self,
+        from: &Q,
+        to: &R,
+        amount: i32,
+    ) -> Result<i32, &'static str> {
+        if amount <= 0 {
+            return Err("Amount must be positive");
+        }
+        let fee = self.ledger.fee_for(from, to, amount).total;
+        let charged = amount.checked_add(fee).ok_or("Amount too large")?;
+        let from_balance = self.balance(from)?;
+        if from_balance < charged {
+            return Err("Insufficient funds");
+        }
+        self.balance(to)?;
+        // Credit after the debit, so a transfer to the payer's own account
+        // nets out instead of overwriting it
+        self.balances.insert(from.to_id(), from_balance - charged);
+        let to_balance = self.balance(to)?;
+        self.balances.insert(to.to_id(), to_balance + amount);
+        if let Some(fees) = self.ledger.fee_account().filter(|_| fee > 0) {
+            let collected = self.balance(fees)?;
+            self.balances.insert(fees.clone(), collected + fee);
+        }
+        self.staged.push(Staged::Transfer { from: from.to_id(), to: to.to_id(), amount });
+        self.balance(from)
+    }
+
+    // Remember the current state under `name`; savepoints nest
+    pub fn savepoint(&mut self, name: &str) {
+        self.savepoints.push(Savepoint {
+            name: name.to_string(),
+            staged: self.staged.len(),
+            balances: self.balances.clone(),
+        });
+    }
+
+    // Undo everything staged since the named savepoint. The savepoint itself
+    // stays so it can be rolled back to again; later ones are released.
+    pub fn rollback_to(&mut self, name: &str) -> Result<usize, &'static str> {
+        let position = self
+            .savepoints
+            .iter()
+            .rposition(|savepoint| savepoint.name == name)
+            .ok_or("No such savepoint")?;
+        self.savepoints.truncate(position + 1);
+
+        let savepoint = &self.savepoints[position];
+        let undone = self.staged.len() - savepoint.staged;
+        self.staged.truncate(savepoint.staged);
+        self.balances = savepoint.balances.clone();
+        Ok(undone)
+    }
+
+    // Name of the innermost savepoint, if any
+    pub fn latest_savepoint(&self) -> Option<&str> {
+        self.savepoints.last().map(|savepoint| savepoint.name.as_str())
+    }
+
+    // The operations that survived, in order, for the caller to post
+    pub fn commit(self) -> Vec<Staged<A>> {
+        self.staged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account_id::{AccountNumber, AccountUuid};
+    use crate::fees::FeeRule;
+    use uuid::Uuid;
+    use AccountStatus::{Closed, Missing, Open, Reserved};
+
+    // Every (status, operation) pair and what it should come to. A status or
+    // operation added to `ALL` without rows here fails `table_is_complete`.
+    const LIFECYCLE: &[(AccountStatus, Lifecycle, Result<AccountStatus, &str>)] = &[
+        (Missing, Lifecycle::Open, Ok(Open)),
+        (Missing, Lifecycle::Use, Err("Account not found")),
+        (Missing, Lifecycle::Close, Err("Account not found")),
+        (Missing, Lifecycle::Restore, Err("Account not found")),
+        (Missing, Lifecycle::Purge, Err("Account not found")),
+        (Reserved, Lifecycle::Open, Err("Account name is reserved")),
+        (Reserved, Lifecycle::Use, Err("Account not found")),
+        (Reserved, Lifecycle::Close, Err("Account not found")),
+        (Reserved, Lifecycle::Restore, Err("Account not found")),
+        (Reserved, Lifecycle::Purge, Err("Account not found")),
+        (Open, Lifecycle::Open, Err("Account already exists")),
+        (Open, Lifecycle::Use, Ok(Open)),
+        (Open, Lifecycle::Close, Ok(Closed)),
+        (Open, Lifecycle::Restore, Err("Account is not closed")),
+        (Open, Lifecycle::Purge, Err("Account is not closed")),
+        (Closed, Lifecycle::Open, Err("Account already exists")),
+        (Closed, Lifecycle::Use, Err("Account is closed")),
+        (Closed, Lifecycle::Close, Err("Account is closed")),
+        (Closed, Lifecycle::Restore, Ok(Open)),
+        (Closed, Lifecycle::Purge, Ok(Missing)),
+    ];
+
+    // A ledger with one account in `status`, and that account's name
+    fn ledger_with(status: AccountStatus) -> (Ledger, &'static str) {
+        let mut ledger = Ledger::new();
+        let account = if status == Reserved { CASH_ACCOUNT } else { "Alice" };
+        if matches!(status, Open | Closed) {
+            ledger.open_account(account, 0).unwrap();
+        }
+        if status == Closed {
+            ledger.close_account(account).unwrap();
+        }
+        assert_eq!(ledger.status(account), status);
+        (ledger, account)
+    }
+
+    fn perform(ledger: &mut Ledger, account: &str, operation: Lifecycle) -> Result<(), &'static str> {
+        match operation {
+            Lifecycle::Open => ledger.open_account(account, 0),
+            Lifecycle::Use => ledger.deposit(account, 1).map(|_| ()),
+            Lifecycle::Close => ledger.close_account(account),
+            Lifecycle::Restore => ledger.restore_account(account),
+            // Purging doesn't say why it passed an account over
+            Lifecycle::Purge => {
+                let purged = ledger.purge_closed(Duration::ZERO);
+                if purged.iter().any(|purged| purged == account) { Ok(()) } else { Err("not purged") }
+            },
+        }
+    }
+
+    #[test]
+    fn table_is_complete() {
+        for status in AccountStatus::ALL {
+            for operation in Lifecycle::ALL {
+                let rows = LIFECYCLE.iter().filter(|(s, o, _)| (*s, *o) == (status, operation)).count();
+                assert_eq!(rows, 1, "{:?} {:?} has {} rows", status, operation, rows);
+            }
+        }
+    }
+
+    #[test]
+    fn transitions_follow_the_table() {
+        for &(status, operation, expected) in LIFECYCLE {
+            assert_eq!(status.apply(operation), expected, "{:?} {:?}", status, operation);
+        }
+    }
+
+    #[test]
+    fn ledger_enforces_the_table() {
+        for &(status, operation, expected) in LIFECYCLE {
+            let (mut ledger, account) = ledger_with(status);
+            let result = perform(&mut ledger, account, operation);
+            match (operation, expected) {
+                (Lifecycle::Purge, _) => assert_eq!(result.is_ok(), expected.is_ok(), "{:?} {:?}", status, operation),
+                (_, Ok(_)) => assert_eq!(result, Ok(()), "{:?} {:?}", status, operation),
+                (_, Err(reason)) => assert_eq!(result, Err(reason), "{:?} {:?}", status, operation),
+            }
+            assert_eq!(ledger.status(account), expected.unwrap_or(status), "{:?} {:?}", status, operation);
+        }
+    }
+
+    #[test]
+    fn non_positive_amounts_are_refused() {
+        let mut ledger = Ledger::with_opening_balances(HashMap::from([("Alice".to_string(), 100), ("Bob".to_string(), 0)]));
+        ledger.add_currency("Bob", Currency::EUR).unwrap();
+        for amount in [0, -500] {
+            assert_eq!(ledger.deposit("Alice", amount), Err("Amount must be positive"));
+            assert_eq!(ledger.transfer("Alice", "Bob", amount), Err("Amount must be positive"));
+            assert_eq!(ledger.transfer_in("Alice", "Bob", amount, Currency::BASE), Err("Amount must be positive"));
+            assert_eq!(
+                ledger.exchange("Alice", "Bob", amount, Currency::BASE, Currency::EUR, &Rates::default()),
+                Err("Amount must be positive")
+            );
+        }
+        let mut batch = ledger.begin();
+        assert_eq!(batch.deposit("Alice", -1), Err("Amount must be positive"));
+        assert_eq!(batch.transfer("Alice", "Bob", -1), Err("Amount must be positive"));
+        assert!(batch.commit().is_empty());
+        assert_eq!((ledger.balance("Alice"), ledger.balance("Bob")), (Some(100), Some(0)));
+    }
+
+    #[test]
+    fn negative_opening_balances_are_refused() {
+        let mut ledger = Ledger::new();
+        assert_eq!(ledger.open_account("Alice", -50), Err("Opening balance must not be negative"));
+        assert_eq!(ledger.status("Alice"), Missing);
+        ledger.open_account("Alice", 0).unwrap();
+        ledger.add_currency("Alice", Currency::EUR).unwrap();
+        assert_eq!(ledger.open_balance_in("Alice", -50, Currency::EUR), Err("Opening balance must not be negative"));
+        assert_eq!(ledger.violations(), vec![]);
+    }
+
+    #[test]
+    fn an_age_past_the_clock_compacts_nothing() {
+        let mut ledger = Ledger::with_opening_balances(HashMap::from([("Alice".to_string(), 100)]));
+        let policy = RetentionPolicy { max_age: Some(Duration::from_secs(u64::MAX)), ..RetentionPolicy::default() };
+        assert_eq!(ledger.compact(&policy).compacted, 0);
+    }
+
+    #[test]
+    fn accounts_can_be_keyed_by_number() {
+        let (alice, bob) = (AccountNumber(1), AccountNumber(2));
+        let mut ledger = Ledger::with_opening_balances(HashMap::from([(alice, 100), (bob, 0)]));
+        assert_eq!(ledger.transfer(&alice, &bob, 30), Ok(70));
+        assert_eq!((ledger.balance(&alice), ledger.balance(&bob)), (Some(70), Some(30)));
+        assert_eq!(ledger.accounts(), vec![alice, bob]);
+        assert!(ledger.entries().iter().any(|entry| entry.memo == "Transfer from #1 to #2"));
+        assert_eq!(ledger.violations(), vec![]);
+    }
+
+    #[test]
+    fn internal_uuids_are_reserved() {
+        let mut ledger: Ledger<AccountUuid> = Ledger::new();
+        let cash = AccountUuid::internal(Internal::Cash);
+        assert_eq!(ledger.open_account(&cash, 0), Err("Account name is reserved"));
+
+        let (payer, payee) = (AccountUuid(Uuid::now_v7()), AccountUuid(Uuid::now_v7()));
+        ledger.open_account(&payer, 100).unwrap();
+        ledger.open_account(&payee, 0).unwrap();
+        let missing = AccountUuid(Uuid::now_v7());
+        let report = ledger.payroll(&payer, &[(payee, 40), (missing, 10)], PayrollMode::BestEffort).unwrap();
+        assert_eq!((report.refunded, report.balance), (10, 60));
+        assert_eq!(report.failed, vec![(missing, 10, "Account not found")]);
+        assert_eq!(ledger.accounts().len(), 2);
+        assert_eq!(ledger.violations(), vec![]);
+    }
+
+    #[test]
+    fn a_staged_self_transfer_only_costs_the_fee() {
+        let accounts = HashMap::from([("Alice".to_string(), 100), ("Bob".to_string(), 0), ("Fees".to_string(), 0)]);
+        let mut ledger = Ledger::with_opening_balances(accounts);
+        let schedule = FeeSchedule { account: "Fees".into(), rules: vec![FeeRule::Flat(1)] };
+        ledger.set_fee_schedule(Some(schedule)).unwrap();
+
+        let mut batch = ledger.begin();
+        assert_eq!(batch.transfer("Alice", "Alice", 50), Ok(99));
+        assert_eq!(batch.transfer("Alice", "Bob", 99), Err("Insufficient funds"));
+        assert_eq!(batch.transfer("Alice", "Bob", 98), Ok(0));
+        for op in batch.commit() {
+            let Staged::Transfer { from, to, amount } = op else { unreachable!() };
+            ledger.transfer(&from, &to, amount).unwrap();
+        }
+        assert_eq!((ledger.balance("Alice"), ledger.balance("Bob"), ledger.balance("Fees")), (Some(0), Some(98), Some(2)));
+    }
+}
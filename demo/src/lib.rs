@@ -0,0 +1,59 @@
+// The bank core, its scenarios and a C API, shared by the `demo` binary
+// and the cdylib embedders link against
+
+pub mod account_id;
+pub mod allocs;
+pub mod async_demo;
+pub mod backfill;
+pub mod bank;
+pub mod bank_demo;
+pub mod blocking;
+pub mod calibration;
+pub mod chain;
+pub mod chaos;
+pub mod client;
+pub mod clock;
+pub mod config;
+pub mod contention;
+pub mod context;
+pub mod crash;
+pub mod currency;
+pub mod deadline;
+pub mod differential;
+pub mod events_demo;
+pub mod fees;
+pub mod ffi;
+pub mod hedge;
+pub mod ids;
+pub mod import;
+pub mod intern;
+pub mod keys;
+pub mod ledger;
+pub mod mailbox;
+pub mod metadata;
+pub mod metrics;
+pub mod notifications;
+pub mod notify_demo;
+pub mod oplog;
+pub mod portable_demo;
+pub mod presets;
+pub mod projection;
+pub mod pubsub;
+pub mod query;
+pub mod quota;
+pub mod reads;
+pub mod region;
+pub mod ring;
+pub mod rollup;
+pub mod rt;
+pub mod runtime_demo;
+pub mod scenario;
+pub mod schedule;
+pub mod shadow;
+pub mod shared_state_demo;
+pub mod signal;
+pub mod slowlog;
+pub mod snapshot;
+pub mod spawn_demo;
+pub mod supervisor;
+pub mod tasks;
@@ -0,0 +1,222 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+// What a send does when the mailbox is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    // Wait for room, like a bounded mpsc channel: nothing is lost, and the
+    // sender slows to the actor's pace
+    Block,
+    // Refuse the new message; the actor works through what it already had
+    DropNewest,
+    // Evict the oldest queued message to make room; the actor always sees
+    // the latest messages
+    DropOldest,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MailboxStats {
+    pub delivered: u64,
+    // Messages lost to the overflow policy, new or evicted
+    pub dropped: u64,
+    pub max_depth: usize,
+}
+
+struct Shared<T> {
+    queue: Mutex<VecDeque<T>>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    senders: AtomicUsize,
+    receiver_alive: AtomicBool,
+    // Signalled when a message arrives or the last sender goes
+    not_empty: Notify,
+    // Signalled when the actor takes a message or goes away
+    not_full: Notify,
+    delivered: AtomicU64,
+    dropped: AtomicU64,
+    max_depth: AtomicUsize,
+}
+
+// Bounded actor mailbox with a choice of overflow policy. A bare mpsc
+// channel can only block or refuse; evicting the oldest message needs the
+// queue itself, so this keeps its own.
+pub fn mailbox<T>(capacity: usize, policy: OverflowPolicy) -> (MailboxSender<T>, MailboxReceiver<T>) {
+    assert!(capacity > 0, "mailbox capacity must be positive");
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(VecDeque::with_capacity(capacity)),
+        capacity,
+        policy,
+        senders: AtomicUsize::new(1),
+        receiver_alive: AtomicBool::new(true),
+        not_empty: Notify::new(),
+        not_full: Notify::new(),
+        delivered: AtomicU64::new(0),
+        dropped: AtomicU64::new(0),
+        max_depth: AtomicUsize::new(0),
+    });
+    (MailboxSender { shared: Arc::clone(&shared) }, MailboxReceiver { shared })
+}
+
+impl<T> Shared<T> {
+    fn stats(&self) -> MailboxStats {
+        MailboxStats {
+            delivered: self.delivered.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            max_depth: self.max_depth.load(Ordering::Relaxed),
+        }
+    }
+}
+
+pub struct MailboxSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for MailboxSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::SeqCst);
+        MailboxSender { shared: Arc::clone(&self.shared) }
+    }
+}
+
+impl<T> Drop for MailboxSender<T> {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.shared.not_empty.notify_waiters();
+        }
+    }
+}
+
+impl<T> MailboxSender<T> {
+    // Err hands the message back once the actor is gone. A message dropped
+    // by the policy still counts as sent: the caller can't tell, just as it
+    // can't tell what the actor does with a delivered one.
+    pub async fn send(&self, message: T) -> Result<(), T> {
+        let shared = &self.shared;
+        let mut message = Some(message);
+        loop {
+            let room = shared.not_full.notified();
+            tokio::pin!(room);
+            room.as_mut().enable();
+
+            if !shared.receiver_alive.load(Ordering::SeqCst) {
+                return Err(message.take().expect("message not sent yet"));
+            }
+            {
+                let mut queue = shared.queue.lock().unwrap();
+                if queue.len() >= shared.capacity {
+                    match shared.policy {
+                        OverflowPolicy::Block => {},
+                        OverflowPolicy::DropNewest => {
+                            shared.dropped.fetch_add(1, Ordering::Relaxed);
+                            return Ok(());
+                        },
+                        OverflowPolicy::DropOldest => {
+                            queue.pop_front();
+                            shared.dropped.fetch_add(1, Ordering::Relaxed);
+                        },
+                    }
+                }
+                if queue.len() < shared.capacity {
+                    queue.push_back(message.take().expect("message not sent yet"));
+                    shared.max_depth.fetch_max(queue.len(), Ordering::Relaxed);
+                    shared.not_empty.notify_one();
+                    return Ok(());
+                }
+            }
+            // Full and blocking: wait for the actor to take something
+            room.await;
+        }
+    }
+
+    pub fn stats(&self) -> MailboxStats {
+        self.shared.stats()
+    }
+}
+
+pub struct MailboxReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Drop for MailboxReceiver<T> {
+    fn drop(&mut self) {
+        self.shared.receiver_alive.store(false, Ordering::SeqCst);
+        self.shared.not_full.notify_waiters();
+    }
+}
+
+impl<T> MailboxReceiver<T> {
+    // None once the mailbox is empty and every sender has gone
+    pub async fn recv(&mut self) -> Option<T> {
+        let shared = &self.shared;
+        loop {
+            let arrival = shared.not_empty.notified();
+            tokio::pin!(arrival);
+            arrival.as_mut().enable();
+
+            let next = shared.queue.lock().unwrap().pop_front();
+            if let Some(message) = next {
+                shared.delivered.fetch_add(1, Ordering::Relaxed);
+                shared.not_full.notify_one();
+                return Some(message);
+            }
+            if shared.senders.load(Ordering::SeqCst) == 0 {
+                return None;
+            }
+            arrival.await;
+        }
+    }
+
+    pub fn stats(&self) -> MailboxStats {
+        self.shared.stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Send 0..10 into a four-slot mailbox, then read whatever survived
+    async fn burst(policy: OverflowPolicy) -> (Vec<i32>, MailboxStats) {
+        let (tx, mut rx) = mailbox(4, policy);
+        let sender = tokio::spawn(async move {
+            for i in 0..10 {
+                tx.send(i).await.unwrap();
+            }
+        });
+        // A dropping sender finishes the burst before anything is read; a
+        // blocking one can't until the receiver makes room
+        if policy != OverflowPolicy::Block {
+            sender.await.unwrap();
+        }
+
+        let mut survivors = vec![];
+        while let Some(i) = rx.recv().await {
+            survivors.push(i);
+        }
+        (survivors, rx.stats())
+    }
+
+    #[tokio::test]
+    async fn drop_newest_keeps_the_first_messages() {
+        let (survivors, stats) = burst(OverflowPolicy::DropNewest).await;
+        assert_eq!(survivors, vec![0, 1, 2, 3]);
+        assert_eq!((stats.delivered, stats.dropped, stats.max_depth), (4, 6, 4));
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_keeps_the_latest_messages() {
+        let (survivors, stats) = burst(OverflowPolicy::DropOldest).await;
+        assert_eq!(survivors, vec![6, 7, 8, 9]);
+        assert_eq!((stats.delivered, stats.dropped, stats.max_depth), (4, 6, 4));
+    }
+
+    #[tokio::test]
+    async fn block_delivers_everything_in_order() {
+        let (survivors, stats) = burst(OverflowPolicy::Block).await;
+        assert_eq!(survivors, (0..10).collect::<Vec<_>>());
+        assert_eq!((stats.delivered, stats.dropped), (10, 0));
+        assert!(stats.max_depth <= 4);
+    }
+}
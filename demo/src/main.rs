@@ -0,0 +1,180 @@
+use demo::backfill::{self, BackfillMode};
+use demo::config::Config;
+use demo::scenario::{self, Scenario};
+use demo::snapshot::SnapshotFormat;
+use demo::{allocs, calibration, contention, crash, ids, import, intern, metrics, slowlog};
+use std::path::PathBuf;
+use tokio::io::AsyncReadExt;
+use tokio::time::sleep;
+
+// Count allocations per task for the report at the end of `demo run`
+#[cfg(feature = "alloc-profiling")]
+#[global_allocator]
+static ALLOCATOR: allocs::CountingAllocator = allocs::CountingAllocator;
+
+fn print_usage() {
+    eprintln!("Usage:");
+    eprintln!("  demo list");
+    eprintln!("  demo run <name|all> [--preset NAME [--presets FILE]] [--clients N] [--work-delay DURATION] [--ids sequential|snowflake|uuid] [--calibrate]");
+    eprintln!("           [--scenario-gap DURATION] [--watchdog-every DURATION] [--sweep-every DURATION] [--refresh-every DURATION] [--stale-after DURATION]");
+    eprintln!("           [--slow-threshold DURATION] [--reconcile-every DURATION] [--east-west DURATION] [--west-east DURATION]");
+    eprintln!("  demo config [run options]");
+    eprintln!("  demo import <file.csv|file.json|-> [--concurrency N]");
+    eprintln!("  demo backfill <snapshot> [--format json|binary] [--output FILE] [--dry-run|--verify]");
+}
+
+fn list() {
+    for scenario in scenario::registry() {
+        println!("{:<20} {}", scenario.name(), scenario.description());
+    }
+}
+
+// Run one scenario, reporting any postcondition it declared that didn't hold
+async fn run_one(scenario: &dyn Scenario, cfg: &Config) -> bool {
+    let failures = allocs::measure(scenario.name(), scenario::run_checked(scenario, cfg)).await;
+    for failure in &failures {
+        println!("Postcondition failed for {}: {}", scenario.name(), failure);
+    }
+    failures.is_empty()
+}
+
+async fn run(name: &str, cfg: &Config) -> Result<(), String> {
+    let passed = if name == "all" {
+        let mut passed = true;
+        for (i, scenario) in scenario::registry().iter().enumerate() {
+            if i > 0 {
+                sleep(cfg.scenario_gap).await;
+            }
+            passed &= run_one(scenario.as_ref(), cfg).await;
+        }
+        passed
+    } else {
+        let selected = scenario::find(name)
+            .ok_or_else(|| format!("Unknown scenario: {} (see `demo list`)", name))?;
+        run_one(selected.as_ref(), cfg).await
+    };
+
+    if passed {
+        Ok(())
+    } else {
+        Err("Some postconditions did not hold".to_string())
+    }
+}
+
+// Load accounts from a file, or from stdin when the path is `-`
+async fn import_accounts(args: &[String]) -> Result<(), String> {
+    let path = args.first().ok_or("Missing file to import")?;
+    let concurrency = match args.get(1).map(String::as_str) {
+        Some("--concurrency") => args
+            .get(2)
+            .and_then(|value| value.parse().ok())
+            .ok_or("--concurrency expects a number")?,
+        Some(other) => return Err(format!("Unknown flag: {}", other)),
+        None => 8,
+    };
+
+    let input = if path == "-" {
+        let mut input = String::new();
+        tokio::io::stdin().read_to_string(&mut input).await.map_err(|e| e.to_string())?;
+        input
+    } else {
+        tokio::fs::read_to_string(path).await.map_err(|e| format!("{}: {}", path, e))?
+    };
+
+    import::run_import(&input, concurrency).await;
+    Ok(())
+}
+
+// Synthesize opening entries for a balances-only snapshot. The result goes
+// next to the input unless `--output` says otherwise.
+fn backfill_snapshot(args: &[String]) -> Result<(), String> {
+    let input = PathBuf::from(args.first().ok_or("Missing snapshot to backfill")?);
+    let mut output = None;
+    let mut format = SnapshotFormat::Json;
+    let mut mode = BackfillMode::Write;
+    let mut flags = args[1..].iter();
+    while let Some(flag) = flags.next() {
+        match flag.as_str() {
+            "--format" => {
+                format = match flags.next().map(String::as_str) {
+                    Some("json") => SnapshotFormat::Json,
+                    Some("binary") => SnapshotFormat::BINARY,
+                    _ => return Err("--format expects json or binary".to_string()),
+                }
+            },
+            "--output" => output = Some(PathBuf::from(flags.next().ok_or("--output expects a file")?)),
+            "--dry-run" => mode = BackfillMode::DryRun,
+            "--verify" => mode = BackfillMode::Verify,
+            other => return Err(format!("Unknown flag: {}", other)),
+        }
+    }
+
+    let output = output.unwrap_or_else(|| {
+        let mut name = input.clone().into_os_string();
+        name.push(".backfilled");
+        PathBuf::from(name)
+    });
+    backfill::run_backfill(&input, &output, format, mode)
+}
+
+#[tokio::main]
+async fn main() {
+    crash::install(std::env::temp_dir().join("demo-crashes"));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        Some("list") => {
+            list();
+            Ok(())
+        },
+        // Show the settings a run with these options would use
+        Some("config") => Config::from_args(&args[1..]).map(|cfg| {
+            for (setting, value) in cfg.describe() {
+                println!("{:<16} {}", setting, value);
+            }
+        }),
+        Some("run") => match args.get(1) {
+            Some(name) => match Config::from_args(&args[2..]) {
+                Ok(cfg) => {
+                    ids::install(cfg.ids.generator());
+                    cfg.chaos.apply();
+                    slowlog::set_threshold(cfg.slow_threshold);
+                    if cfg.calibrate {
+                        calibration::enable();
+                        calibration::probe().await;
+                    }
+                    let result = run(name, &cfg).await;
+                    contention::print_report(name);
+                    metrics::print_channel_report();
+                    // Every posting after an account's first reuses its name
+                    let names = intern::stats();
+                    if names.names > 0 {
+                        metrics::set_gauge("interned_names", names.names as u64);
+                        metrics::set_gauge("name_allocs_avoided", names.reused);
+                    }
+                    metrics::print_gauges();
+                    slowlog::print_report();
+                    allocs::print_report();
+                    if cfg.calibrate {
+                        calibration::print_report();
+                    }
+                    result
+                },
+                Err(e) => Err(e),
+            },
+            None => Err("Missing scenario name".to_string()),
+        },
+        Some("import") => import_accounts(&args[1..]).await,
+        Some("backfill") => backfill_snapshot(&args[1..]),
+        _ => {
+            print_usage();
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        print_usage();
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+// How concurrent writes to one metadata key are reconciled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    // Keep the write with the latest stamp, whatever order writes arrive in
+    LastWriteWins,
+    // Keep every value ever written (a grow-only set), so no write is lost
+    Merge,
+}
+
+// When and by whom a value was written. Clients stamp writes themselves, so
+// a write made earlier but delivered late still counts as earlier. Ties on
+// the clock are broken by writer name so every replica picks the same winner.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Stamp {
+    pub millis: i64,
+    pub writer: String,
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Register { value: String, stamp: Stamp },
+    Set(BTreeSet<String>),
+}
+
+impl Value {
+    fn values(&self) -> Vec<String> {
+        match self {
+            Value::Register { value, .. } => vec![value.clone()],
+            Value::Set(values) => values.iter().cloned().collect(),
+        }
+    }
+}
+
+// Free-form key/value notes on accounts. Keys default to last-write-wins
+// unless defined otherwise.
+pub struct MetadataStore {
+    policies: HashMap<String, MergePolicy>,
+    accounts: HashMap<String, BTreeMap<String, Value>>,
+}
+
+impl MetadataStore {
+    pub fn new() -> Self {
+        MetadataStore {
+            policies: HashMap::new(),
+            accounts: HashMap::new(),
+        }
+    }
+
+    // Only affects values written from now on. Returns the previous policy.
+    pub fn define_key(&mut self, key: &str, policy: MergePolicy) -> Option<MergePolicy> {
+        self.policies.insert(key.to_string(), policy)
+    }
+
+    // Apply one write and return the key's values afterwards
+    pub fn write(&mut self, account: &str, key: &str, value: &str, stamp: Stamp) -> Vec<String> {
+        let policy = self.policies.get(key).copied().unwrap_or(MergePolicy::LastWriteWins);
+        let entries = self.accounts.entry(account.to_string()).or_default();
+
+        let entry = entries.entry(key.to_string());
+        let merged = match policy {
+            MergePolicy::LastWriteWins => {
+                let current = entry.or_insert_with(|| Value::Register { value: value.to_string(), stamp: stamp.clone() });
+                match current {
+                    Value::Register { value: current_value, stamp: current_stamp } => {
+                        if stamp > *current_stamp {
+                            *current_value = value.to_string();
+                            *current_stamp = stamp;
+                        }
+                    },
+                    // The key changed policy: start a fresh register
+                    Value::Set(_) => *current = Value::Register { value: value.to_string(), stamp },
+                }
+                current
+            },
+            MergePolicy::Merge => {
+                let current = entry.or_insert_with(|| Value::Set(BTreeSet::new()));
+                match current {
+                    Value::Set(values) => {
+                        values.insert(value.to_string());
+                    },
+                    Value::Register { value: previous, .. } => {
+                        let values = BTreeSet::from([previous.clone(), value.to_string()]);
+                        *current = Value::Set(values);
+                    },
+                }
+                current
+            },
+        };
+        merged.values()
+    }
+
+    pub fn get(&self, account: &str) -> BTreeMap<String, Vec<String>> {
+        self.accounts
+            .get(account)
+            .map(|entries| entries.iter().map(|(key, value)| (key.clone(), value.values())).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl Default for MetadataStore {
+    fn default() -> Self {
+        MetadataStore::new()
+    }
+}
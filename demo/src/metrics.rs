@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::{SendError, TrySendError};
+use tokio::time::{Duration, Instant};
+
+use crate::chaos::{self, Subsystem};
+
+// Counters shared by every channel created under one name, updated by every
+// clone of their senders
+#[derive(Debug, Default)]
+pub struct ChannelStats {
+    // Channels created under the name
+    channels: AtomicU64,
+    // Largest buffer among them
+    capacity: AtomicUsize,
+    sent: AtomicU64,
+    // Total time senders spent waiting for room in the buffer
+    send_wait_nanos: AtomicU64,
+    max_depth: AtomicUsize,
+    // Messages refused because the buffer was full or the receiver was gone
+    dropped: AtomicU64,
+}
+
+impl ChannelStats {
+    // The shared entry for `name`, counting one more channel of `buffer`
+    // slots under it. For channels that aren't tokio's, like the ones `rt`
+    // hands out on other runtimes.
+    pub(crate) fn register(name: &'static str, buffer: usize) -> Arc<ChannelStats> {
+        let stats = Arc::clone(CHANNELS.lock().unwrap().entry(name).or_default());
+        stats.channels.fetch_add(1, Ordering::Relaxed);
+        stats.capacity.fetch_max(buffer, Ordering::Relaxed);
+        stats
+    }
+
+    pub(crate) fn record_wait(&self, waited: Duration) {
+        self.send_wait_nanos.fetch_add(waited.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    // A send that went through, leaving `depth` messages buffered, or one
+    // that was refused
+    pub(crate) fn record(&self, sent: bool, depth: usize) {
+        if sent {
+            self.sent.fetch_add(1, Ordering::Relaxed);
+            self.max_depth.fetch_max(depth, Ordering::Relaxed);
+        } else {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+// Stats for every name passed to `channel_with_metrics`, for the report.
+// Channels share their name's entry, so this grows with the names in use
+// rather than with every channel ever created.
+static CHANNELS: Mutex<BTreeMap<&'static str, Arc<ChannelStats>>> = Mutex::new(BTreeMap::new());
+
+// Last value reported for each gauge, e.g. memory held by the ledger
+static GAUGES: Mutex<BTreeMap<&'static str, u64>> = Mutex::new(BTreeMap::new());
+
+pub fn set_gauge(name: &'static str, value: u64) {
+    GAUGES.lock().unwrap().insert(name, value);
+}
+
+// Sender that records send latency, buffer depth and drops. It derefs to the
+// plain tokio sender so it can be passed wherever `&mpsc::Sender` is expected.
+#[derive(Debug)]
+pub struct MeteredSender<T> {
+    inner: mpsc::Sender<T>,
+    stats: Arc<ChannelStats>,
+}
+
+impl<T> Clone for MeteredSender<T> {
+    fn clone(&self) -> Self {
+        MeteredSender {
+            inner: self.inner.clone(),
+            stats: Arc::clone(&self.stats),
+        }
+    }
+}
+
+impl<T> Deref for MeteredSender<T> {
+    type Target = mpsc::Sender<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> MeteredSender<T> {
+    pub async fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let started = Instant::now();
+        chaos::inject(Subsystem::Channel).await;
+        let result = self.inner.send(value).await;
+        self.stats.record_wait(started.elapsed());
+        self.record(result.is_ok());
+        result
+    }
+
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        if chaos::channels_full() {
+            self.record(false);
+            return Err(TrySendError::Full(value));
+        }
+        let result = self.inner.try_send(value);
+        self.record(result.is_ok());
+        result
+    }
+
+    fn record(&self, sent: bool) {
+        self.stats.record(sent, self.inner.max_capacity() - self.inner.capacity());
+    }
+}
+
+// Drop-in replacement for `mpsc::channel` that counts the channel under
+// `name` so its utilization shows up in the channel report
+pub fn channel_with_metrics<T>(name: &'static str, buffer: usize) -> (MeteredSender<T>, mpsc::Receiver<T>) {
+    let (tx, rx) = mpsc::channel(buffer);
+    (MeteredSender { inner: tx, stats: ChannelStats::register(name, buffer) }, rx)
+}
+
+// Print the totals for every channel name used so far
+pub fn print_channel_report() {
+    let channels = CHANNELS.lock().unwrap();
+    if channels.is_empty() {
+        return;
+    }
+
+    println!("\n=== Channel Metrics ===");
+    println!("{:<12} {:>8} {:>8} {:>14} {:>12} {:>8}", "channel", "count", "sent", "avg send wait", "max depth", "dropped");
+    for (name, stats) in channels.iter() {
+        let sent = stats.sent.load(Ordering::Relaxed);
+        let avg_wait = Duration::from_nanos(stats.send_wait_nanos.load(Ordering::Relaxed) / sent.max(1));
+        println!(
+            "{:<12} {:>8} {:>8} {:>14?} {:>7}/{:<4} {:>8}",
+            name,
+            stats.channels.load(Ordering::Relaxed),
+            sent,
+            avg_wait,
+            stats.max_depth.load(Ordering::Relaxed),
+            stats.capacity.load(Ordering::Relaxed),
+            stats.dropped.load(Ordering::Relaxed)
+        );
+    }
+}
+
+pub fn print_gauges() {
+    let gauges = GAUGES.lock().unwrap();
+    if gauges.is_empty() {
+        return;
+    }
+
+    println!("\n=== Gauges ===");
+    for (name, value) in gauges.iter() {
+        println!("{:<20} {:>12}", name, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channels_with_one_name_share_an_entry() {
+        let before = CHANNELS.lock().unwrap().len();
+        for _ in 0..100 {
+            let (tx, _rx) = channel_with_metrics::<u8>("metrics-test", 1);
+            tx.try_send(1).unwrap();
+        }
+        let channels = CHANNELS.lock().unwrap();
+        assert!(channels.len() <= before + 1);
+        let stats = &channels["metrics-test"];
+        assert_eq!(stats.channels.load(Ordering::Relaxed), 100);
+        assert_eq!(stats.sent.load(Ordering::Relaxed), 100);
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::bank::BankEvent;
+use crate::ids::Id;
+use crate::projection::Projection;
+use crate::pubsub::Envelope;
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub transaction: Id,
+    pub account: String,
+    pub message: String,
+}
+
+// Where notifications go. An error leaves the notification unsent, to be
+// retried.
+pub trait Sink: Send + 'static {
+    fn send(&mut self, notification: &Notification) -> Result<(), String>;
+}
+
+// Appends a line per notification to a file
+pub struct FileSink {
+    path: PathBuf,
+}
+
+impl FileSink {
+    pub fn new(path: PathBuf) -> Self {
+        FileSink { path }
+    }
+}
+
+impl Sink for FileSink {
+    fn send(&mut self, notification: &Notification) -> Result<(), String> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("{}: {}", self.path.display(), e))?;
+        writeln!(file, "{}\t{}\t{}", notification.transaction, notification.account, notification.message)
+            .map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+}
+
+// A notification that went out, with the event that caused it
+#[derive(Debug, Clone)]
+pub struct Sent {
+    pub notification: Notification,
+    pub seq: u64,
+    // 1, plus one per admin resend
+    pub deliveries: u32,
+}
+
+struct State {
+    sink: Box<dyn Sink>,
+    sent: HashMap<Id, Sent>,
+    order: Vec<Id>,
+    duplicates: u64,
+}
+
+// The sent log, keyed by transaction, and the sink it guards. Events are
+// delivered at least once: retries, and replays after a restart from an
+// old checkpoint, bring some back. Checking the log before sending makes
+// that exactly one notification per committed transaction. Clones share
+// the log, so a restarted notifier and admin commands see the same one.
+#[derive(Clone)]
+pub struct Notifications {
+    state: Arc<Mutex<State>>,
+}
+
+impl Notifications {
+    pub fn new(sink: impl Sink) -> Self {
+        Notifications {
+            state: Arc::new(Mutex::new(State {
+                sink: Box::new(sink),
+                sent: HashMap::new(),
+                order: vec![],
+                duplicates: 0,
+            })),
+        }
+    }
+
+    fn deliver(&self, seq: u64, notification: Notification) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        if state.sent.contains_key(&notification.transaction) {
+            state.duplicates += 1;
+            return Ok(());
+        }
+        state.sink.send(&notification)?;
+        let transaction = notification.transaction;
+        state.order.push(transaction);
+        state.sent.insert(transaction, Sent { notification, seq, deliveries: 1 });
+        Ok(())
+    }
+
+    // Admin: send a transaction's notification again, e.g. for a customer
+    // who lost it. Errors for transactions nothing was sent for.
+    pub fn resend(&self, transaction: Id) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        let State { sink, sent, .. } = &mut *state;
+        let Some(record) = sent.get_mut(&transaction) else {
+            return Err(format!("No notification was sent for transaction {}", transaction));
+        };
+        sink.send(&record.notification)?;
+        record.deliveries += 1;
+        Ok(())
+    }
+
+    // Everything sent so far, in the order it first went out
+    pub fn sent(&self) -> Vec<Sent> {
+        let state = self.state.lock().unwrap();
+        state.order.iter().map(|transaction| state.sent[transaction].clone()).collect()
+    }
+
+    // Redelivered events that were skipped because they'd been notified
+    pub fn duplicates(&self) -> u64 {
+        self.state.lock().unwrap().duplicates
+    }
+}
+
+// Projection telling payers about large outgoing payments. Only committed
+// transactions are published, so nothing is sent for a refused or
+// rolled-back one.
+pub struct Notifier {
+    threshold: i32,
+    notifications: Notifications,
+}
+
+impl Notifier {
+    pub fn new(threshold: i32, notifications: Notifications) -> Self {
+        Notifier { threshold, notifications }
+    }
+}
+
+impl Projection<BankEvent> for Notifier {
+    fn apply(&mut self, envelope: &Envelope<BankEvent>) -> Result<(), String> {
+        let (transaction, from, message) = match &envelope.event {
+            BankEvent::Transferred { transaction, from, to, currency, amount, .. } if *amount >= self.threshold => {
+                (*transaction, from, format!("Large payment: {} {} sent to {}", amount, currency, to))
+            },
+            BankEvent::Exchanged { transaction, from, to, currency, amount, .. } if *amount >= self.threshold => {
+                (*transaction, from, format!("Large payment: {} {} sent to {}", amount, currency, to))
+            },
+            _ => return Ok(()),
+        };
+        let notification = Notification { transaction, account: from.clone(), message };
+        self.notifications.deliver(envelope.seq, notification)
+    }
+}
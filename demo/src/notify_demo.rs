@@ -0,0 +1,191 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{oneshot, Notify};
+use tokio::task::{yield_now, JoinSet};
+use tokio::time::{sleep, timeout, Duration, Instant};
+
+use crate::config::Config;
+use crate::scenario::{scenario, Scenario};
+use crate::signal::WorkSignal;
+
+// Work queue shared by a producer and a single worker. Each item records
+// when it was queued so the worker can measure its wakeup latency.
+struct WorkQueue {
+    items: Mutex<VecDeque<Instant>>,
+    signal: WorkSignal,
+}
+
+impl WorkQueue {
+    fn new() -> Self {
+        WorkQueue {
+            items: Mutex::new(VecDeque::new()),
+            signal: WorkSignal::new(),
+        }
+    }
+
+    fn push(&self) {
+        self.items.lock().unwrap().push_back(Instant::now());
+        self.signal.notify();
+    }
+
+    fn pop(&self) -> Option<Instant> {
+        self.items.lock().unwrap().pop_front()
+    }
+}
+
+async fn produce(queue: Arc<WorkQueue>, items: usize) {
+    for _ in 0..items {
+        sleep(Duration::from_millis(37)).await;
+        queue.push();
+    }
+}
+
+// Checks the queue on a fixed interval, whether or not anything arrived
+async fn polling_worker(queue: Arc<WorkQueue>, items: usize, interval: Duration) -> Vec<Duration> {
+    let mut latencies = vec![];
+    while latencies.len() < items {
+        while let Some(queued_at) = queue.pop() {
+            latencies.push(queued_at.elapsed());
+        }
+        if latencies.len() < items {
+            sleep(interval).await;
+        }
+    }
+    latencies
+}
+
+// Sleeps until the producer signals new work
+async fn notified_worker(queue: Arc<WorkQueue>, items: usize) -> Vec<Duration> {
+    let mut latencies = vec![];
+    while latencies.len() < items {
+        while let Some(queued_at) = queue.pop() {
+            latencies.push(queued_at.elapsed());
+        }
+        if latencies.len() < items {
+            queue.signal.wait().await;
+        }
+    }
+    latencies
+}
+
+fn print_latencies(label: &str, latencies: &[Duration]) {
+    let total: Duration = latencies.iter().sum();
+    let max = latencies.iter().max().copied().unwrap_or_default();
+    println!(
+        "{:<16} {} items - avg wakeup {:>10?}, max {:>10?}",
+        label,
+        latencies.len(),
+        total / latencies.len().max(1) as u32,
+        max
+    );
+}
+
+async fn run_notify_wakeup_example(_cfg: Config) {
+    println!("\n=== Notify Wakeup Example (Signaling vs Polling) ===");
+    let items = 20;
+
+    let queue = Arc::new(WorkQueue::new());
+    let producer = tokio::spawn(produce(Arc::clone(&queue), items));
+    let latencies = polling_worker(queue, items, Duration::from_millis(100)).await;
+    producer.await.unwrap();
+    print_latencies("Polling (100ms)", &latencies);
+
+    let queue = Arc::new(WorkQueue::new());
+    let producer = tokio::spawn(produce(Arc::clone(&queue), items));
+    let latencies = notified_worker(queue, items).await;
+    producer.await.unwrap();
+    print_latencies("Notify", &latencies);
+}
+
+// How long to wait before concluding a wakeup was lost
+const LOST: Duration = Duration::from_millis(200);
+
+async fn run_lost_wakeup_example(cfg: Config) {
+    println!("\n=== Lost Wakeup Example (Notify Pitfalls and WorkSignal) ===");
+
+    // Pitfall: `notify_waiters` only wakes tasks already waiting. A worker
+    // that checks the queue, finds it empty and is preempted before it
+    // waits misses the signal and sleeps with work queued.
+    let queue = Arc::new(Mutex::new(VecDeque::new()));
+    let notify = Arc::new(Notify::new());
+    let (checked_tx, checked) = oneshot::channel();
+    let (resume, resume_rx) = oneshot::channel::<()>();
+    let worker = {
+        let (queue, notify) = (Arc::clone(&queue), Arc::clone(&notify));
+        tokio::spawn(async move {
+            let empty = queue.lock().unwrap().is_empty();
+            let _ = checked_tx.send(());
+            if !empty {
+                return true;
+            }
+            // Preempted between the check and the wait
+            let _ = resume_rx.await;
+            timeout(LOST, notify.notified()).await.is_ok()
+        })
+    };
+    let _ = checked.await;
+    queue.lock().unwrap().push_back(1);
+    notify.notify_waiters();
+    let _ = resume.send(());
+    let woken = worker.await.unwrap();
+    println!("notify_waiters between check and wait: woken = {} ({} item(s) waiting)", woken, queue.lock().unwrap().len());
+
+    // Fix 1: `notify_one` stores a permit when nobody is waiting yet
+    let notify = Notify::new();
+    notify.notify_one();
+    let woken = timeout(LOST, notify.notified()).await.is_ok();
+    println!("notify_one before the wait:           woken = {}", woken);
+
+    // Fix 2: register interest before checking the condition, so a
+    // broadcast sent after the check still counts
+    let notify = Notify::new();
+    let notified = notify.notified();
+    tokio::pin!(notified);
+    notified.as_mut().enable();
+    notify.notify_waiters();
+    let woken = timeout(LOST, notified).await.is_ok();
+    println!("enable() before check, then wait:     woken = {}", woken);
+
+    // A pool built on WorkSignal: every job is handled and every worker
+    // sees the close, however the wakeups interleave
+    let queue = Arc::new(WorkQueue::new());
+    let handled = Arc::new(AtomicUsize::new(0));
+    let mut pool = JoinSet::new();
+    for id in 0..3 {
+        let (queue, handled) = (Arc::clone(&queue), Arc::clone(&handled));
+        let work = cfg.work_delay / 10;
+        pool.spawn(async move {
+            let mut mine = 0;
+            loop {
+                while queue.pop().is_some() {
+                    sleep(work).await;
+                    handled.fetch_add(1, Ordering::SeqCst);
+                    mine += 1;
+                }
+                if queue.signal.is_closed() {
+                    break;
+                }
+                queue.signal.wait().await;
+            }
+            (id, mine)
+        });
+    }
+    for _ in 0..12 {
+        queue.push();
+        yield_now().await;
+    }
+    queue.signal.close();
+    while let Some(result) = pool.join_next().await {
+        let (id, mine) = result.unwrap();
+        println!("Worker {} handled {} job(s)", id, mine);
+    }
+    println!("Pool handled {} of 12 jobs, all workers stopped", handled.load(Ordering::SeqCst));
+}
+
+pub fn scenarios() -> Vec<Box<dyn Scenario>> {
+    vec![
+        scenario("notify-wakeup", "Worker woken by Notify vs polling every 100ms", run_notify_wakeup_example),
+        scenario("lost-wakeup", "Lost Notify wakeups and a WorkSignal-based worker pool", run_lost_wakeup_example),
+    ]
+}
@@ -0,0 +1,190 @@
+use chrono::Local;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration};
+
+use crate::allocs;
+use crate::bank::{BankError, BankMessage};
+use crate::client;
+use crate::currency::Currency;
+use crate::ledger::{is_internal, EntryKind, JournalEntry, Ledger};
+use crate::metrics::{self, channel_with_metrics, MeteredSender};
+
+// Shipments a follower can fall behind by before the leader cuts it off
+const FOLLOWER_BUFFER: usize = 64;
+
+// A committed journal entry on its way to a follower, with the leader's
+// latest sequence number when it was sent
+#[derive(Debug, Clone)]
+pub struct Shipment {
+    pub entry: JournalEntry,
+    pub head: u64,
+}
+
+// Leader side, kept by the manager: streams every entry it commits to the
+// followers connected to it
+pub struct Oplog {
+    followers: Vec<MeteredSender<Shipment>>,
+    // Last entry handed to the followers
+    shipped: u64,
+}
+
+impl Oplog {
+    pub fn new(ledger: &Ledger) -> Self {
+        Oplog { followers: vec![], shipped: head(ledger) }
+    }
+
+    // Connect a follower that has applied everything up to `after`. It gets
+    // the entries since then first, then each new one as it's committed.
+    // A follower starting from nothing can take a compaction summary in
+    // place of the entries it replaced; one partway through them can't.
+    pub fn follow(&mut self, ledger: &Ledger, after: u64) -> Result<mpsc::Receiver<Shipment>, BankError> {
+        let entries = ledger.entries();
+        if let Some(first) = entries.first() {
+            if first.kind == EntryKind::Summary && after > 0 && after < first.seq {
+                return Err(BankError::Rejected("Oplog compacted past that point"));
+            }
+        }
+
+        let backlog: Vec<&JournalEntry> = entries.iter().filter(|entry| entry.seq > after).collect();
+        let (tx, rx) = channel_with_metrics("oplog", backlog.len() + FOLLOWER_BUFFER);
+        let head = head(ledger);
+        for entry in backlog {
+            // The channel is sized for the backlog, so this only fails when
+            // chaos fills channels; the follower is cut off and reconnects
+            if tx.try_send(Shipment { entry: entry.clone(), head }).is_err() {
+                return Ok(rx);
+            }
+        }
+        self.followers.push(tx);
+        Ok(rx)
+    }
+
+    // Ship whatever was committed since the last call. A follower too far
+    // behind to take it all is dropped; it reconnects and catches up from
+    // where it got to.
+    pub fn ship(&mut self, ledger: &Ledger) {
+        let head = head(ledger);
+        if head == self.shipped {
+            return;
+        }
+        let new: Vec<&JournalEntry> = ledger.entries().iter().filter(|entry| entry.seq > self.shipped).collect();
+        self.followers.retain(|follower| {
+            new.iter().all(|entry| follower.try_send(Shipment { entry: (*entry).clone(), head }).is_ok())
+        });
+        self.shipped = head;
+        metrics::set_gauge("oplog_followers", self.followers.len() as u64);
+    }
+}
+
+fn head(ledger: &Ledger) -> u64 {
+    ledger.entries().last().map_or(0, |entry| entry.seq)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FollowerStats {
+    // Last entry applied
+    pub applied: u64,
+    // Entries the leader had committed past it when it was applied
+    pub behind: u64,
+    // From the leader recording the last entry to it being applied here
+    pub delay: Duration,
+    // Connections after the first, whether cut off or refused
+    pub reconnects: u64,
+    // Times the follower had to start over from a compacted oplog
+    pub bootstraps: u64,
+}
+
+// Base-currency customer balances as replayed from the oplog. An account
+// shows up once something is posted to it.
+#[derive(Default)]
+struct Replica {
+    balances: HashMap<String, i32>,
+    stats: FollowerStats,
+}
+
+impl Replica {
+    fn apply(&mut self, shipment: Shipment) {
+        let entry = shipment.entry;
+        // Already applied before a reconnect
+        if entry.seq <= self.stats.applied {
+            return;
+        }
+        for posting in &entry.postings {
+            if posting.currency == Currency::BASE && !is_internal(&posting.account) {
+                *self.balances.entry(posting.account.to_string()).or_insert(0) += posting.signed_amount();
+            }
+        }
+        self.stats.applied = entry.seq;
+        self.stats.behind = shipment.head.saturating_sub(entry.seq);
+        self.stats.delay = (Local::now() - entry.recorded_at).to_std().unwrap_or_default();
+        metrics::set_gauge("follower_behind", self.stats.behind);
+    }
+
+    fn reset(&mut self) {
+        self.balances.clear();
+        self.stats.applied = 0;
+        self.stats.bootstraps += 1;
+    }
+}
+
+// Read replica of a leader bank. It applies the leader's committed entries
+// to its own copy of the balances and serves reads from that, so reads
+// never queue on the leader. When cut off or the leader is unavailable it
+// waits `retry` and reconnects from the last entry it applied.
+pub struct Follower {
+    replica: Arc<Mutex<Replica>>,
+    task: JoinHandle<()>,
+}
+
+impl Follower {
+    pub fn spawn(leader: MeteredSender<BankMessage>, retry: Duration) -> Self {
+        let replica = Arc::new(Mutex::new(Replica::default()));
+        let task = tokio::spawn(allocs::profiled("oplog-follower", follow(leader, Arc::clone(&replica), retry)));
+        Follower { replica, task }
+    }
+
+    pub fn balance(&self, account: &str) -> Option<i32> {
+        self.replica.lock().unwrap().balances.get(account).copied()
+    }
+
+    pub fn balances(&self) -> HashMap<String, i32> {
+        self.replica.lock().unwrap().balances.clone()
+    }
+
+    pub fn stats(&self) -> FollowerStats {
+        self.replica.lock().unwrap().stats
+    }
+
+    pub fn shutdown(self) {
+        self.task.abort();
+    }
+}
+
+async fn follow(leader: MeteredSender<BankMessage>, replica: Arc<Mutex<Replica>>, retry: Duration) {
+    let mut connected_before = false;
+    loop {
+        if connected_before {
+            replica.lock().unwrap().stats.reconnects += 1;
+        }
+        connected_before = true;
+
+        let after = replica.lock().unwrap().stats.applied;
+        match client::ship_oplog(&leader, after).await {
+            Ok(mut shipments) => {
+                while let Some(shipment) = shipments.recv().await {
+                    replica.lock().unwrap().apply(shipment);
+                }
+            },
+            // Start over from the summary rather than wait
+            Err(BankError::Rejected(_)) => {
+                replica.lock().unwrap().reset();
+                continue;
+            },
+            Err(_) => {},
+        }
+        sleep(retry).await;
+    }
+}
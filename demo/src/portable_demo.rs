@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::ledger::Ledger;
+use crate::rt;
+use crate::scenario::{scenario, Scenario};
+
+// The same request/response actor as the bank manager, written only against
+// `rt` so it runs unchanged on whichever runtime the crate was built for.
+// Replies go over a capacity-1 channel instead of a oneshot.
+enum LedgerMessage {
+    Deposit {
+        account: String,
+        amount: i32,
+        respond_to: rt::Sender<Result<i32, &'static str>>,
+    },
+    Balance {
+        account: String,
+        respond_to: rt::Sender<Option<i32>>,
+    },
+}
+
+// The ledger itself never touches a runtime, so it is portable as is
+async fn run_ledger_actor(mut rx: rt::Receiver<LedgerMessage>, accounts: HashMap<String, i32>, delay: Duration) {
+    let mut ledger = Ledger::with_opening_balances(accounts);
+
+    while let Some(msg) = rx.recv().await {
+        rt::sleep(delay).await;
+        match msg {
+            LedgerMessage::Deposit { account, amount, respond_to } => {
+                let _ = respond_to.send(ledger.deposit(&account, amount)).await;
+            },
+            LedgerMessage::Balance { account, respond_to } => {
+                let _ = respond_to.send(ledger.balance(&account)).await;
+            },
+        }
+    }
+}
+
+async fn run_portable_actor_example(cfg: Config) {
+    println!("\n=== Portable Actor Example (Running on {}) ===", rt::NAME);
+    let start = Instant::now();
+    let (tx, rx) = rt::channel(32);
+
+    let mut accounts = HashMap::new();
+    accounts.insert("Alice".to_string(), 100);
+    let actor = rt::spawn(run_ledger_actor(rx, accounts, cfg.work_delay / 4));
+
+    let mut clients = vec![];
+    for i in 0..cfg.clients {
+        let tx = tx.clone();
+        clients.push(rt::spawn(async move {
+            let (resp_tx, mut resp_rx) = rt::channel(1);
+            let sent = tx.send(LedgerMessage::Deposit {
+                account: "Alice".to_string(),
+                amount: 10,
+                respond_to: resp_tx,
+            }).await;
+            if sent.is_err() {
+                return;
+            }
+            match resp_rx.recv().await {
+                Some(Ok(balance)) => println!("[{:>4}ms] Client {} deposited - Balance: {}", start.elapsed().as_millis(), i, balance),
+                Some(Err(e)) => println!("[{:>4}ms] Client {} failed - {}", start.elapsed().as_millis(), i, e),
+                None => println!("[{:>4}ms] Client {} lost the actor", start.elapsed().as_millis(), i),
+            }
+        }));
+    }
+    for client in clients {
+        client.join().await;
+    }
+
+    let (resp_tx, mut resp_rx) = rt::channel(1);
+    if tx.send(LedgerMessage::Balance { account: "Alice".to_string(), respond_to: resp_tx }).await.is_ok() {
+        if let Some(Some(balance)) = resp_rx.recv().await {
+            println!("[{:>4}ms] Final balance: {}", start.elapsed().as_millis(), balance);
+        }
+    }
+
+    drop(tx);
+    actor.join().await;
+}
+
+pub fn scenarios() -> Vec<Box<dyn Scenario>> {
+    vec![
+        scenario("portable-actor", "An actor written against the runtime-neutral rt module", run_portable_actor_example),
+    ]
+}
@@ -0,0 +1,85 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tokio::time::Duration;
+
+use crate::chaos::{ChaosSettings, Subsystem};
+use crate::config::Config;
+
+pub const DEFAULT_FILE: &str = "presets.json";
+
+// A named run setup. Every field is required, so a preset always means the
+// same run whatever the defaults are.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Preset {
+    // Workload
+    clients: usize,
+    work_delay_ms: u64,
+    hot_share: u32,
+    read_rounds: usize,
+    // Topology
+    mailbox: usize,
+    // Chaos
+    chaos: PresetChaos,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PresetChaos {
+    store: Option<Latency>,
+    channel: Option<Latency>,
+    cache: Option<Latency>,
+    channels_full: bool,
+    cache_misses: bool,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Latency {
+    delay_ms: u64,
+    jitter_ms: u64,
+}
+
+// The config for preset `name` in the JSON file at `path`, an object of
+// presets by name
+pub fn load(path: &Path, name: &str) -> Result<Config, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut presets: BTreeMap<String, Preset> =
+        serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let Some(preset) = presets.remove(name) else {
+        let known: Vec<String> = presets.into_keys().collect();
+        return Err(format!("Unknown preset: {} (known: {})", name, known.join(", ")));
+    };
+    if preset.hot_share > 100 {
+        return Err(format!("Preset {}: hot_share is a percentage", name));
+    }
+    if preset.mailbox == 0 {
+        return Err(format!("Preset {}: mailbox must hold at least one message", name));
+    }
+
+    let latency = [
+        (Subsystem::Store, preset.chaos.store),
+        (Subsystem::Channel, preset.chaos.channel),
+        (Subsystem::Cache, preset.chaos.cache),
+    ]
+    .into_iter()
+    .filter_map(|(subsystem, latency)| {
+        latency.map(|latency| (subsystem, Duration::from_millis(latency.delay_ms), Duration::from_millis(latency.jitter_ms)))
+    })
+    .collect();
+
+    Ok(Config {
+        clients: preset.clients,
+        work_delay: Duration::from_millis(preset.work_delay_ms),
+        hot_share: preset.hot_share,
+        read_rounds: preset.read_rounds,
+        mailbox: preset.mailbox,
+        chaos: ChaosSettings {
+            latency,
+            channels_full: preset.chaos.channels_full,
+            cache_misses: preset.chaos.cache_misses,
+        },
+        ..Config::default()
+    })
+}
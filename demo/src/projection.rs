@@ -0,0 +1,273 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+
+use crate::pubsub::Envelope;
+
+// Read model kept up to date from a subscription. An error from `apply`
+// doesn't lose the event: it waits in the projection's retry queue.
+pub trait Projection<T>: Send + 'static {
+    fn apply(&mut self, envelope: &Envelope<T>) -> Result<(), String>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    // Failed events waiting for another attempt; past this the oldest is
+    // dead-lettered to make room
+    pub capacity: usize,
+    // Attempts, the first one included, before an event is treated as poison
+    pub max_attempts: u32,
+    pub retry_every: Duration,
+}
+
+// An event a projection gave up on, with the error from its last attempt
+#[derive(Debug, Clone)]
+pub struct DeadLetter<T> {
+    pub projection: String,
+    pub envelope: Envelope<T>,
+    pub attempts: u32,
+    pub error: String,
+}
+
+// Sink for dead letters. Clones share it, so several projections can feed one
+// sink that's inspected in a single place.
+#[derive(Clone)]
+pub struct DeadLetters<T> {
+    letters: Arc<Mutex<Vec<DeadLetter<T>>>>,
+}
+
+impl<T: Clone> DeadLetters<T> {
+    // Everything dead-lettered so far, oldest first
+    pub fn list(&self) -> Vec<DeadLetter<T>> {
+        self.letters.lock().unwrap().clone()
+    }
+
+    // Take the letters out, e.g. to replay them once the projection is fixed
+    pub fn drain(&self) -> Vec<DeadLetter<T>> {
+        std::mem::take(&mut *self.letters.lock().unwrap())
+    }
+
+    fn push(&self, letter: DeadLetter<T>) {
+        println!("Dead-lettered #{} for {} after {} attempt(s) - {}", letter.envelope.seq, letter.projection, letter.attempts, letter.error);
+        self.letters.lock().unwrap().push(letter);
+    }
+}
+
+impl<T> Default for DeadLetters<T> {
+    fn default() -> Self {
+        DeadLetters {
+            letters: Arc::new(Mutex::new(vec![])),
+        }
+    }
+}
+
+struct Pending<T> {
+    envelope: Envelope<T>,
+    attempts: u32,
+    error: String,
+}
+
+// One projection's failed events, in the order they'll be retried
+struct RetryQueue<T> {
+    projection: String,
+    policy: RetryPolicy,
+    pending: VecDeque<Pending<T>>,
+    dead_letters: DeadLetters<T>,
+}
+
+impl<T: Clone> RetryQueue<T> {
+    fn push(&mut self, pending: Pending<T>) {
+        if pending.attempts >= self.policy.max_attempts {
+            self.give_up(pending);
+            return;
+        }
+        self.pending.push_back(pending);
+        if self.pending.len() > self.policy.capacity {
+            let mut oldest = self.pending.pop_front().unwrap();
+            oldest.error = format!("retry queue full, last error: {}", oldest.error);
+            self.give_up(oldest);
+        }
+    }
+
+    // One pass over what's queued; events failing again go to the back
+    fn retry<P: Projection<T>>(&mut self, projection: &mut P) {
+        for _ in 0..self.pending.len() {
+            let mut pending = self.pending.pop_front().unwrap();
+            if let Err(error) = projection.apply(&pending.envelope) {
+                pending.attempts += 1;
+                pending.error = error;
+                self.push(pending);
+            }
+        }
+    }
+
+    fn give_up(&self, pending: Pending<T>) {
+        self.dead_letters.push(DeadLetter {
+            projection: self.projection.clone(),
+            envelope: pending.envelope,
+            attempts: pending.attempts,
+            error: pending.error,
+        });
+    }
+}
+
+// Applies every event from `events` to `projection` until the subscription
+// closes. Failed events are retried on each tick while new ones keep being
+// applied, so one bad event doesn't stall the read model. Once the
+// subscription closes the queue is worked off, and the projection is handed
+// back.
+pub async fn run_projection<T, P>(
+    name: String,
+    projection: P,
+    events: mpsc::Receiver<Envelope<T>>,
+    policy: RetryPolicy,
+    dead_letters: DeadLetters<T>,
+) -> P
+where
+    T: Clone + Send + 'static,
+    P: Projection<T>,
+{
+    run_projection_saving(name, projection, events, policy, dead_letters, |_, _| {}).await
+}
+
+// Like `run_projection`, saving the projection to `store` as it goes and
+// once more at the end. State resumed from a checkpoint covers
+// every event up to `from_seq`, so subscribe for the events after it.
+pub async fn run_checkpointed<T, P>(
+    name: String,
+    projection: P,
+    from_seq: u64,
+    events: mpsc::Receiver<Envelope<T>>,
+    policy: RetryPolicy,
+    dead_letters: DeadLetters<T>,
+    store: CheckpointStore,
+) -> P
+where
+    T: Clone + Send + 'static,
+    P: Projection<T> + Serialize,
+{
+    let checkpoint = name.clone();
+    let (mut saved, mut latest) = (from_seq, from_seq);
+    let projection = run_projection_saving(name, projection, events, policy, dead_letters, |projection: &P, seq| {
+        latest = seq;
+        if seq >= saved + store.every {
+            match store.save(&checkpoint, seq, projection) {
+                Ok(()) => saved = seq,
+                Err(e) => println!("Checkpoint for {} at #{} failed - {}", checkpoint, seq, e),
+            }
+        }
+    })
+    .await;
+    if latest > saved {
+        if let Err(e) = store.save(&checkpoint, latest, &projection) {
+            println!("Final checkpoint for {} at #{} failed - {}", checkpoint, latest, e);
+        }
+    }
+    projection
+}
+
+// `save` is called with the projection and the last sequence number applied
+// whenever nothing is waiting for a retry: only then does the state cover
+// every event up to that number, and nothing after it
+async fn run_projection_saving<T, P>(
+    name: String,
+    mut projection: P,
+    mut events: mpsc::Receiver<Envelope<T>>,
+    policy: RetryPolicy,
+    dead_letters: DeadLetters<T>,
+    mut save: impl FnMut(&P, u64),
+) -> P
+where
+    T: Clone + Send + 'static,
+    P: Projection<T>,
+{
+    let mut retries = RetryQueue { projection: name, policy, pending: VecDeque::new(), dead_letters };
+    let mut ticker = interval(policy.retry_every);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut last_seq = None;
+
+    loop {
+        tokio::select! {
+            received = events.recv() => match received {
+                Some(envelope) => {
+                    last_seq = Some(envelope.seq);
+                    if let Err(error) = projection.apply(&envelope) {
+                        retries.push(Pending { envelope, attempts: 1, error });
+                    }
+                },
+                None => break,
+            },
+            _ = ticker.tick(), if !retries.pending.is_empty() => retries.retry(&mut projection),
+        }
+        if let (Some(seq), true) = (last_seq, retries.pending.is_empty()) {
+            save(&projection, seq);
+        }
+    }
+
+    // Every queued event has a bounded number of attempts left, so this ends
+    while !retries.pending.is_empty() {
+        ticker.tick().await;
+        retries.retry(&mut projection);
+    }
+    if let Some(seq) = last_seq {
+        save(&projection, seq);
+    }
+    projection
+}
+
+// A projection's state as of a sequence number
+#[derive(Serialize, Deserialize)]
+struct Checkpoint<P> {
+    seq: u64,
+    state: P,
+}
+
+// Checkpoints as one JSON file per projection. State and sequence number
+// are written together and swapped in with a rename, so a crash leaves
+// either the old checkpoint or the new one, never a state from one and a
+// sequence number from the other.
+#[derive(Debug, Clone)]
+pub struct CheckpointStore {
+    dir: PathBuf,
+    // Sequence numbers a projection moves on before it's saved again
+    every: u64,
+}
+
+impl CheckpointStore {
+    pub fn new(dir: PathBuf, every: u64) -> Self {
+        CheckpointStore { dir, every }
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.checkpoint.json", name))
+    }
+
+    pub fn save<P: Serialize>(&self, name: &str, seq: u64, state: &P) -> Result<(), String> {
+        let path = self.path(name);
+        let staging = path.with_extension("json.tmp");
+        let json = serde_json::to_string(&Checkpoint { seq, state }).map_err(|e| e.to_string())?;
+        std::fs::write(&staging, json).map_err(|e| format!("{}: {}", staging.display(), e))?;
+        std::fs::rename(&staging, &path).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // The saved state and the sequence number it covers, or None if the
+    // projection was never checkpointed
+    pub fn load<P: DeserializeOwned>(&self, name: &str) -> Result<Option<(u64, P)>, String> {
+        let path = self.path(name);
+        let json = match std::fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+        let checkpoint: Checkpoint<P> = serde_json::from_str(&json).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Some((checkpoint.seq, checkpoint.state)))
+    }
+
+    pub fn remove(&self, name: &str) {
+        let _ = std::fs::remove_file(self.path(name));
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::VecDeque;
+use tokio::sync::mpsc;
+
+use crate::ids::{self, Id};
+use crate::metrics::{channel_with_metrics, MeteredSender};
+
+// Event as delivered to subscribers, tagged with its topic, a unique ID and
+// a sequence number that increases with every publish on the router
+#[derive(Debug, Clone)]
+pub struct Envelope<T> {
+    pub id: Id,
+    pub seq: u64,
+    pub topic: String,
+    pub event: T,
+}
+
+// Topic patterns are dot separated segments. `*` matches exactly one
+// segment, except as the last segment where it matches the rest of the
+// topic: `account.alice.*` matches `account.alice.deposit` and `transfers.*`
+// matches `transfers.alice.bob`.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    segments: Vec<String>,
+}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Self {
+        Pattern {
+            segments: pattern.split('.').map(str::to_string).collect(),
+        }
+    }
+
+    pub fn matches(&self, topic: &str) -> bool {
+        let topic: Vec<&str> = topic.split('.').collect();
+
+        for (i, segment) in self.segments.iter().enumerate() {
+            let is_last = i == self.segments.len() - 1;
+            match topic.get(i) {
+                None => return false,
+                Some(_) if segment == "*" && is_last => return true,
+                Some(_) if segment == "*" => {},
+                Some(part) if *part == segment.as_str() => {},
+                Some(_) => return false,
+            }
+        }
+
+        topic.len() == self.segments.len()
+    }
+}
+
+// A reconnecting subscriber asked for events older than the router retains
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryTruncated {
+    pub oldest: u64,
+}
+
+struct Subscriber<T> {
+    pattern: Pattern,
+    tx: MeteredSender<Envelope<T>>,
+    dropped: u64,
+}
+
+// Fans each published event out to the subscribers whose pattern matches its
+// topic. Every subscription has its own bounded buffer: a slow subscriber
+// loses events instead of holding up the publisher or the other subscribers.
+// The most recent events are kept so a subscriber that reconnects can catch
+// up on what it missed.
+pub struct Router<T> {
+    subscribers: Vec<Subscriber<T>>,
+    next_seq: u64,
+    history: VecDeque<Envelope<T>>,
+    history_limit: usize,
+}
+
+impl<T: Clone + Send + 'static> Router<T> {
+    pub fn new() -> Self {
+        Router::with_history(1024)
+    }
+
+    pub fn with_history(history_limit: usize) -> Self {
+        Router {
+            subscribers: vec![],
+            next_seq: 1,
+            history: VecDeque::new(),
+            history_limit,
+        }
+    }
+
+    pub fn subscribe(&mut self, pattern: &str, buffer: usize) -> mpsc::Receiver<Envelope<T>> {
+        let (tx, rx) = channel_with_metrics("events", buffer);
+        self.subscribers.push(Subscriber {
+            pattern: Pattern::new(pattern),
+            tx,
+            dropped: 0,
+        });
+        rx
+    }
+
+    // Subscribe again after a disconnect: every retained event matching the
+    // pattern with a sequence number after `last_seen` is delivered first,
+    // then live events follow on the same receiver.
+    //
+    // The live subscription is registered in the same step that copies the
+    // backlog, so the first live event is always the one right after the
+    // backlog's tail. The forwarding task still skips anything at or below
+    // the last sequence number it sent, so the handover can never repeat an
+    // event. Live events published while the backlog drains are moved out
+    // of the live buffer as they arrive and held until the backlog is sent,
+    // so a slow reader can't lose them to the catch-up. Once caught up, an
+    // overflowing live buffer drops and counts events as usual.
+    //
+    // A `last_seen` past the latest event, e.g. one saved before a restart
+    // renumbered the events, is taken to mean "caught up": delivery starts
+    // with the next event rather than waiting for a number that may never
+    // come.
+    pub fn resubscribe(
+        &mut self,
+        pattern: &str,
+        last_seen: u64,
+        buffer: usize,
+    ) -> Result<mpsc::Receiver<Envelope<T>>, HistoryTruncated> {
+        let last_seen = last_seen.min(self.last_seq());
+        let oldest = self.history.front().map_or(self.next_seq, |envelope| envelope.seq);
+        if last_seen + 1 < oldest {
+            return Err(HistoryTruncated { oldest });
+        }
+
+        let matcher = Pattern::new(pattern);
+        let backlog: Vec<Envelope<T>> = self
+            .history
+            .iter()
+            .filter(|envelope| envelope.seq > last_seen && matcher.matches(&envelope.topic))
+            .cloned()
+            .collect();
+        let mut live = self.subscribe(pattern, buffer);
+
+        let (tx, rx) = channel_with_metrics("catch-up", buffer);
+        tokio::spawn(async move {
+            // Keep draining the live buffer while the backlog is replayed,
+            // so a slow reader can't make it overflow during the catch-up
+            let mut pending = VecDeque::new();
+            let mut last_sent = last_seen;
+            for envelope in backlog {
+                last_sent = envelope.seq;
+                let send = tx.send(envelope);
+                tokio::pin!(send);
+                loop {
+                    tokio::select! {
+                        result = &mut send => {
+                            if result.is_err() {
+                                return;
+                            }
+                            break;
+                        },
+                        Some(envelope) = live.recv() => pending.push_back(envelope),
+                    }
+                }
+            }
+
+            loop {
+                let envelope = match pending.pop_front() {
+                    Some(envelope) => envelope,
+                    None => match live.recv().await {
+                        Some(envelope) => envelope,
+                        None => return,
+                    },
+                };
+                if envelope.seq <= last_sent {
+                    continue;
+                }
+                last_sent = envelope.seq;
+                if tx.send(envelope).await.is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(rx)
+    }
+
+    // Returns how many subscribers received the event
+    pub fn publish(&mut self, topic: &str, event: T) -> usize {
+        let envelope = Envelope {
+            id: ids::next_id(),
+            seq: self.next_seq,
+            topic: topic.to_string(),
+            event,
+        };
+        self.next_seq += 1;
+
+        // Forget subscribers that went away
+        self.subscribers.retain(|subscriber| !subscriber.tx.is_closed());
+
+        let mut delivered = 0;
+        for subscriber in self.subscribers.iter_mut().filter(|s| s.pattern.matches(topic)) {
+            match subscriber.tx.try_send(envelope.clone()) {
+                Ok(()) => delivered += 1,
+                Err(_) => subscriber.dropped += 1,
+            }
+        }
+
+        self.history.push_back(envelope);
+        if self.history.len() > self.history_limit {
+            self.history.pop_front();
+        }
+        delivered
+    }
+
+    // Sequence number of the latest event, 0 before the first
+    pub fn last_seq(&self) -> u64 {
+        self.next_seq - 1
+    }
+
+    // Events lost because a subscriber's buffer was full
+    pub fn dropped(&self) -> u64 {
+        self.subscribers.iter().map(|subscriber| subscriber.dropped).sum()
+    }
+}
+
+impl<T: Clone + Send + 'static> Default for Router<T> {
+    fn default() -> Self {
+        Router::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::Duration;
+
+    #[tokio::test]
+    async fn live_events_survive_a_slow_catch_up() {
+        let mut router = Router::new();
+        for i in 0..4 {
+            router.publish("account.alice.deposit", i);
+        }
+        let mut events = router.resubscribe("*", 0, 2).unwrap();
+        // Published before the reader takes anything, far past the buffer
+        for i in 4..20 {
+            router.publish("account.alice.deposit", i);
+            tokio::task::yield_now().await;
+        }
+
+        let mut seen = vec![];
+        while seen.len() < 20 {
+            let envelope = tokio::time::timeout(Duration::from_secs(1), events.recv()).await;
+            seen.push(envelope.expect("an event was lost").unwrap().event);
+        }
+        assert_eq!(seen, (0..20).collect::<Vec<_>>());
+        assert_eq!(router.dropped(), 0);
+    }
+
+    #[tokio::test]
+    async fn resubscribing_past_the_last_sequence_number_gets_new_events() {
+        for last_seen in [2, 1000, u64::MAX] {
+            let mut router: Router<u32> = Router::new();
+            router.publish("account.alice.deposit", 1);
+            let mut events = router.resubscribe("*", last_seen, 4).unwrap();
+            router.publish("account.alice.deposit", 2);
+            let envelope = tokio::time::timeout(Duration::from_secs(1), events.recv()).await.unwrap().unwrap();
+            assert_eq!((envelope.seq, envelope.event), (2, 2), "last seen {}", last_seen);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+use tokio::time::Duration;
+
+use crate::currency::Currency;
+use crate::ledger::{is_internal, EntryKind, Ledger};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Aggregate {
+    // Net amount posted to each account
+    Sum,
+    // Number of journal entries touching each account
+    Count,
+}
+
+// A tiny `SELECT account, SUM(amount) ... WHERE ... GROUP BY account`
+// over the journal, e.g. "sum of deposits per account in the last hour":
+//
+//     Query::journal().kind(EntryKind::Deposit).within(Duration::from_secs(3600)).run(&ledger)
+#[derive(Debug, Clone)]
+pub struct Query {
+    kind: Option<EntryKind>,
+    account: Option<String>,
+    within: Option<Duration>,
+    aggregate: Aggregate,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryRow {
+    pub account: String,
+    pub value: i64,
+}
+
+impl Query {
+    pub fn journal() -> Self {
+        Query {
+            kind: None,
+            account: None,
+            within: None,
+            aggregate: Aggregate::Sum,
+        }
+    }
+
+    pub fn kind(mut self, kind: EntryKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    pub fn account(mut self, account: &str) -> Self {
+        self.account = Some(account.to_string());
+        self
+    }
+
+    pub fn within(mut self, window: Duration) -> Self {
+        self.within = Some(window);
+        self
+    }
+
+    pub fn count(mut self) -> Self {
+        self.aggregate = Aggregate::Count;
+        self
+    }
+
+    // Rows are grouped per customer account and sorted by account name.
+    // Only postings in the base currency are counted. The window is read
+    // off the ledger's clock; one reaching back past its range covers
+    // everything.
+    pub fn run(&self, ledger: &Ledger) -> Vec<QueryRow> {
+        let cutoff = self
+            .within
+            .and_then(|window| chrono::Duration::from_std(window).ok())
+            .and_then(|window| ledger.wall_clock().checked_sub_signed(window));
+
+        let mut groups: BTreeMap<&str, i64> = BTreeMap::new();
+        for entry in ledger.entries() {
+            if self.kind.is_some_and(|kind| kind != entry.kind) {
+                continue;
+            }
+            if cutoff.is_some_and(|cutoff| entry.recorded_at < cutoff) {
+                continue;
+            }
+
+            for posting in &entry.postings {
+                let account = &*posting.account;
+                if is_internal(account) || posting.currency != Currency::BASE {
+                    continue;
+                }
+                if self.account.as_deref().is_some_and(|a| a != account) {
+                    continue;
+                }
+
+                let value = groups.entry(account).or_insert(0);
+                match self.aggregate {
+                    Aggregate::Sum => *value += i64::from(posting.signed_amount()),
+                    Aggregate::Count => *value += 1,
+                }
+            }
+        }
+
+        groups
+            .into_iter()
+            .map(|(account, value)| QueryRow { account: account.to_string(), value })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn windows_are_read_off_the_ledgers_clock() {
+        let mut ledger = Ledger::with_opening_balances(HashMap::from([("Alice".to_string(), 0)]));
+        ledger.deposit("Alice", 50).unwrap();
+        ledger.skew_clock(chrono::Duration::days(1));
+
+        let recent = Query::journal().kind(EntryKind::Deposit).within(Duration::from_secs(3600)).run(&ledger);
+        assert!(recent.is_empty());
+
+        let all = Query::journal().kind(EntryKind::Deposit).within(Duration::MAX).run(&ledger);
+        assert_eq!(all, vec![QueryRow { account: "Alice".to_string(), value: 50 }]);
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::fmt;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{Duration, Instant};
+
+use crate::bank::{BankError, BankMessage};
+use crate::client;
+use crate::metrics::{channel_with_metrics, MeteredSender};
+
+const MINUTE: Duration = Duration::from_secs(60);
+const HOUR: Duration = Duration::from_secs(60 * 60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Operations a client may perform in any sliding minute, hour and day
+#[derive(Debug, Clone, Copy)]
+pub struct QuotaLimits {
+    pub per_minute: u64,
+    pub per_hour: u64,
+    pub per_day: u64,
+}
+
+impl Default for QuotaLimits {
+    fn default() -> Self {
+        QuotaLimits {
+            per_minute: 60,
+            per_hour: 1_000,
+            per_day: 10_000,
+        }
+    }
+}
+
+// Sliding window approximated from two fixed windows: the previous window's
+// count is weighted by how much of it still overlaps the sliding window.
+// Constant memory per client, unlike keeping every timestamp for a day.
+#[derive(Debug)]
+struct SlidingWindow {
+    length: Duration,
+    started: Instant,
+    current: u64,
+    previous: u64,
+}
+
+impl SlidingWindow {
+    fn new(length: Duration, now: Instant) -> Self {
+        SlidingWindow { length, started: now, current: 0, previous: 0 }
+    }
+
+    fn roll(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.started);
+        if elapsed >= self.length * 2 {
+            self.previous = 0;
+            self.current = 0;
+            self.started = now;
+        } else if elapsed >= self.length {
+            self.previous = self.current;
+            self.current = 0;
+            self.started += self.length;
+        }
+    }
+
+    fn estimate(&mut self, now: Instant) -> u64 {
+        self.roll(now);
+        let overlap = 1.0 - now.duration_since(self.started).as_secs_f64() / self.length.as_secs_f64();
+        self.current + (self.previous as f64 * overlap).round() as u64
+    }
+}
+
+#[derive(Debug)]
+struct ClientQuota {
+    minute: SlidingWindow,
+    hour: SlidingWindow,
+    day: SlidingWindow,
+}
+
+impl ClientQuota {
+    fn new(now: Instant) -> Self {
+        ClientQuota {
+            minute: SlidingWindow::new(MINUTE, now),
+            hour: SlidingWindow::new(HOUR, now),
+            day: SlidingWindow::new(DAY, now),
+        }
+    }
+
+    fn usage(&mut self, now: Instant) -> QuotaUsage {
+        QuotaUsage {
+            minute: self.minute.estimate(now),
+            hour: self.hour.estimate(now),
+            day: self.day.estimate(now),
+        }
+    }
+}
+
+// Estimated operations in each sliding window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaUsage {
+    pub minute: u64,
+    pub hour: u64,
+    pub day: u64,
+}
+
+impl fmt::Display for QuotaUsage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/min, {}/hour, {}/day", self.minute, self.hour, self.day)
+    }
+}
+
+#[derive(Debug)]
+pub enum QuotaMessage {
+    // Count one operation for the client if it is within every limit
+    Acquire {
+        client: String,
+        respond_to: oneshot::Sender<Result<(), BankError>>
+    },
+    // Admin: current usage, without counting anything
+    Usage {
+        client: String,
+        respond_to: oneshot::Sender<QuotaUsage>
+    },
+    // Admin: forget a client's history
+    Reset {
+        client: String,
+        respond_to: oneshot::Sender<()>
+    },
+}
+
+// Quota actor: owns every client's counters, so checks never race
+pub async fn run_quota_manager(mut rx: mpsc::Receiver<QuotaMessage>, limits: QuotaLimits) {
+    let mut clients: HashMap<String, ClientQuota> = HashMap::new();
+
+    while let Some(msg) = rx.recv().await {
+        let now = Instant::now();
+        match msg {
+            QuotaMessage::Acquire { client, respond_to } => {
+                let quota = clients.entry(client).or_insert_with(|| ClientQuota::new(now));
+                let usage = quota.usage(now);
+                let result = if usage.minute >= limits.per_minute {
+                    Err(BankError::QuotaExceeded("minute"))
+                } else if usage.hour >= limits.per_hour {
+                    Err(BankError::QuotaExceeded("hour"))
+                } else if usage.day >= limits.per_day {
+                    Err(BankError::QuotaExceeded("day"))
+                } else {
+                    quota.minute.current += 1;
+                    quota.hour.current += 1;
+                    quota.day.current += 1;
+                    Ok(())
+                };
+                let _ = respond_to.send(result);
+            },
+            QuotaMessage::Usage { client, respond_to } => {
+                let usage = match clients.get_mut(&client) {
+                    Some(quota) => quota.usage(now),
+                    None => QuotaUsage { minute: 0, hour: 0, day: 0 },
+                };
+                let _ = respond_to.send(usage);
+            },
+            QuotaMessage::Reset { client, respond_to } => {
+                clients.remove(&client);
+                let _ = respond_to.send(());
+            },
+        }
+    }
+}
+
+pub fn spawn_quota_manager(limits: QuotaLimits) -> MeteredSender<QuotaMessage> {
+    let (tx, rx) = channel_with_metrics("quota", 32);
+    tokio::spawn(run_quota_manager(rx, limits));
+    tx
+}
+
+// Client-side handle for one client: every request is checked against the
+// quota actor before it is forwarded to the bank manager
+#[derive(Clone)]
+pub struct QuotaGate {
+    client: String,
+    quota: MeteredSender<QuotaMessage>,
+    bank: MeteredSender<BankMessage>,
+}
+
+impl QuotaGate {
+    pub fn new(client: &str, quota: &MeteredSender<QuotaMessage>, bank: &MeteredSender<BankMessage>) -> Self {
+        QuotaGate {
+            client: client.to_string(),
+            quota: quota.clone(),
+            bank: bank.clone(),
+        }
+    }
+
+    pub async fn deposit(&self, account: &str, amount: i32) -> Result<i32, BankError> {
+        self.acquire().await?;
+        client::deposit(&self.bank, account, amount).await
+    }
+
+    async fn acquire(&self) -> Result<(), BankError> {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        self.quota.send(QuotaMessage::Acquire {
+            client: self.client.clone(),
+            respond_to: resp_tx,
+        }).await.map_err(|_| BankError::ManagerUnavailable)?;
+        resp_rx.await.map_err(|_| BankError::ManagerUnavailable)?
+    }
+}
+
+// Operator calls. A quota manager that has stopped, or dropped the request,
+// is unavailable, the same as a stopped bank manager.
+pub async fn usage(quota: &MeteredSender<QuotaMessage>, client: &str) -> Result<QuotaUsage, BankError> {
+    let (resp_tx, resp_rx) = oneshot::channel();
+    quota.send(QuotaMessage::Usage { client: client.to_string(), respond_to: resp_tx })
+        .await
+        .map_err(|_| BankError::ManagerUnavailable)?;
+    resp_rx.await.map_err(|_| BankError::ManagerUnavailable)
+}
+
+pub async fn reset(quota: &MeteredSender<QuotaMessage>, client: &str) -> Result<(), BankError> {
+    let (resp_tx, resp_rx) = oneshot::channel();
+    quota.send(QuotaMessage::Reset { client: client.to_string(), respond_to: resp_tx })
+        .await
+        .map_err(|_| BankError::ManagerUnavailable)?;
+    resp_rx.await.map_err(|_| BankError::ManagerUnavailable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn a_stopped_quota_manager_is_unavailable() {
+        let (quota, rx) = channel_with_metrics("quota", 1);
+        drop(rx);
+        assert!(matches!(usage(&quota, "batch-job").await, Err(BankError::ManagerUnavailable)));
+        assert_eq!(reset(&quota, "batch-job").await, Err(BankError::ManagerUnavailable));
+    }
+}
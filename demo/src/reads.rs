@@ -0,0 +1,269 @@
+use arc_swap::ArcSwap;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{oneshot, watch};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout, Duration, Instant};
+
+use crate::bank::{BankError, BankEvent, BankMessage};
+use crate::chaos::{self, Subsystem};
+use crate::client;
+use crate::metrics::MeteredSender;
+
+// How fresh a balance read has to be
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadConsistency {
+    // Ask the manager, queueing behind every pending write
+    Strong,
+    // Serve from a local cache that events invalidate shortly after writes
+    Cached,
+    // Read the periodic snapshot if it is younger than the given age,
+    // otherwise fall back to a strong read
+    Stale(Duration),
+}
+
+// Copy of every open account's balance and when it was taken
+struct BalanceSnapshot {
+    balances: HashMap<String, i32>,
+    taken_at: Instant,
+}
+
+// Cached balance and when it was fetched from the manager
+type Cache = Arc<Mutex<HashMap<String, (i32, Instant)>>>;
+
+// How the reconciler samples and whether it repairs what it finds
+#[derive(Debug, Clone, Copy)]
+pub struct ReconcilePolicy {
+    pub every: Duration,
+    // Accounts checked per round, taking turns through all of them
+    pub sample: usize,
+    // Evict cached balances found wrong, so the next read asks the manager
+    pub heal: bool,
+}
+
+// The copy of a balance that disagreed with the one behind it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    // Cached balance, fetched `age` before the check, against the manager's
+    Cache { age: Duration },
+    // Manager's in-memory balance against the one replayed from its journal
+    Memory,
+}
+
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    pub account: String,
+    pub layer: Layer,
+    pub found: i32,
+    pub expected: i32,
+    // Latest event the manager had published when it was checked
+    pub seq: u64,
+    pub healed: bool,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.layer {
+            Layer::Cache { age } => write!(
+                f,
+                "{}: cached {} (fetched {:?} before) but the manager has {} as of event {}",
+                self.account, self.found, age, self.expected, self.seq
+            )?,
+            Layer::Memory => write!(
+                f,
+                "{}: the manager has {} but its journal adds up to {} as of event {}",
+                self.account, self.found, self.expected, self.seq
+            )?,
+        }
+        if self.healed {
+            write!(f, " - evicted")?;
+        }
+        Ok(())
+    }
+}
+
+// Client-side balance reads at a chosen consistency level. Keeps a cache
+// invalidated by the manager's events and a snapshot refreshed on a timer,
+// both by background tasks that stop with the manager.
+pub struct BalanceReader {
+    tx: MeteredSender<BankMessage>,
+    cache: Cache,
+    snapshot: Arc<ArcSwap<BalanceSnapshot>>,
+    // Latest event the invalidator has handled
+    invalidated_through: watch::Receiver<u64>,
+    divergences: Arc<Mutex<Vec<Divergence>>>,
+}
+
+impl BalanceReader {
+    pub async fn new(tx: &MeteredSender<BankMessage>, refresh_every: Duration) -> Result<Self, BankError> {
+        let cache: Cache = Arc::new(Mutex::new(HashMap::new()));
+        let snapshot = Arc::new(ArcSwap::from_pointee(BalanceSnapshot {
+            balances: client::balances(tx).await?,
+            taken_at: Instant::now(),
+        }));
+
+        // Drop cached balances as soon as the manager reports a change
+        let mut events = client::subscribe(tx, "*").await?;
+        let invalidated = Arc::clone(&cache);
+        let (handled_tx, invalidated_through) = watch::channel(0);
+        tokio::spawn(async move {
+            while let Some(envelope) = events.recv().await {
+                let mut cache = invalidated.lock().unwrap();
+                handled_tx.send_replace(envelope.seq);
+                if chaos::invalidations_lost() {
+                    continue;
+                }
+                match envelope.event {
+                    BankEvent::Deposited { account, .. } => {
+                        cache.remove(&account);
+                    },
+                    BankEvent::Transferred { from, to, fee, .. } => {
+                        cache.remove(&from);
+                        cache.remove(&to);
+                        if let Some((fees, _)) = fee {
+                            cache.remove(&fees);
+                        }
+                    },
+                    BankEvent::Exchanged { from, to, .. } => {
+                        cache.remove(&from);
+                        cache.remove(&to);
+                    },
+                    _ => {},
+                }
+            }
+        });
+
+        // Like the watchdog, the refresher only holds a weak sender
+        let weak_tx = tx.downgrade();
+        let refreshed = Arc::clone(&snapshot);
+        tokio::spawn(async move {
+            loop {
+                sleep(refresh_every).await;
+                let Some(tx) = weak_tx.upgrade() else { break };
+
+                let (resp_tx, resp_rx) = oneshot::channel();
+                if tx.send(BankMessage::Balances { respond_to: resp_tx }).await.is_err() {
+                    break;
+                }
+                drop(tx);
+                let Ok(balances) = resp_rx.await else { break };
+                refreshed.store(Arc::new(BalanceSnapshot { balances, taken_at: Instant::now() }));
+            }
+        });
+
+        Ok(BalanceReader {
+            tx: tx.clone(),
+            cache,
+            snapshot,
+            invalidated_through,
+            divergences: Arc::new(Mutex::new(vec![])),
+        })
+    }
+
+    pub async fn balance(&self, account: &str, consistency: ReadConsistency) -> Result<i32, BankError> {
+        match consistency {
+            ReadConsistency::Strong => self.strong(account).await,
+            ReadConsistency::Cached => {
+                chaos::inject(Subsystem::Cache).await;
+                let cached = if chaos::cache_misses_forced() {
+                    None
+                } else {
+                    self.cache.lock().unwrap().get(account).map(|&(balance, _)| balance)
+                };
+                match cached {
+                    Some(balance) => Ok(balance),
+                    None => {
+                        let balance = self.strong(account).await?;
+                        self.cache.lock().unwrap().insert(account.to_string(), (balance, Instant::now()));
+                        Ok(balance)
+                    }
+                }
+            },
+            ReadConsistency::Stale(max_age) => {
+                let snapshot = self.snapshot.load();
+                match snapshot.balances.get(account) {
+                    Some(&balance) if snapshot.taken_at.elapsed() <= max_age => Ok(balance),
+                    _ => self.strong(account).await,
+                }
+            },
+        }
+    }
+
+    async fn strong(&self, account: &str) -> Result<i32, BankError> {
+        client::balance(&self.tx, account).await
+    }
+
+    // Every divergence the reconciler has found, oldest first
+    pub fn divergences(&self) -> Vec<Divergence> {
+        self.divergences.lock().unwrap().clone()
+    }
+
+    // Background task comparing, for a sample of accounts each round, the
+    // cached balance with the manager's and the manager's with its journal.
+    // A cached balance only counts as wrong if it was fetched before the
+    // check and is still there once the invalidator has seen every event up
+    // to it; anything else is an invalidation still on its way. Like the
+    // refresher it only holds a weak sender.
+    pub fn spawn_reconciler(&self, policy: ReconcilePolicy) -> JoinHandle<()> {
+        let weak_tx = self.tx.downgrade();
+        let cache = Arc::clone(&self.cache);
+        let mut invalidated_through = self.invalidated_through.clone();
+        let divergences = Arc::clone(&self.divergences);
+        tokio::spawn(async move {
+            let mut turn = 0;
+            loop {
+                sleep(policy.every).await;
+                let Some(tx) = weak_tx.upgrade() else { break };
+
+                let (resp_tx, resp_rx) = oneshot::channel();
+                if tx.send(BankMessage::ListAccounts { respond_to: resp_tx }).await.is_err() {
+                    break;
+                }
+                let Ok(accounts) = resp_rx.await else { break };
+                for i in 0..policy.sample.min(accounts.len()) {
+                    let account = &accounts[(turn + i) % accounts.len()];
+                    let asked_at = Instant::now();
+                    let (resp_tx, resp_rx) = oneshot::channel();
+                    let message = BankMessage::AuditBalance { account: account.clone(), respond_to: resp_tx };
+                    if tx.send(message).await.is_err() {
+                        return;
+                    }
+                    // Closed or purged since the accounts were listed
+                    let Ok(Ok(audit)) = resp_rx.await else { continue };
+
+                    if audit.balance != audit.journal {
+                        divergences.lock().unwrap().push(Divergence {
+                            account: account.clone(),
+                            layer: Layer::Memory,
+                            found: audit.balance,
+                            expected: audit.journal,
+                            seq: audit.seq,
+                            healed: false,
+                        });
+                    }
+
+                    // Events can be dropped, so don't wait on one forever
+                    let _ = timeout(policy.every, invalidated_through.wait_for(|&seq| seq >= audit.seq)).await;
+                    let mut cache = cache.lock().unwrap();
+                    let Some(&(cached, fetched_at)) = cache.get(account) else { continue };
+                    if fetched_at > asked_at || cached == audit.balance {
+                        continue;
+                    }
+                    if policy.heal {
+                        cache.remove(account);
+                    }
+                    divergences.lock().unwrap().push(Divergence {
+                        account: account.clone(),
+                        layer: Layer::Cache { age: asked_at - fetched_at },
+                        found: cached,
+                        expected: audit.balance,
+                        seq: audit.seq,
+                        healed: policy.heal,
+                    });
+                }
+                turn += policy.sample;
+            }
+        })
+    }
+}
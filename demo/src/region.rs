@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time::{sleep, sleep_until, Duration, Instant};
+
+use crate::bank::{run_bank_manager, BankError, BankMessage};
+use crate::client;
+use crate::metrics::{channel_with_metrics, MeteredSender};
+
+// How a transfer to an account homed in another region completes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrossRegion {
+    // Answer once the local debit is done; the credit is replicated to the
+    // other region in the background, so for a while the money is in neither
+    Async,
+    // Wait for the other region to apply the credit and say so: a round trip
+    // over the link, after which reads anywhere see the transfer
+    Forward,
+}
+
+const SETTLEMENT_PREFIX: &str = "region:";
+
+// Each region holds an account per peer region that money sent there is
+// moved into, so its own books still balance while a credit is in flight
+fn settlement_account(region: &str) -> String {
+    format!("{}{}", SETTLEMENT_PREFIX, region)
+}
+
+// A credit on its way over a link, sent at `sent_at`
+struct Credit {
+    from: String,
+    to: String,
+    amount: i32,
+    sent_at: Instant,
+    // Told how the credit went, for forwarded transfers
+    ack: Option<oneshot::Sender<Result<(), BankError>>>,
+}
+
+struct Region {
+    name: String,
+    bank: MeteredSender<BankMessage>,
+    manager: JoinHandle<()>,
+}
+
+// One-way connection between two regions, in order and with a fixed latency
+struct Link {
+    from: usize,
+    to: usize,
+    latency: Duration,
+    tx: MeteredSender<Credit>,
+    task: JoinHandle<()>,
+}
+
+// Several regions, each with its own manager and accounts, connected by
+// links whose latency can differ by direction
+//
+//     let topology = Topology::builder().region("east", &[("Alice", 100)]).region("west", &[("Bob", 0)])
+//         .link("east", "west", east_west).link("west", "east", west_east).build(work_delay).await;
+pub struct Topology {
+    regions: Vec<Region>,
+    links: Vec<Link>,
+    homes: HashMap<String, usize>,
+}
+
+#[derive(Default)]
+pub struct TopologyBuilder {
+    regions: Vec<(String, HashMap<String, i32>)>,
+    links: Vec<(String, String, Duration)>,
+}
+
+impl TopologyBuilder {
+    pub fn region(mut self, name: &str, accounts: &[(&str, i32)]) -> Self {
+        let accounts = accounts.iter().map(|&(account, balance)| (account.to_string(), balance)).collect();
+        self.regions.push((name.to_string(), accounts));
+        self
+    }
+
+    pub fn link(mut self, from: &str, to: &str, latency: Duration) -> Self {
+        self.links.push((from.to_string(), to.to_string(), latency));
+        self
+    }
+
+    // Panics on links between unknown regions
+    pub async fn build(self, work_delay: Duration) -> Topology {
+        let position = |name: &str| self.regions.iter().position(|(region, _)| region == name).expect("unknown region");
+
+        let mut homes = HashMap::new();
+        let mut regions = vec![];
+        for (index, (name, accounts)) in self.regions.iter().enumerate() {
+            let mut books = accounts.clone();
+            for (peer, _) in self.regions.iter().filter(|(peer, _)| peer != name) {
+                books.insert(settlement_account(peer), 0);
+            }
+            homes.extend(accounts.keys().map(|account| (account.clone(), index)));
+
+            let (bank, rx) = channel_with_metrics("bank", 32);
+            let manager = tokio::spawn(run_bank_manager(rx, books, work_delay));
+            regions.push(Region { name: name.clone(), bank, manager });
+        }
+
+        let mut links = vec![];
+        for (from, to, latency) in &self.links {
+            let (from, to) = (position(from), position(to));
+            // Refunds for failed credits travel back over the reverse link
+            let back = self
+                .links
+                .iter()
+                .find(|(back_from, back_to, _)| position(back_from) == to && position(back_to) == from)
+                .map_or(*latency, |&(_, _, back)| back);
+            let (tx, rx) = channel_with_metrics("region-link", 1024);
+            let task = tokio::spawn(run_link(
+                rx,
+                *latency,
+                back,
+                regions[to].bank.clone(),
+                regions[from].bank.clone(),
+                settlement_account(&regions[to].name),
+            ));
+            links.push(Link { from, to, latency: *latency, tx, task });
+        }
+
+        Topology { regions, links, homes }
+    }
+}
+
+// Delivers credits in order, each no sooner than `latency` after it was
+// sent. A credit the other side refuses is refunded to the sender from the
+// settlement account once the refusal has come back over the link.
+async fn run_link(
+    mut credits: mpsc::Receiver<Credit>,
+    latency: Duration,
+    back: Duration,
+    remote: MeteredSender<BankMessage>,
+    local: MeteredSender<BankMessage>,
+    settlement: String,
+) {
+    let mut refunds = JoinSet::new();
+    while let Some(credit) = credits.recv().await {
+        sleep_until(credit.sent_at + latency).await;
+        let result = client::deposit(&remote, &credit.to, credit.amount).await.map(|_| ());
+        if result.is_err() {
+            let local = local.clone();
+            let settlement = settlement.clone();
+            let (from, amount) = (credit.from.clone(), credit.amount);
+            refunds.spawn(async move {
+                sleep(back).await;
+                let _ = client::transfer(&local, &settlement, &from, amount).await;
+            });
+        }
+        if let Some(ack) = credit.ack {
+            let _ = ack.send(result);
+        }
+    }
+    while refunds.join_next().await.is_some() {}
+}
+
+impl Topology {
+    pub fn builder() -> TopologyBuilder {
+        TopologyBuilder::default()
+    }
+
+    // Panics for unknown regions
+    pub fn bank(&self, region: &str) -> &MeteredSender<BankMessage> {
+        &self.regions[self.index(region)].bank
+    }
+
+    pub fn home(&self, account: &str) -> Option<&str> {
+        self.homes.get(account).map(|&index| self.regions[index].name.as_str())
+    }
+
+    fn index(&self, region: &str) -> usize {
+        self.regions.iter().position(|r| r.name == region).expect("unknown region")
+    }
+
+    fn link(&self, from: usize, to: usize) -> Result<&Link, BankError> {
+        self.links
+            .iter()
+            .find(|link| link.from == from && link.to == to)
+            .ok_or(BankError::Rejected("No link between the regions"))
+    }
+
+    // Move money between accounts in any regions; returns the payer's new
+    // balance. Only the payer's region is asked before answering, unless the
+    // transfer is forwarded.
+    pub async fn transfer(&self, from: &str, to: &str, amount: i32, mode: CrossRegion) -> Result<i32, BankError> {
+        let source = *self.homes.get(from).ok_or(BankError::Rejected("Account not found"))?;
+        let target = *self.homes.get(to).ok_or(BankError::Rejected("Account not found"))?;
+        let bank = &self.regions[source].bank;
+        if source == target {
+            return client::transfer(bank, from, to, amount).await;
+        }
+
+        let link = self.link(source, target)?;
+        let settlement = settlement_account(&self.regions[target].name);
+        let balance = client::transfer(bank, from, &settlement, amount).await?;
+
+        let (ack, acked) = match mode {
+            CrossRegion::Async => (None, None),
+            CrossRegion::Forward => {
+                let (ack, acked) = oneshot::channel();
+                (Some(ack), Some(acked))
+            },
+        };
+        let credit = Credit { from: from.to_string(), to: to.to_string(), amount, sent_at: Instant::now(), ack };
+        link.tx.send(credit).await.map_err(|_| BankError::Rejected("Link closed"))?;
+
+        if let Some(acked) = acked {
+            let result = acked.await.unwrap_or(Err(BankError::Rejected("Link closed")));
+            // The answer takes the reverse link home
+            sleep(self.link(target, source).map_or(link.latency, |back| back.latency)).await;
+            result?;
+        }
+        Ok(balance)
+    }
+
+    // Balance as seen from `region`: local accounts are read directly, others
+    // cost a round trip to their home region
+    pub async fn balance(&self, region: &str, account: &str) -> Result<i32, BankError> {
+        let here = self.index(region);
+        let home = *self.homes.get(account).ok_or(BankError::Rejected("Account not found"))?;
+        if here == home {
+            return client::balance(&self.regions[home].bank, account).await;
+        }
+        sleep(self.link(here, home)?.latency).await;
+        let balance = client::balance(&self.regions[home].bank, account).await;
+        sleep(self.link(home, here)?.latency).await;
+        balance
+    }
+
+    // Customer money across every region, leaving out settlement accounts.
+    // Falls while credits are in flight and comes back once they land.
+    pub async fn total(&self) -> Result<i32, BankError> {
+        let mut total = 0;
+        for region in &self.regions {
+            let balances = client::balances(&region.bank).await?;
+            total += balances
+                .iter()
+                .filter(|(account, _)| !account.starts_with(SETTLEMENT_PREFIX))
+                .map(|(_, balance)| balance)
+                .sum::<i32>();
+        }
+        Ok(total)
+    }
+
+    // Lets the links deliver what they hold, then stops every manager
+    pub async fn shutdown(self) {
+        let tasks: Vec<JoinHandle<()>> = self.links.into_iter().map(|link| link.task).collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        for region in self.regions {
+            drop(region.bank);
+            region.manager.await.unwrap();
+        }
+    }
+}
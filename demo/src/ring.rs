@@ -0,0 +1,278 @@
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+// Experimental bounded many-producer, single-consumer channel on a lock-free
+// ring buffer (Dmitry Vyukov's bounded queue). Producers claim a position
+// with a compare-and-swap on the tail and never take a lock; `Notify` is
+// only touched to wake a side that's asleep.
+
+struct Slot<T> {
+    // Whose turn the slot is: its position while free for the writer at
+    // that position, position + 1 once written, position + capacity once
+    // read and free for the next lap
+    turn: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+struct Ring<T> {
+    slots: Box<[Slot<T>]>,
+    mask: usize,
+    // Next position to read; only the receiver moves it
+    head: AtomicUsize,
+    // Next position to write; producers race for it
+    tail: AtomicUsize,
+    senders: AtomicUsize,
+    receiver_alive: AtomicBool,
+    not_empty: Notify,
+    not_full: Notify,
+}
+
+// Values are handed between threads through the slots, and each slot is
+// written or read by exactly one side at a time, as decided by its turn
+unsafe impl<T: Send> Send for Ring<T> {}
+unsafe impl<T: Send> Sync for Ring<T> {}
+
+impl<T> Ring<T> {
+    fn push(&self, value: T) -> Result<(), T> {
+        let mut position = self.tail.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[position & self.mask];
+            let lag = slot.turn.load(Ordering::Acquire).wrapping_sub(position) as isize;
+            if lag == 0 {
+                match self.tail.compare_exchange_weak(position, position + 1, Ordering::Relaxed, Ordering::Relaxed) {
+                    Ok(_) => {
+                        // The position is ours alone until the turn moves on
+                        unsafe { (*slot.value.get()).write(value) };
+                        slot.turn.store(position + 1, Ordering::Release);
+                        return Ok(());
+                    },
+                    Err(current) => position = current,
+                }
+            } else if lag < 0 {
+                // Last lap's value is still unread: full
+                return Err(value);
+            } else {
+                position = self.tail.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    // Only ever called by the single receiver
+    fn pop(&self) -> Option<T> {
+        let position = self.head.load(Ordering::Relaxed);
+        let slot = &self.slots[position & self.mask];
+        // Empty, or the producer that claimed the slot hasn't written it yet
+        if slot.turn.load(Ordering::Acquire) != position + 1 {
+            return None;
+        }
+        let value = unsafe { (*slot.value.get()).assume_init_read() };
+        slot.turn.store(position + self.slots.len(), Ordering::Release);
+        self.head.store(position + 1, Ordering::Relaxed);
+        Some(value)
+    }
+}
+
+impl<T> Drop for Ring<T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+// Capacity is rounded up to a power of two
+pub fn channel<T: Send>(capacity: usize) -> (RingSender<T>, RingReceiver<T>) {
+    let capacity = capacity.max(2).next_power_of_two();
+    let ring = Arc::new(Ring {
+        slots: (0..capacity)
+            .map(|position| Slot { turn: AtomicUsize::new(position), value: UnsafeCell::new(MaybeUninit::uninit()) })
+            .collect(),
+        mask: capacity - 1,
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+        senders: AtomicUsize::new(1),
+        receiver_alive: AtomicBool::new(true),
+        not_empty: Notify::new(),
+        not_full: Notify::new(),
+    });
+    (RingSender { ring: Arc::clone(&ring) }, RingReceiver { ring })
+}
+
+pub struct RingSender<T> {
+    ring: Arc<Ring<T>>,
+}
+
+impl<T> Clone for RingSender<T> {
+    fn clone(&self) -> Self {
+        self.ring.senders.fetch_add(1, Ordering::SeqCst);
+        RingSender { ring: Arc::clone(&self.ring) }
+    }
+}
+
+impl<T> Drop for RingSender<T> {
+    fn drop(&mut self) {
+        if self.ring.senders.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.ring.not_empty.notify_waiters();
+        }
+    }
+}
+
+impl<T> RingSender<T> {
+    // Waits for room when full; Err hands the message back once the
+    // receiver is gone
+    pub async fn send(&self, message: T) -> Result<(), T> {
+        let ring = &self.ring;
+        let mut message = message;
+        loop {
+            let room = ring.not_full.notified();
+            tokio::pin!(room);
+            room.as_mut().enable();
+
+            if !ring.receiver_alive.load(Ordering::SeqCst) {
+                return Err(message);
+            }
+            match ring.push(message) {
+                Ok(()) => {
+                    ring.not_empty.notify_one();
+                    return Ok(());
+                },
+                Err(returned) => message = returned,
+            }
+            room.await;
+        }
+    }
+}
+
+pub struct RingReceiver<T> {
+    ring: Arc<Ring<T>>,
+}
+
+impl<T> Drop for RingReceiver<T> {
+    fn drop(&mut self) {
+        self.ring.receiver_alive.store(false, Ordering::SeqCst);
+        self.ring.not_full.notify_waiters();
+    }
+}
+
+impl<T> RingReceiver<T> {
+    // None once the ring is empty and every sender has gone
+    pub async fn recv(&mut self) -> Option<T> {
+        let ring = &self.ring;
+        loop {
+            let arrival = ring.not_empty.notified();
+            tokio::pin!(arrival);
+            arrival.as_mut().enable();
+
+            if let Some(message) = ring.pop() {
+                ring.not_full.notify_one();
+                return Some(message);
+            }
+            if ring.senders.load(Ordering::SeqCst) == 0 {
+                // The last sender may have pushed just before leaving
+                return ring.pop();
+            }
+            arrival.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Counts its drops, to catch values leaked or dropped twice
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn each_producers_messages_arrive_in_order() {
+        let (tx, mut rx) = channel(8);
+        let producers: Vec<_> = (0..4)
+            .map(|producer| {
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    for i in 0..1000 {
+                        tx.send((producer, i)).await.unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+
+        let mut next = [0; 4];
+        while let Some((producer, i)) = rx.recv().await {
+            assert_eq!(i, next[producer], "producer {}", producer);
+            next[producer] += 1;
+        }
+        assert_eq!(next, [1000; 4]);
+        for producer in producers {
+            producer.await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn positions_wrap_around_for_many_laps() {
+        let (tx, mut rx) = channel(4);
+        for lap in 0..500 {
+            // Odd laps leave the ring part full, so positions drift against
+            // the slot boundaries
+            let batch = if lap % 2 == 0 { 4 } else { 3 };
+            for i in 0..batch {
+                tx.send(lap * 10 + i).await.unwrap();
+            }
+            for i in 0..batch {
+                assert_eq!(rx.recv().await, Some(lap * 10 + i));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn unread_values_are_dropped_with_the_ring() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let (tx, mut rx) = channel(4);
+        for _ in 0..3 {
+            tx.send(Counted(Arc::clone(&drops))).await.ok().unwrap();
+        }
+        drop(rx.recv().await);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        drop((tx, rx));
+        assert_eq!(drops.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn sending_without_a_receiver_hands_the_message_back() {
+        let (tx, rx) = channel(4);
+        drop(rx);
+        assert_eq!(tx.send(5).await, Err(5));
+    }
+
+    #[tokio::test]
+    async fn a_full_sender_is_released_when_the_receiver_goes() {
+        let (tx, rx) = channel(2);
+        tx.send(1).await.unwrap();
+        tx.send(2).await.unwrap();
+        let blocked = tokio::spawn(async move { tx.send(3).await });
+        tokio::task::yield_now().await;
+        drop(rx);
+        assert_eq!(blocked.await.unwrap(), Err(3));
+    }
+
+    #[tokio::test]
+    async fn recv_ends_once_every_sender_is_gone() {
+        let (tx, mut rx) = channel(4);
+        let other = tx.clone();
+        tx.send(1).await.unwrap();
+        other.send(2).await.unwrap();
+        drop(tx);
+        assert_eq!(rx.recv().await, Some(1));
+        drop(other);
+        assert_eq!(rx.recv().await, Some(2));
+        assert_eq!(rx.recv().await, None);
+    }
+}
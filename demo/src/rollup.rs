@@ -0,0 +1,151 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::bank::BankEvent;
+use crate::currency::Currency;
+use crate::projection::Projection;
+use crate::pubsub::Envelope;
+
+// Base-currency balances with each account's sub-accounts rolled into it,
+// kept from the event stream. A change to an account is added to it and to
+// each of its ancestors as it's applied, so reading a roll-up is a lookup
+// and the manager never walks the tree.
+pub struct RollupView {
+    parents: HashMap<String, String>,
+    // Each account's own balance, and with everything below it added
+    own: BTreeMap<String, i32>,
+    rolled_up: HashMap<String, i32>,
+}
+
+impl RollupView {
+    // Starts from the balances as of the subscription, with no sub-accounts
+    pub fn new(balances: impl IntoIterator<Item = (String, i32)>) -> Self {
+        let own: BTreeMap<String, i32> = balances.into_iter().collect();
+        let rolled_up = own.iter().map(|(account, balance)| (account.clone(), *balance)).collect();
+        RollupView { parents: HashMap::new(), own, rolled_up }
+    }
+
+    pub fn own(&self, account: &str) -> Option<i32> {
+        self.own.get(account).copied()
+    }
+
+    pub fn rolled_up(&self, account: &str) -> Option<i32> {
+        self.rolled_up.get(account).copied()
+    }
+
+    // Direct sub-accounts, sorted by name
+    pub fn children(&self, account: &str) -> Vec<&str> {
+        let mut children: Vec<&str> = self
+            .parents
+            .iter()
+            .filter(|(_, parent)| parent.as_str() == account)
+            .map(|(child, _)| child.as_str())
+            .collect();
+        children.sort();
+        children
+    }
+
+    // Accounts without a parent, sorted by name
+    pub fn roots(&self) -> Vec<&str> {
+        self.own.keys().filter(|account| !self.parents.contains_key(*account)).map(String::as_str).collect()
+    }
+
+    fn add_up(&mut self, account: &str, amount: i32) {
+        let mut current = Some(account.to_string());
+        while let Some(account) = current {
+            *self.rolled_up.entry(account.clone()).or_insert(0) += amount;
+            current = self.parents.get(&account).cloned();
+        }
+    }
+
+    fn change(&mut self, account: &str, amount: i32) {
+        *self.own.entry(account.to_string()).or_insert(0) += amount;
+        self.add_up(account, amount);
+    }
+}
+
+impl Projection<BankEvent> for RollupView {
+    fn apply(&mut self, envelope: &Envelope<BankEvent>) -> Result<(), String> {
+        match &envelope.event {
+            // Deposits carry the new balance, which also catches the view up
+            // on accounts opened after it started
+            BankEvent::Deposited { account, currency, balance, .. } if *currency == Currency::BASE => {
+                let change = balance - self.own(account).unwrap_or(0);
+                self.change(account, change);
+            },
+            // The payer is charged the fee on top, and the fees account
+            // collects it
+            BankEvent::Transferred { from, to, currency, amount, fee, .. } if *currency == Currency::BASE => {
+                self.change(from, -amount);
+                self.change(to, *amount);
+                if let Some((fees, fee)) = fee {
+                    self.change(from, -fee);
+                    self.change(fees, *fee);
+                }
+            },
+            BankEvent::Exchanged { from, to, currency, amount, to_currency, credited, .. } => {
+                if *currency == Currency::BASE {
+                    self.change(from, -amount);
+                }
+                if *to_currency == Currency::BASE {
+                    self.change(to, *credited);
+                }
+            },
+            // The sub-account's whole subtree moves from its old ancestors
+            // to its new ones
+            BankEvent::AccountLinked { account, parent } => {
+                let subtree = self.rolled_up(account).unwrap_or(0);
+                if let Some(old_parent) = self.parents.remove(account) {
+                    self.add_up(&old_parent, -subtree);
+                }
+                self.add_up(parent, subtree);
+                self.parents.insert(account.clone(), parent.clone());
+            },
+            // Purged accounts are empty; their children become top-level
+            BankEvent::AccountPurged { account } => {
+                for child in self.children(account).into_iter().map(str::to_string).collect::<Vec<_>>() {
+                    let subtree = self.rolled_up(&child).unwrap_or(0);
+                    self.add_up(account, -subtree);
+                    self.parents.remove(&child);
+                }
+                self.parents.remove(account);
+                self.own.remove(account);
+                self.rolled_up.remove(account);
+            },
+            _ => {},
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids;
+
+    fn envelope(seq: u64, event: BankEvent) -> Envelope<BankEvent> {
+        Envelope { id: ids::next_id(), seq, topic: "transfers.alice.bob".to_string(), event }
+    }
+
+    #[test]
+    fn charged_transfers_move_the_fee_to_the_fees_account() {
+        let balances = [("Alice", 100), ("Bob", 0), ("Fees", 0), ("Household", 0)];
+        let mut view = RollupView::new(balances.map(|(account, balance)| (account.to_string(), balance)));
+        let link = BankEvent::AccountLinked { account: "Alice".to_string(), parent: "Household".to_string() };
+        view.apply(&envelope(1, link)).unwrap();
+
+        let transfer = BankEvent::Transferred {
+            transaction: ids::next_id(),
+            from: "Alice".to_string(),
+            to: "Bob".to_string(),
+            currency: Currency::BASE,
+            amount: 40,
+            fee: Some(("Fees".to_string(), 2)),
+        };
+        view.apply(&envelope(2, transfer)).unwrap();
+
+        assert_eq!(view.own("Alice"), Some(58));
+        assert_eq!(view.rolled_up("Household"), Some(58));
+        assert_eq!(view.own("Bob"), Some(40));
+        assert_eq!(view.own("Fees"), Some(2));
+    }
+}
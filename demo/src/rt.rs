@@ -0,0 +1,186 @@
+// Thin runtime layer: just enough spawn, sleep and channel to run an actor.
+// Tokio is the default; build with `--features async-std` or `--features smol`
+// to run the portable scenarios on another executor.
+//
+// Only the ledger and the `portable-actor` scenario's actor are written
+// against it. The bank manager, its clients and the services around it use
+// tokio's channels, timers and select! directly, and need tokio whichever
+// feature is on.
+//
+// Everything here has an equivalent on each runtime. What doesn't - select!,
+// watch, Notify, JoinSet, spawn_blocking, current_thread runtimes - stays
+// tokio-specific and is used directly by the other scenarios.
+
+#[cfg(all(feature = "async-std", feature = "smol"))]
+compile_error!("features `async-std` and `smol` are mutually exclusive");
+
+#[cfg(not(any(feature = "async-std", feature = "smol")))]
+pub use self::tokio_rt::*;
+#[cfg(feature = "async-std")]
+pub use self::async_std_rt::*;
+#[cfg(feature = "smol")]
+pub use self::smol_rt::*;
+
+#[cfg(any(feature = "async-std", feature = "smol"))]
+pub use self::async_channel_rt::*;
+
+#[cfg(not(any(feature = "async-std", feature = "smol")))]
+mod tokio_rt {
+    use std::future::Future;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    use crate::metrics::{channel_with_metrics, MeteredSender};
+
+    pub const NAME: &str = "tokio";
+
+    pub struct JoinHandle<T>(tokio::task::JoinHandle<T>);
+
+    impl<T> JoinHandle<T> {
+        // Wait for the task; a panic in the task is propagated
+        pub async fn join(self) -> T {
+            self.0.await.expect("task panicked")
+        }
+    }
+
+    pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        JoinHandle(tokio::spawn(future))
+    }
+
+    pub async fn sleep(duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+
+    pub struct Sender<T>(MeteredSender<T>);
+
+    pub struct Receiver<T>(mpsc::Receiver<T>);
+
+    impl<T> Clone for Sender<T> {
+        fn clone(&self) -> Self {
+            Sender(self.0.clone())
+        }
+    }
+
+    impl<T> Sender<T> {
+        // Gives the value back if the receiver is gone
+        pub async fn send(&self, value: T) -> Result<(), T> {
+            self.0.send(value).await.map_err(|e| e.0)
+        }
+    }
+
+    impl<T> Receiver<T> {
+        // `None` once every sender is dropped and the buffer is empty
+        pub async fn recv(&mut self) -> Option<T> {
+            self.0.recv().await
+        }
+    }
+
+    pub fn channel<T>(buffer: usize) -> (Sender<T>, Receiver<T>) {
+        let (tx, rx) = channel_with_metrics("rt", buffer);
+        (Sender(tx), Receiver(rx))
+    }
+}
+
+#[cfg(feature = "async-std")]
+mod async_std_rt {
+    use std::future::Future;
+    use std::time::Duration;
+
+    pub const NAME: &str = "async-std";
+
+    pub struct JoinHandle<T>(async_std::task::JoinHandle<T>);
+
+    impl<T> JoinHandle<T> {
+        pub async fn join(self) -> T {
+            self.0.await
+        }
+    }
+
+    pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        JoinHandle(async_std::task::spawn(future))
+    }
+
+    pub async fn sleep(duration: Duration) {
+        async_std::task::sleep(duration).await
+    }
+}
+
+#[cfg(feature = "smol")]
+mod smol_rt {
+    use std::future::Future;
+    use std::time::Duration;
+
+    pub const NAME: &str = "smol";
+
+    pub struct JoinHandle<T>(smol::Task<T>);
+
+    impl<T> JoinHandle<T> {
+        pub async fn join(self) -> T {
+            self.0.await
+        }
+    }
+
+    pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        JoinHandle(smol::spawn(future))
+    }
+
+    pub async fn sleep(duration: Duration) {
+        smol::Timer::after(duration).await;
+    }
+}
+
+// Neither async-std nor smol has its own channels; both use async-channel.
+// Sends are counted under "rt" in the channel report, as on tokio.
+#[cfg(any(feature = "async-std", feature = "smol"))]
+mod async_channel_rt {
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    use crate::metrics::ChannelStats;
+
+    pub struct Sender<T> {
+        inner: async_channel::Sender<T>,
+        stats: Arc<ChannelStats>,
+    }
+
+    pub struct Receiver<T>(async_channel::Receiver<T>);
+
+    impl<T> Clone for Sender<T> {
+        fn clone(&self) -> Self {
+            Sender { inner: self.inner.clone(), stats: Arc::clone(&self.stats) }
+        }
+    }
+
+    impl<T> Sender<T> {
+        pub async fn send(&self, value: T) -> Result<(), T> {
+            let started = Instant::now();
+            let result = self.inner.send(value).await.map_err(|e| e.0);
+            self.stats.record_wait(started.elapsed());
+            self.stats.record(result.is_ok(), self.inner.len());
+            result
+        }
+    }
+
+    impl<T> Receiver<T> {
+        pub async fn recv(&mut self) -> Option<T> {
+            self.0.recv().await.ok()
+        }
+    }
+
+    pub fn channel<T>(buffer: usize) -> (Sender<T>, Receiver<T>) {
+        let (tx, rx) = async_channel::bounded(buffer);
+        (Sender { inner: tx, stats: ChannelStats::register("rt", buffer) }, Receiver(rx))
+    }
+}
@@ -0,0 +1,74 @@
+use std::hint::black_box;
+use tokio::time::{sleep, Duration, Instant};
+
+use crate::config::Config;
+use crate::scenario::{scenario, Scenario};
+
+// Spin the CPU for a while without ever reaching an await point
+fn busy_work(seed: u64, duration: Duration) -> u64 {
+    let until = Instant::now() + duration;
+    let mut checksum = seed;
+    while Instant::now() < until {
+        checksum = black_box(checksum.wrapping_mul(31).wrapping_add(7));
+    }
+    checksum
+}
+
+// Run a heartbeat next to a CPU-bound task on a single-threaded runtime and
+// return the longest gap between heartbeats
+fn heartbeat_next_to_hot_loop(cooperative: bool) -> Duration {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+
+    rt.block_on(async move {
+        let heartbeat = tokio::spawn(async {
+            let mut max_gap = Duration::ZERO;
+            let mut last = Instant::now();
+            for _ in 0..20 {
+                sleep(Duration::from_millis(10)).await;
+                let now = Instant::now();
+                max_gap = max_gap.max(now - last);
+                last = now;
+            }
+            max_gap
+        });
+
+        let hot_loop = tokio::spawn(async move {
+            let mut checksum = 0;
+            for _ in 0..50 {
+                checksum = busy_work(checksum, Duration::from_millis(5));
+                // Without this the heartbeat can't run until the loop is done
+                if cooperative {
+                    tokio::task::yield_now().await;
+                }
+            }
+            checksum
+        });
+
+        hot_loop.await.unwrap();
+        heartbeat.await.unwrap()
+    })
+}
+
+async fn run_starvation_example(_cfg: Config) {
+    println!("\n=== Starvation Example (current_thread Runtime) ===");
+    println!("Heartbeat every 10ms next to a 250ms CPU-bound loop");
+
+    for cooperative in [false, true] {
+        // Build the single-threaded runtime off the main runtime's workers
+        let max_gap = tokio::task::spawn_blocking(move || heartbeat_next_to_hot_loop(cooperative))
+            .await
+            .unwrap();
+
+        let label = if cooperative { "with yield_now" } else { "hot loop" };
+        println!("{:<15} - longest gap between heartbeats: {:?}", label, max_gap);
+    }
+}
+
+pub fn scenarios() -> Vec<Box<dyn Scenario>> {
+    vec![
+        scenario("starvation", "A hot loop starving a heartbeat until it yields", run_starvation_example),
+    ]
+}
@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use tokio::time::Duration;
+
+use crate::config::Config;
+use crate::{
+    async_demo, bank_demo, events_demo, notify_demo, portable_demo, runtime_demo, shared_state_demo, spawn_demo,
+};
+
+pub type ScenarioFuture<'a> = Pin<Box<dyn Future<Output = Outcome> + 'a>>;
+
+// What a scenario observed, checked against its postconditions.
+// Print-only scenarios return `()`, which becomes an empty outcome.
+#[derive(Debug, Default)]
+pub struct Outcome {
+    pub balances: BTreeMap<String, i32>,
+    pub operations: u64,
+    pub max_latency: Duration,
+    // Broken invariants the manager reported at the end
+    pub violations: usize,
+}
+
+impl Outcome {
+    // Count one completed operation and how long it took
+    pub fn record(&mut self, latency: Duration) {
+        self.operations += 1;
+        self.max_latency = self.max_latency.max(latency);
+    }
+
+    pub fn with_balance(mut self, account: &str, balance: i32) -> Self {
+        self.balances.insert(account.to_string(), balance);
+        self
+    }
+
+    pub fn with_balances(mut self, balances: impl IntoIterator<Item = (String, i32)>) -> Self {
+        self.balances.extend(balances);
+        self
+    }
+
+    pub fn with_violations(mut self, violations: usize) -> Self {
+        self.violations = violations;
+        self
+    }
+}
+
+impl From<()> for Outcome {
+    fn from(_: ()) -> Self {
+        Outcome::default()
+    }
+}
+
+// Something that must hold once a scenario has finished
+#[derive(Debug, Clone)]
+pub enum Postcondition {
+    FinalBalance { account: &'static str, expected: i32 },
+    Operations(u64),
+    MaxLatency(Duration),
+    BooksBalanced,
+}
+
+impl Postcondition {
+    // A `FinalBalance` for each account, and books that balance
+    pub fn books(expected: &[(&'static str, i32)]) -> Vec<Postcondition> {
+        expected
+            .iter()
+            .map(|&(account, expected)| Postcondition::FinalBalance { account, expected })
+            .chain([Postcondition::BooksBalanced])
+            .collect()
+    }
+
+    pub fn check(&self, outcome: &Outcome) -> Result<(), String> {
+        match self {
+            Postcondition::FinalBalance { account, expected } => match outcome.balances.get(*account) {
+                Some(balance) if balance == expected => Ok(()),
+                Some(balance) => Err(format!("{} ended with {} instead of {}", account, balance, expected)),
+                None => Err(format!("no final balance reported for {}", account)),
+            },
+            Postcondition::Operations(expected) if outcome.operations != *expected => {
+                Err(format!("{} operations completed instead of {}", outcome.operations, expected))
+            },
+            Postcondition::MaxLatency(bound) if outcome.max_latency > *bound => {
+                Err(format!("slowest operation took {:?}, over the {:?} bound", outcome.max_latency, bound))
+            },
+            Postcondition::BooksBalanced if outcome.violations > 0 => {
+                Err(format!("{} invariant violation(s) in the books", outcome.violations))
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+type Postconditions = Box<dyn Fn(&Config) -> Vec<Postcondition>>;
+
+// A runnable demo, listed by `demo list` and started by `demo run <name>`
+pub trait Scenario {
+    fn name(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    fn run<'a>(&'a self, cfg: &'a Config) -> ScenarioFuture<'a>;
+
+    // Expectations for a run with the given config; none by default
+    fn postconditions(&self, _cfg: &Config) -> Vec<Postcondition> {
+        vec![]
+    }
+}
+
+// Scenario backed by a plain async function taking the config
+pub struct FnScenario<F> {
+    name: &'static str,
+    description: &'static str,
+    run: F,
+    postconditions: Option<Postconditions>,
+}
+
+impl<F, Fut> Scenario for FnScenario<F>
+where
+    F: Fn(Config) -> Fut,
+    Fut: Future + 'static,
+    Fut::Output: Into<Outcome>,
+{
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn run<'a>(&'a self, cfg: &'a Config) -> ScenarioFuture<'a> {
+        let run = (self.run)(cfg.clone());
+        Box::pin(async move {
+            let outcome: Outcome = run.await.into();
+            outcome
+        })
+    }
+
+    fn postconditions(&self, cfg: &Config) -> Vec<Postcondition> {
+        self.postconditions.as_ref().map_or_else(Vec::new, |postconditions| postconditions(cfg))
+    }
+}
+
+pub fn scenario<F, Fut>(name: &'static str, description: &'static str, run: F) -> Box<dyn Scenario>
+where
+    F: Fn(Config) -> Fut + 'static,
+    Fut: Future + 'static,
+    Fut::Output: Into<Outcome>,
+{
+    Box::new(FnScenario { name, description, run, postconditions: None })
+}
+
+// Like `scenario`, for scenarios that verify their own result
+pub fn checked_scenario<F, Fut, P>(
+    name: &'static str,
+    description: &'static str,
+    run: F,
+    postconditions: P,
+) -> Box<dyn Scenario>
+where
+    F: Fn(Config) -> Fut + 'static,
+    Fut: Future + 'static,
+    Fut::Output: Into<Outcome>,
+    P: Fn(&Config) -> Vec<Postcondition> + 'static,
+{
+    Box::new(FnScenario { name, description, run, postconditions: Some(Box::new(postconditions)) })
+}
+
+// Run a scenario and check its postconditions, returning the failures
+pub async fn run_checked(scenario: &dyn Scenario, cfg: &Config) -> Vec<String> {
+    let outcome = scenario.run(cfg).await;
+    scenario
+        .postconditions(cfg)
+        .iter()
+        .filter_map(|postcondition| postcondition.check(&outcome).err())
+        .collect()
+}
+
+// Every scenario in the order `demo run all` executes them
+pub fn registry() -> Vec<Box<dyn Scenario>> {
+    let mut scenarios = vec![];
+    scenarios.extend(async_demo::scenarios());
+    scenarios.extend(spawn_demo::scenarios());
+    scenarios.extend(shared_state_demo::scenarios());
+    scenarios.extend(bank_demo::scenarios());
+    scenarios.extend(events_demo::scenarios());
+    scenarios.extend(notify_demo::scenarios());
+    scenarios.extend(runtime_demo::scenarios());
+    scenarios.extend(portable_demo::scenarios());
+    scenarios
+}
+
+pub fn find(name: &str) -> Option<Box<dyn Scenario>> {
+    registry().into_iter().find(|scenario| scenario.name() == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn scenarios_meet_their_postconditions() {
+        let cfg = Config {
+            work_delay: Duration::from_millis(20),
+            ..Config::default()
+        };
+
+        for scenario in registry() {
+            if scenario.postconditions(&cfg).is_empty() {
+                continue;
+            }
+            let failures = run_checked(scenario.as_ref(), &cfg).await;
+            assert!(failures.is_empty(), "{}: {}", scenario.name(), failures.join(", "));
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant};
+
+use crate::bank::BankMessage;
+
+// Order in which the manager takes requests from its mailbox
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchedulePolicy {
+    Fifo,
+    // Experimental: pull up to `lookahead` requests off the mailbox and run
+    // the cheapest first by `BankMessage::estimated_cost`. A request held
+    // back for `max_wait` goes next whatever its cost, so long jobs can't
+    // starve.
+    ShortestFirst { lookahead: usize, max_wait: Duration },
+}
+
+// Counted across every scheduling manager since the last `take_stats`
+static PICKED: AtomicU64 = AtomicU64::new(0);
+static REORDERED: AtomicU64 = AtomicU64::new(0);
+static PROMOTED: AtomicU64 = AtomicU64::new(0);
+static MAX_HELD_NANOS: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScheduleStats {
+    pub picked: u64,
+    // Requests run ahead of one that arrived before them
+    pub reordered: u64,
+    // Requests run next because they'd waited `max_wait`
+    pub promoted: u64,
+    // Longest any request sat in the lookahead
+    pub max_held: Duration,
+}
+
+pub fn take_stats() -> ScheduleStats {
+    ScheduleStats {
+        picked: PICKED.swap(0, Ordering::Relaxed),
+        reordered: REORDERED.swap(0, Ordering::Relaxed),
+        promoted: PROMOTED.swap(0, Ordering::Relaxed),
+        max_held: Duration::from_nanos(MAX_HELD_NANOS.swap(0, Ordering::Relaxed)),
+    }
+}
+
+struct Held {
+    message: BankMessage,
+    cost: u32,
+    // When it came off the mailbox, which also gives arrival order
+    taken_at: Instant,
+}
+
+pub struct Scheduler {
+    policy: SchedulePolicy,
+    held: Vec<Held>,
+}
+
+impl Scheduler {
+    pub fn new(policy: SchedulePolicy) -> Self {
+        Scheduler { policy, held: vec![] }
+    }
+
+    // The next request to run; None once the mailbox is closed and nothing
+    // is held. Cancel-safe: a request only leaves the mailbox to be held.
+    pub async fn next(&mut self, rx: &mut mpsc::Receiver<BankMessage>) -> Option<BankMessage> {
+        let SchedulePolicy::ShortestFirst { lookahead, max_wait } = self.policy else {
+            return rx.recv().await;
+        };
+
+        if self.held.is_empty() {
+            let message = rx.recv().await?;
+            self.hold(message);
+        }
+        // Only as much as the lookahead, so senders still feel backpressure
+        while self.held.len() < lookahead.max(1) {
+            match rx.try_recv() {
+                Ok(message) => self.hold(message),
+                Err(_) => break,
+            }
+        }
+
+        let now = Instant::now();
+        let starved = self.held.iter().position(|held| now - held.taken_at >= max_wait);
+        let index = match starved {
+            // The oldest, since they're held in arrival order
+            Some(index) => {
+                PROMOTED.fetch_add(1, Ordering::Relaxed);
+                index
+            },
+            None => (0..self.held.len()).min_by_key(|&index| self.held[index].cost).expect("held isn't empty"),
+        };
+
+        let held = self.held.remove(index);
+        PICKED.fetch_add(1, Ordering::Relaxed);
+        if index > 0 {
+            REORDERED.fetch_add(1, Ordering::Relaxed);
+        }
+        MAX_HELD_NANOS.fetch_max((now - held.taken_at).as_nanos() as u64, Ordering::Relaxed);
+        Some(held.message)
+    }
+
+    fn hold(&mut self, message: BankMessage) {
+        let cost = message.estimated_cost();
+        self.held.push(Held { message, cost, taken_at: Instant::now() });
+    }
+}
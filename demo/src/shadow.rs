@@ -0,0 +1,157 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio::time::{Duration, Instant};
+
+use crate::differential::{BoxFuture, Implementation};
+
+// A request whose shadow came back different from what the primary answered
+#[derive(Debug, Clone)]
+pub struct Mismatch {
+    pub account: String,
+    pub amount: i32,
+    pub primary: Result<(), String>,
+    pub candidate: Result<(), String>,
+}
+
+#[derive(Debug, Default)]
+struct Recorded {
+    mirrored: u64,
+    // Not mirrored because `max_in_flight` shadows were already running
+    skipped: u64,
+    mismatches: Vec<Mismatch>,
+    primary_time: Duration,
+    candidate_time: Duration,
+}
+
+pub struct ShadowReport {
+    pub primary: &'static str,
+    pub candidate: &'static str,
+    pub mirrored: u64,
+    pub skipped: u64,
+    pub mismatches: Vec<Mismatch>,
+    // Mean time per mirrored request on each side
+    pub primary_mean: Duration,
+    pub candidate_mean: Duration,
+    // Accounts whose balances ended up different, with each side's
+    pub balance_diffs: Vec<(String, Option<i32>, Option<i32>)>,
+}
+
+// Serves every request from `primary` and mirrors it to `candidate` in the
+// background. The candidate's answers are only compared and recorded, never
+// returned, so a candidate that is wrong or slow can't hurt callers while a
+// migration to it is being tried out. Accepted or refused is what's
+// compared; the balances returned depend on how requests interleaved.
+pub struct Shadow {
+    primary: Arc<dyn Implementation>,
+    candidate: Arc<dyn Implementation>,
+    // Shadows running at once, so a slow candidate can't pile up work
+    in_flight: Arc<Semaphore>,
+    shadows: Mutex<JoinSet<()>>,
+    recorded: Arc<Mutex<Recorded>>,
+}
+
+impl Shadow {
+    pub fn new(primary: Arc<dyn Implementation>, candidate: Arc<dyn Implementation>, max_in_flight: usize) -> Self {
+        Shadow {
+            primary,
+            candidate,
+            in_flight: Arc::new(Semaphore::new(max_in_flight)),
+            shadows: Mutex::new(JoinSet::new()),
+            recorded: Arc::new(Mutex::new(Recorded::default())),
+        }
+    }
+
+    // Wait for the shadows still running, then compare final balances
+    pub async fn finish(&self) -> ShadowReport {
+        let mut shadows = std::mem::take(&mut *self.shadows.lock().unwrap());
+        while shadows.join_next().await.is_some() {}
+
+        let primary = self.primary.balances().await;
+        let candidate = self.candidate.balances().await;
+        let accounts: BTreeSet<&String> = primary.keys().chain(candidate.keys()).collect();
+        let balance_diffs = accounts
+            .into_iter()
+            .map(|account| (account.clone(), primary.get(account).copied(), candidate.get(account).copied()))
+            .filter(|(_, primary, candidate)| primary != candidate)
+            .collect();
+
+        let recorded = self.recorded.lock().unwrap();
+        let mean = |total: Duration| total.checked_div(recorded.mirrored as u32).unwrap_or_default();
+        ShadowReport {
+            primary: self.primary.name(),
+            candidate: self.candidate.name(),
+            mirrored: recorded.mirrored,
+            skipped: recorded.skipped,
+            mismatches: recorded.mismatches.clone(),
+            primary_mean: mean(recorded.primary_time),
+            candidate_mean: mean(recorded.candidate_time),
+            balance_diffs,
+        }
+    }
+}
+
+impl Implementation for Shadow {
+    fn name(&self) -> &'static str {
+        self.primary.name()
+    }
+
+    fn deposit<'a>(&'a self, account: &'a str, amount: i32) -> BoxFuture<'a, Result<i32, String>> {
+        Box::pin(async move {
+            let started = Instant::now();
+            let result = self.primary.deposit(account, amount).await;
+            let primary_time = started.elapsed();
+
+            let Ok(permit) = Arc::clone(&self.in_flight).try_acquire_owned() else {
+                self.recorded.lock().unwrap().skipped += 1;
+                return result;
+            };
+            let (candidate, recorded) = (Arc::clone(&self.candidate), Arc::clone(&self.recorded));
+            let (account, primary) = (account.to_string(), result.clone().map(|_| ()));
+            self.shadows.lock().unwrap().spawn(async move {
+                let started = Instant::now();
+                let shadowed = candidate.deposit(&account, amount).await.map(|_| ());
+                let candidate_time = started.elapsed();
+                drop(permit);
+
+                let mut recorded = recorded.lock().unwrap();
+                recorded.mirrored += 1;
+                recorded.primary_time += primary_time;
+                recorded.candidate_time += candidate_time;
+                if shadowed != primary {
+                    recorded.mismatches.push(Mismatch { account, amount, primary, candidate: shadowed });
+                }
+            });
+            result
+        })
+    }
+
+    fn balances(&self) -> BoxFuture<'_, BTreeMap<String, i32>> {
+        self.primary.balances()
+    }
+}
+
+impl ShadowReport {
+    pub fn print(&self) {
+        println!(
+            "Mirrored {} requests to {} ({} skipped), mean {:?} on {} vs {:?}",
+            self.mirrored, self.candidate, self.skipped, self.primary_mean, self.primary, self.candidate_mean
+        );
+        if self.mismatches.is_empty() {
+            println!("Responses: no differences");
+        }
+        for mismatch in &self.mismatches {
+            println!(
+                "Deposit {} to {}: {} answered {:?}, {} {:?}",
+                mismatch.amount, mismatch.account, self.primary, mismatch.primary, self.candidate, mismatch.candidate
+            );
+        }
+        if self.balance_diffs.is_empty() {
+            println!("Final balances: identical");
+        }
+        for (account, primary, candidate) in &self.balance_diffs {
+            println!("Final balance of {}: {:?} vs {:?}", account, primary, candidate);
+        }
+    }
+}
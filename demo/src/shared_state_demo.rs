@@ -0,0 +1,1171 @@
+use arc_swap::ArcSwap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+use tokio::task::JoinSet;
+use tokio::time::{sleep, Duration, Instant};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::bank::{run_bank_manager, BankMessage, Progress, TaggedReply};
+use crate::calibration;
+use crate::chaos::{self, Subsystem};
+use crate::client;
+use crate::config::Config;
+use crate::contention;
+use crate::context::AppContext;
+use crate::deadline::{self, Deadline};
+use crate::differential::{self, BoxFuture, Implementation};
+use crate::ids;
+use crate::ledger::EntryKind;
+use crate::mailbox::{self, OverflowPolicy};
+use crate::metrics::{channel_with_metrics, MeteredSender};
+use crate::query::Query;
+use crate::reads::ReadConsistency;
+use crate::ring;
+use crate::scenario::{checked_scenario, scenario, Outcome, Postcondition, Scenario};
+use crate::shadow::Shadow;
+use crate::slowlog;
+use crate::tasks::TaskGroup;
+
+// Helper function to print timing info
+async fn log_operation(start: Instant, operation: &str, details: &str) {
+    let elapsed = start.elapsed().as_millis();
+    println!("[{:>4}ms] {} - {}", elapsed, operation, details);
+}
+
+// Example 1: Basic Mutex - Quick operations
+struct BasicBank {
+    accounts: Mutex<HashMap<String, i32>>,
+}
+
+impl BasicBank {
+    fn new() -> Self {
+        let mut accounts = HashMap::new();
+        accounts.insert("Alice".to_string(), 100);
+        BasicBank::from_accounts(accounts)
+    }
+
+    fn from_accounts(accounts: HashMap<String, i32>) -> Self {
+        BasicBank {
+            accounts: Mutex::new(accounts)
+        }
+    }
+
+    fn deposit(&self, account: &str, amount: i32) -> Result<i32, &'static str> {
+        // Basic mutex locks immediately block other threads
+        let mut accounts = self.accounts.lock().unwrap();
+        if let Some(balance) = accounts.get_mut(account) {
+            *balance += amount;
+            Ok(*balance)
+        } else {
+            Err("Account not found")
+        }
+    }
+}
+
+// Example 2: Async Mutex - Complex operations
+struct AsyncBank {
+    accounts: tokio::sync::Mutex<HashMap<String, i32>>,
+    work_delay: Duration,
+}
+
+impl AsyncBank {
+    fn new(work_delay: Duration) -> Self {
+        let mut accounts = HashMap::new();
+        accounts.insert("Alice".to_string(), 100);
+        AsyncBank::from_accounts(accounts, work_delay)
+    }
+
+    fn from_accounts(accounts: HashMap<String, i32>, work_delay: Duration) -> Self {
+        AsyncBank {
+            accounts: tokio::sync::Mutex::new(accounts),
+            work_delay,
+        }
+    }
+
+    async fn process_deposit(&self, account: &str, amount: i32) -> Result<i32, &'static str> {
+        // One lock guards every account, so any two deposits conflict
+        let waiting = Instant::now();
+        let (mut accounts, conflicted) = match self.accounts.try_lock() {
+            Ok(accounts) => (accounts, false),
+            Err(_) => (self.accounts.lock().await, true),
+        };
+        contention::record(account, waiting.elapsed(), conflicted);
+
+        // Simulate some async processing while holding the lock
+        let started = Instant::now();
+        calibration::simulate_work(self.work_delay).await;
+        slowlog::check(started.elapsed(), || format!("AsyncBank deposit {} to {}", amount, account));
+        
+        if let Some(balance) = accounts.get_mut(account) {
+            *balance += amount;
+            Ok(*balance)
+        } else {
+            Err("Account not found")
+        }
+    }
+}
+
+// Example 2b: Sharded Async Mutex - One lock per account
+struct ShardedBank {
+    accounts: HashMap<String, tokio::sync::Mutex<i32>>,
+    work_delay: Duration,
+}
+
+impl ShardedBank {
+    fn from_accounts(accounts: HashMap<String, i32>, work_delay: Duration) -> Self {
+        ShardedBank {
+            accounts: accounts
+                .into_iter()
+                .map(|(account, balance)| (account, tokio::sync::Mutex::new(balance)))
+                .collect(),
+            work_delay,
+        }
+    }
+
+    async fn process_deposit(&self, account: &str, amount: i32) -> Result<i32, &'static str> {
+        let lock = self.accounts.get(account).ok_or("Account not found")?;
+
+        // Only deposits to the same account wait for each other
+        let waiting = Instant::now();
+        let (mut balance, conflicted) = match lock.try_lock() {
+            Ok(balance) => (balance, false),
+            Err(_) => (lock.lock().await, true),
+        };
+        contention::record(account, waiting.elapsed(), conflicted);
+
+        let started = Instant::now();
+        calibration::simulate_work(self.work_delay).await;
+        slowlog::check(started.elapsed(), || format!("ShardedBank deposit {} to {}", amount, account));
+        *balance += amount;
+        Ok(*balance)
+    }
+}
+
+// Example 3: Copy-on-write snapshots - Readers never block
+struct SnapshotBank {
+    accounts: ArcSwap<HashMap<String, i32>>,
+    // Number of map entries cloned by writes, i.e. the write amplification
+    copied_entries: AtomicUsize,
+}
+
+impl SnapshotBank {
+    fn new() -> Self {
+        let mut accounts = HashMap::new();
+        accounts.insert("Alice".to_string(), 100);
+        SnapshotBank::from_accounts(accounts)
+    }
+
+    fn from_accounts(accounts: HashMap<String, i32>) -> Self {
+        SnapshotBank {
+            accounts: ArcSwap::from_pointee(accounts),
+            copied_entries: AtomicUsize::new(0),
+        }
+    }
+
+    fn balance(&self, account: &str) -> Option<i32> {
+        // Loading a snapshot never waits for writers
+        self.accounts.load().get(account).copied()
+    }
+
+    fn deposit(&self, account: &str, amount: i32) -> Result<i32, &'static str> {
+        let mut result = Err("Account not found");
+        // Clone the whole map, apply the change, then swap it in atomically.
+        // rcu retries the closure if another writer swapped in between.
+        self.accounts.rcu(|current| {
+            self.copied_entries.fetch_add(current.len(), Ordering::Relaxed);
+            let mut next = HashMap::clone(current);
+            result = match next.get_mut(account) {
+                Some(balance) => {
+                    *balance += amount;
+                    Ok(*balance)
+                },
+                None => Err("Account not found")
+            };
+            next
+        });
+        result
+    }
+}
+
+// Helper to build a bank with many accounts for the write cost comparison
+fn sample_accounts(count: usize) -> HashMap<String, i32> {
+    (0..count).map(|i| (format!("Account{}", i), 100)).collect()
+}
+
+async fn run_basic_mutex_example(cfg: Config) -> Outcome {
+    println!("\n=== Basic Mutex Example (Blocking Operations) ===");
+    let bank = Arc::new(BasicBank::new());
+    let start = Instant::now();
+    let mut tasks = TaskGroup::new();
+
+    // Launch concurrent operations
+    for i in 0..cfg.clients {
+        let bank = Arc::clone(&bank);
+        tasks.spawn(async move {
+            log_operation(start, "Task", &format!("{} starting", i)).await;
+            let started = Instant::now();
+            
+            match bank.deposit("Alice", 50) {
+                Ok(balance) => {
+                    log_operation(start, "Task", 
+                        &format!("{} completed - Balance: {}", i, balance)).await;
+                },
+                Err(e) => log_operation(start, "Task", 
+                    &format!("{} failed - {}", i, e)).await,
+            }
+            started.elapsed()
+        });
+    }
+
+    let mut outcome = Outcome::default();
+    for elapsed in tasks.join().await {
+        outcome.record(elapsed);
+    }
+    let balance = bank.accounts.lock().unwrap()["Alice"];
+    outcome.with_balance("Alice", balance)
+}
+
+async fn run_async_mutex_example(cfg: Config) -> Outcome {
+    println!("\n=== Async Mutex Example (Non-blocking Operations) ===");
+    let bank = Arc::new(AsyncBank::new(cfg.work_delay));
+    let start = Instant::now();
+    let mut tasks = TaskGroup::new();
+
+    // Launch concurrent operations
+    for i in 0..cfg.clients {
+        let bank = Arc::clone(&bank);
+        tasks.spawn(async move {
+            log_operation(start, "Task", &format!("{} starting", i)).await;
+            let started = Instant::now();
+            
+            match bank.process_deposit("Alice", 50).await {
+                Ok(balance) => {
+                    log_operation(start, "Task", 
+                        &format!("{} completed - Balance: {}", i, balance)).await;
+                },
+                Err(e) => log_operation(start, "Task", 
+                    &format!("{} failed - {}", i, e)).await,
+            }
+            started.elapsed()
+        });
+    }
+
+    let mut outcome = Outcome::default();
+    for elapsed in tasks.join().await {
+        outcome.record(elapsed);
+    }
+    let balance = bank.accounts.lock().await["Alice"];
+    outcome.with_balance("Alice", balance)
+}
+
+async fn run_message_passing_example(cfg: Config) -> Outcome {
+    println!("\n=== Message Passing Example (Independent Manager) ===");
+    let (tx, rx) = channel_with_metrics("bank", 32);
+    let start = Instant::now();
+
+    // Spawn the bank manager task
+    let mut accounts = HashMap::new();
+    accounts.insert("Alice".to_string(), 100);
+    let manager = tokio::spawn(run_bank_manager(rx, accounts, cfg.work_delay));
+
+    let work_delay = cfg.work_delay;
+
+    // Launch concurrent client requests
+    let mut clients = TaskGroup::new();
+    for i in 0..cfg.clients {
+        let tx = tx.clone();
+        clients.spawn(async move {
+            log_operation(start, "Client", &format!("{} sending request", i)).await;
+            let started = Instant::now();
+            
+            let (resp_tx, resp_rx) = oneshot::channel();
+            tx.send(BankMessage::Deposit {
+                account: "Alice".to_string(),
+                amount: 50,
+                respond_to: resp_tx,
+            }).await.unwrap();
+
+            match resp_rx.await.unwrap() {
+                Ok(balance) => {
+                    log_operation(start, "Client", 
+                        &format!("{} got response - Balance: {}", i, balance)).await;
+                },
+                Err(e) => log_operation(start, "Client", 
+                    &format!("{} got error - {}", i, e)).await,
+            }
+            // Time spent queued behind the other clients' requests
+            let waited = started.elapsed().saturating_sub(work_delay);
+            contention::record("Alice", waited, waited >= work_delay);
+            started.elapsed()
+        });
+    }
+
+    // Wait for all clients and cleanup
+    let mut outcome = Outcome::default();
+    for elapsed in clients.join().await {
+        outcome.record(elapsed);
+    }
+    let (resp_tx, resp_rx) = oneshot::channel();
+    tx.send(BankMessage::Balance { account: "Alice".to_string(), respond_to: resp_tx }).await.unwrap();
+    let balance = resp_rx.await.unwrap().unwrap();
+    drop(tx);
+    manager.await.unwrap();
+    outcome.with_balance("Alice", balance)
+}
+
+async fn run_fan_out_example(_cfg: Config) {
+    println!("\n=== Fan-out/Fan-in Example (Total Balance Across Shards) ===");
+    let start = Instant::now();
+    // The whole query's deadline caps each shard's own budget
+    let query_deadline = Deadline::after("total query", Duration::from_millis(300));
+
+    // Each shard is an independent manager answering at its own pace, with
+    // as long to answer as it usually needs
+    let shards = [
+        ("us-east", vec![("Alice", 100), ("Bob", 50)], 100, 150),
+        ("eu-west", vec![("Carol", 75)], 200, 150),
+        ("ap-south", vec![("Dave", 120), ("Erin", 30)], 500, 600),
+    ];
+
+    let mut managers = TaskGroup::new();
+    let mut senders = vec![];
+    for (region, accounts, delay_ms, budget_ms) in shards {
+        let (tx, rx) = channel_with_metrics("shard", 32);
+        let accounts = accounts
+            .into_iter()
+            .map(|(name, balance)| (name.to_string(), balance))
+            .collect();
+        managers.spawn(run_bank_manager(rx, accounts, Duration::from_millis(delay_ms)));
+        senders.push((region, tx, Duration::from_millis(budget_ms)));
+    }
+
+    // Fan out: one branch per shard, each bounded by its budget or the
+    // query's deadline, whichever comes first
+    let mut branches = JoinSet::new();
+    for (region, tx, budget) in &senders {
+        let (region, budget) = (*region, *budget);
+        let tx = tx.clone();
+        branches.spawn(query_deadline.enter(async move {
+            log_operation(start, "Query", &format!("{} asked for total", region)).await;
+
+            let (resp_tx, resp_rx) = oneshot::channel();
+            let query = async move {
+                tx.send(BankMessage::TotalBalance { respond_to: resp_tx }).await.ok()?;
+                resp_rx.await.ok()
+            };
+            (region, deadline::within("shard query", budget, query).await)
+        }));
+    }
+
+    // Fan in: aggregate whatever arrived in time and remember what didn't
+    let mut total = 0;
+    let mut missing = vec![];
+    while let Some(branch) = branches.join_next().await {
+        let (region, outcome) = branch.unwrap();
+        match outcome {
+            Ok(Some(balance)) => {
+                total += balance;
+                log_operation(start, "Query",
+                    &format!("{} answered - Balance: {}", region, balance)).await;
+            },
+            Ok(None) => {
+                missing.push(region);
+                log_operation(start, "Query", &format!("{} unavailable", region)).await;
+            },
+            Err(exceeded) => {
+                missing.push(region);
+                log_operation(start, "Query", &format!("{}: {}", region, exceeded)).await;
+            }
+        }
+    }
+
+    if missing.is_empty() {
+        println!("Total balance: {} (all shards answered)", total);
+    } else {
+        println!("Partial total balance: {} (timed out: {})", total, missing.join(", "));
+    }
+
+    // Cleanup: closing the channels lets every manager finish
+    drop(senders);
+    managers.join().await;
+}
+
+async fn run_snapshot_example(cfg: Config) {
+    println!("\n=== Copy-on-Write Snapshot Example (Non-blocking Reads) ===");
+    let bank = Arc::new(SnapshotBank::new());
+    let start = Instant::now();
+    let mut tasks = TaskGroup::new();
+
+    // Launch writers and readers concurrently
+    for i in 0..cfg.clients {
+        let writer_bank = Arc::clone(&bank);
+        tasks.spawn(async move {
+            log_operation(start, "Writer", &format!("{} starting", i)).await;
+
+            match writer_bank.deposit("Alice", 50) {
+                Ok(balance) => log_operation(start, "Writer",
+                    &format!("{} completed - Balance: {}", i, balance)).await,
+                Err(e) => log_operation(start, "Writer",
+                    &format!("{} failed - {}", i, e)).await,
+            }
+        });
+
+        let reader_bank = Arc::clone(&bank);
+        tasks.spawn(async move {
+            // Readers see whichever snapshot was current when they loaded it
+            let balance = reader_bank.balance("Alice").unwrap_or_default();
+            log_operation(start, "Reader", &format!("{} saw Balance: {}", i, balance)).await;
+        });
+    }
+
+    tasks.join().await;
+
+    // The price of lock-free reads: every write copies the whole map
+    println!("\nWrite cost for 1000 deposits:");
+    for size in [10, 1_000, 10_000] {
+        let mutex_bank = BasicBank::from_accounts(sample_accounts(size));
+        let started = Instant::now();
+        for i in 0..1000 {
+            mutex_bank.deposit(&format!("Account{}", i % size), 1).unwrap();
+        }
+        let mutex_elapsed = started.elapsed();
+
+        let snapshot_bank = SnapshotBank::from_accounts(sample_accounts(size));
+        let started = Instant::now();
+        for i in 0..1000 {
+            snapshot_bank.deposit(&format!("Account{}", i % size), 1).unwrap();
+        }
+        let snapshot_elapsed = started.elapsed();
+
+        println!(
+            "  {:>6} accounts - mutex: {:>10?}, snapshot: {:>10?} ({} entries copied)",
+            size,
+            mutex_elapsed,
+            snapshot_elapsed,
+            snapshot_bank.copied_entries.load(Ordering::Relaxed)
+        );
+    }
+}
+
+async fn run_long_report_example(_cfg: Config) {
+    println!("\n=== Long-running Job Example (Progress and Cancellation) ===");
+    let (tx, rx) = channel_with_metrics("bank", 32);
+    let start = Instant::now();
+
+    let mut accounts = HashMap::new();
+    accounts.insert("Alice".to_string(), 100);
+    accounts.insert("Bob".to_string(), 50);
+    let manager = tokio::spawn(run_bank_manager(rx, accounts, Duration::from_millis(10)));
+
+    // Client 0 waits for its statement, client 1 gives up halfway through and
+    // client 2's statement is cancelled by ID from elsewhere
+    let cancelled_request = ids::next_id();
+    let requests = [
+        (0, "Alice", None, ids::next_id()),
+        (1, "Bob", Some(50), ids::next_id()),
+        (2, "Alice", None, cancelled_request),
+    ];
+    let mut tasks = TaskGroup::new();
+    for (i, account, cancel_at, request_id) in requests {
+        let tx = tx.clone();
+        tasks.spawn(async move {
+            log_operation(start, "Client", &format!("{} requesting statement for {}", i, account)).await;
+
+            let (progress_tx, mut progress_rx) = channel_with_metrics("progress", 16);
+            let (resp_tx, resp_rx) = oneshot::channel();
+            tx.send(BankMessage::Statement {
+                request_id,
+                account: account.to_string(),
+                entries: 1_000_000,
+                progress: progress_tx,
+                respond_to: resp_tx,
+            }).await.unwrap();
+
+            while let Some(Progress { pct, eta }) = progress_rx.recv().await {
+                log_operation(start, "Client",
+                    &format!("{} progress {:>3}% - eta {:?}", i, pct, eta)).await;
+
+                if cancel_at.is_some_and(|limit| pct >= limit) {
+                    log_operation(start, "Client", &format!("{} cancelling", i)).await;
+                    return;
+                }
+            }
+
+            match resp_rx.await {
+                Ok(Ok(statement)) => log_operation(start, "Client",
+                    &format!("{} got statement for {} - {} entries, closing balance {}",
+                        i, statement.account, statement.entries, statement.closing_balance)).await,
+                Ok(Err(e)) => log_operation(start, "Client", &format!("{} got error - {}", i, e)).await,
+                Err(_) => log_operation(start, "Client", &format!("{} lost the manager", i)).await,
+            }
+        });
+    }
+
+    // The manager stays responsive while the statements are generated
+    sleep(Duration::from_millis(500)).await;
+    let (resp_tx, resp_rx) = oneshot::channel();
+    tx.send(BankMessage::Deposit {
+        account: "Alice".to_string(),
+        amount: 50,
+        respond_to: resp_tx,
+    }).await.unwrap();
+    if let Ok(Ok(balance)) = resp_rx.await {
+        log_operation(start, "Client", &format!("deposit during report - Balance: {}", balance)).await;
+    }
+
+    sleep(Duration::from_millis(500)).await;
+    let running = client::cancel(&tx, cancelled_request).await.unwrap();
+    log_operation(start, "Client", &format!("cancel request {} - still running: {}", cancelled_request, running)).await;
+
+    tasks.join().await;
+    drop(tx);
+    manager.await.unwrap();
+}
+
+async fn run_double_entry_example(cfg: Config) -> Outcome {
+    println!("\n=== Double-entry Example (Balanced Books) ===");
+    let (tx, rx) = channel_with_metrics("bank", 32);
+    let start = Instant::now();
+
+    let mut accounts = HashMap::new();
+    accounts.insert("Alice".to_string(), 100);
+    accounts.insert("Bob".to_string(), 50);
+    let manager = tokio::spawn(run_bank_manager(rx, accounts, cfg.work_delay));
+
+    // Every operation becomes a journal entry with matching debits and credits
+    let transfers = [("Alice", "Bob", 30), ("Bob", "Alice", 500), ("Bob", "Carol", 10)];
+    let mut tasks = TaskGroup::new();
+    for (i, (from, to, amount)) in transfers.into_iter().enumerate() {
+        let tx = tx.clone();
+        tasks.spawn(async move {
+            let (resp_tx, resp_rx) = oneshot::channel();
+            tx.send(BankMessage::Transfer {
+                from: from.to_string(),
+                to: to.to_string(),
+                amount,
+                respond_to: resp_tx,
+            }).await.unwrap();
+
+            match resp_rx.await.unwrap() {
+                Ok(balance) => {
+                    log_operation(start, "Client",
+                        &format!("{} moved {} from {} to {} - {} now has {}", i, amount, from, to, from, balance)).await;
+                    true
+                },
+                Err(e) => {
+                    log_operation(start, "Client",
+                        &format!("{} transfer {} -> {} rejected - {}", i, from, to, e)).await;
+                    false
+                }
+            }
+        });
+    }
+
+    let (resp_tx, resp_rx) = oneshot::channel();
+    tx.send(BankMessage::Deposit {
+        account: "Bob".to_string(),
+        amount: 25,
+        respond_to: resp_tx,
+    }).await.unwrap();
+    if let Ok(Ok(balance)) = resp_rx.await {
+        log_operation(start, "Client", &format!("deposited 25 to Bob - Balance: {}", balance)).await;
+    }
+
+    // Only successful transfers count as operations
+    let mut outcome = Outcome::default();
+    for transferred in tasks.join().await {
+        if transferred {
+            outcome.record(Duration::ZERO);
+        }
+    }
+    for account in ["Alice", "Bob"] {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        tx.send(BankMessage::Balance { account: account.to_string(), respond_to: resp_tx }).await.unwrap();
+        outcome = outcome.with_balance(account, resp_rx.await.unwrap().unwrap());
+    }
+
+    // The manager checks the books once the last sender is dropped
+    drop(tx);
+    manager.await.unwrap();
+    outcome
+}
+
+// Simulated network hop: every message arrives `latency` after it was sent,
+// but messages travel concurrently like packets on a link
+fn spawn_link<T: Send + 'static>(latency: Duration, downstream: MeteredSender<T>) -> MeteredSender<T> {
+    let (tx, mut rx) = channel_with_metrics("link", 1024);
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            let downstream = downstream.clone();
+            tokio::spawn(async move {
+                sleep(latency).await;
+                let _ = downstream.send(msg).await;
+            });
+        }
+    });
+    tx
+}
+
+// Keep up to `window` deposits in flight, matching replies to requests by ID.
+// A window of 1 is the strictly sequential send-then-wait client.
+async fn pipelined_deposits(
+    tx: MeteredSender<BankMessage>,
+    requests: u64,
+    window: usize,
+    latency: Duration,
+) -> (Duration, Duration, usize) {
+    let (reply_tx, mut reply_rx) = channel_with_metrics::<TaggedReply>("replies", window);
+    let reply_tx = spawn_link(latency, reply_tx);
+
+    let started = Instant::now();
+    let mut in_flight = HashMap::new();
+    let mut total_latency = Duration::ZERO;
+    let mut failures = 0;
+    let mut sent = 0;
+    let mut completed = 0;
+
+    while completed < requests {
+        // Fill the window before waiting for anything
+        while in_flight.len() < window && sent < requests {
+            let id = ids::next_id();
+            tx.send(BankMessage::TaggedDeposit {
+                id,
+                account: "Alice".to_string(),
+                amount: 1,
+                respond_to: reply_tx.clone(),
+            }).await.unwrap();
+            in_flight.insert(id, Instant::now());
+            sent += 1;
+        }
+
+        // Replies may arrive in any order, the correlation ID says whose it is
+        let reply = reply_rx.recv().await.unwrap();
+        match in_flight.remove(&reply.id) {
+            Some(sent_at) => total_latency += sent_at.elapsed(),
+            None => println!("Reply for unknown request {}", reply.id),
+        }
+        if reply.result.is_err() {
+            failures += 1;
+        }
+        completed += 1;
+    }
+
+    (started.elapsed(), total_latency / requests as u32, failures)
+}
+
+async fn run_pipelining_example(_cfg: Config) {
+    println!("\n=== Pipelining Example (Bounded In-flight Window) ===");
+    let requests = 100;
+    let latency = Duration::from_millis(20);
+
+    for window in [1, 4, 16] {
+        let (manager_tx, rx) = channel_with_metrics("bank", 32);
+        let mut accounts = HashMap::new();
+        accounts.insert("Alice".to_string(), 100);
+        let manager = tokio::spawn(run_bank_manager(rx, accounts, Duration::from_millis(1)));
+
+        // Requests reach the manager over the simulated link
+        let tx = spawn_link(latency, manager_tx);
+        let (elapsed, avg_latency, failures) = pipelined_deposits(tx, requests, window, latency).await;
+
+        println!(
+            "window {:>2}: {} deposits in {:>10?} ({:>6.0} ops/s, avg latency {:?}, {} failed)",
+            window,
+            requests,
+            elapsed,
+            requests as f64 / elapsed.as_secs_f64(),
+            avg_latency,
+            failures
+        );
+        manager.await.unwrap();
+    }
+}
+
+async fn run_ledger_query_example(cfg: Config) {
+    println!("\n=== Ledger Query Example (Aggregations Over the Journal) ===");
+    let (tx, rx) = channel_with_metrics("bank", 32);
+
+    let mut accounts = HashMap::new();
+    accounts.insert("Alice".to_string(), 100);
+    accounts.insert("Bob".to_string(), 50);
+    let manager = tokio::spawn(run_bank_manager(rx, accounts, cfg.work_delay / 10));
+
+    for (account, amount) in [("Alice", 50), ("Bob", 20), ("Alice", 30)] {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        tx.send(BankMessage::Deposit {
+            account: account.to_string(),
+            amount,
+            respond_to: resp_tx,
+        }).await.unwrap();
+        resp_rx.await.unwrap().unwrap();
+    }
+    let (resp_tx, resp_rx) = oneshot::channel();
+    tx.send(BankMessage::Transfer {
+        from: "Alice".to_string(),
+        to: "Bob".to_string(),
+        amount: 40,
+        respond_to: resp_tx,
+    }).await.unwrap();
+    resp_rx.await.unwrap().unwrap();
+
+    let queries = [
+        ("Sum of deposits per account in the last hour",
+            Query::journal().kind(EntryKind::Deposit).within(Duration::from_secs(3600))),
+        ("Number of transfers per account",
+            Query::journal().kind(EntryKind::Transfer).count()),
+        ("Net movement on Alice",
+            Query::journal().account("Alice")),
+    ];
+
+    for (title, query) in queries {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        tx.send(BankMessage::Query { query, respond_to: resp_tx }).await.unwrap();
+
+        println!("{}:", title);
+        for row in resp_rx.await.unwrap() {
+            println!("  {:<8} {:>6}", row.account, row.value);
+        }
+    }
+
+    drop(tx);
+    manager.await.unwrap();
+}
+
+// Skewed workload: four out of five deposits go to Alice
+async fn run_skewed_workload<F, Fut>(clients: usize, hot_share: u32, deposit: F)
+where
+    F: Fn(&'static str) -> Fut,
+    Fut: std::future::Future<Output = Result<i32, &'static str>> + Send + 'static,
+{
+    let others = ["Bob", "Carol", "Dave", "Erin"];
+    let mut tasks = TaskGroup::new();
+    for i in 0..clients {
+        // Stepping by 37 spreads the cold deposits evenly through the run
+        let account = if (i * 37 % 100) as u32 >= hot_share { others[i % others.len()] } else { "Alice" };
+        tasks.spawn(deposit(account));
+    }
+    for result in tasks.join().await {
+        result.unwrap();
+    }
+}
+
+async fn run_contention_example(cfg: Config) {
+    println!("\n=== Contention Example (Skewed Workload, Single Lock vs Per-account Locks) ===");
+    let clients = cfg.clients * 10;
+    let hot_share = cfg.hot_share;
+    let work_delay = cfg.work_delay / 10;
+    let accounts: HashMap<String, i32> = ["Alice", "Bob", "Carol", "Dave", "Erin"]
+        .into_iter()
+        .map(|account| (account.to_string(), 100))
+        .collect();
+
+    let bank = Arc::new(AsyncBank::from_accounts(accounts.clone(), work_delay));
+    let started = Instant::now();
+    run_skewed_workload(clients, hot_share, |account| {
+        let bank = Arc::clone(&bank);
+        async move { bank.process_deposit(account, 10).await }
+    }).await;
+    println!("Single lock: {} deposits in {:?}", clients, started.elapsed());
+    contention::print_report("single lock");
+
+    let bank = Arc::new(ShardedBank::from_accounts(accounts, work_delay));
+    let started = Instant::now();
+    run_skewed_workload(clients, hot_share, |account| {
+        let bank = Arc::clone(&bank);
+        async move { bank.process_deposit(account, 10).await }
+    }).await;
+    println!("Per-account locks: {} deposits in {:?}", clients, started.elapsed());
+    contention::print_report("per-account locks");
+}
+
+// The skewed workload against each design, timed
+async fn time_designs(clients: usize, hot_share: u32, work_delay: Duration, accounts: &HashMap<String, i32>) {
+    let bank = Arc::new(AsyncBank::from_accounts(accounts.clone(), work_delay));
+    let started = Instant::now();
+    run_skewed_workload(clients, hot_share, |account| {
+        let bank = Arc::clone(&bank);
+        async move { bank.process_deposit(account, 10).await }
+    }).await;
+    println!("  single lock        {:?}", started.elapsed());
+
+    let (tx, rx) = channel_with_metrics("chaos", 32);
+    let manager = tokio::spawn(run_bank_manager(rx, accounts.clone(), work_delay));
+    let started = Instant::now();
+    run_skewed_workload(clients, hot_share, |account| {
+        let tx = tx.clone();
+        async move { client::deposit(&tx, account, 10).await.map_err(|_| "Deposit rejected") }
+    }).await;
+    println!("  actor              {:?}", started.elapsed());
+    drop(tx);
+    manager.await.unwrap();
+
+    let bank = Arc::new(ShardedBank::from_accounts(accounts.clone(), work_delay));
+    let started = Instant::now();
+    run_skewed_workload(clients, hot_share, |account| {
+        let bank = Arc::clone(&bank);
+        async move { bank.process_deposit(account, 10).await }
+    }).await;
+    println!("  per-account locks  {:?}", started.elapsed());
+}
+
+async fn run_chaos_example(cfg: Config) {
+    println!("\n=== Chaos Example (Latency Injected per Subsystem) ===");
+    let clients = cfg.clients * 4;
+    let work_delay = cfg.work_delay / 10;
+    let accounts: HashMap<String, i32> = ["Alice", "Bob", "Carol", "Dave", "Erin"]
+        .into_iter()
+        .map(|account| (account.to_string(), 100))
+        .collect();
+
+    println!("Baseline:");
+    time_designs(clients, cfg.hot_share, work_delay, &accounts).await;
+
+    // Anything that serializes on the store pays the extra latency once per
+    // deposit; per-account locks only per deposit to the same account
+    chaos::slow(Subsystem::Store, work_delay * 4, work_delay * 2);
+    println!("Slow store (+{:?}, up to {:?} jitter):", work_delay * 4, work_delay * 2);
+    // Log anything taking five times the usual work
+    slowlog::set_threshold(work_delay * 5);
+    time_designs(clients, cfg.hot_share, work_delay, &accounts).await;
+    for call in slowlog::slowest(work_delay * 5).into_iter().take(3) {
+        println!("  slow: {:>10?} {} at {}", call.duration, call.operation, call.site);
+    }
+    slowlog::set_threshold(cfg.slow_threshold);
+    chaos::reset();
+
+    // Only the actor talks over a channel, and its clients wait concurrently
+    chaos::slow(Subsystem::Channel, work_delay * 4, Duration::ZERO);
+    println!("Slow channel sends (+{:?}):", work_delay * 4);
+    time_designs(clients, cfg.hot_share, work_delay, &accounts).await;
+    chaos::reset();
+
+    // Channels that look full refuse non-blocking sends, counted as drops in
+    // the channel report
+    chaos::fill_channels(true);
+    let (tx, _rx) = channel_with_metrics("chaos", 8);
+    if let Err(e) = tx.try_send(1) {
+        println!("try_send with channels full: {}", e);
+    }
+    chaos::reset();
+
+    // Forced misses send every cached read back to the manager
+    let ctx = AppContext::builder(&cfg)
+        .account("Alice", 100)
+        .manager_delay(work_delay)
+        .cached_reads(Duration::from_secs(1))
+        .build()
+        .await;
+    for forced in [false, true] {
+        chaos::force_cache_misses(forced);
+        let started = Instant::now();
+        for _ in 0..10 {
+            ctx.reader().balance("Alice", ReadConsistency::Cached).await.unwrap();
+        }
+        let label = if forced { "with forced misses" } else { "normally" };
+        println!("10 cached reads {}: {:?}", label, started.elapsed());
+    }
+    chaos::reset();
+    ctx.shutdown().await;
+}
+
+// Every client deposits 50 into Alice's 100, one at a time
+fn deposit_postconditions(cfg: &Config, max_latency: Duration) -> Vec<Postcondition> {
+    vec![
+        Postcondition::FinalBalance { account: "Alice", expected: 100 + 50 * cfg.clients as i32 },
+        Postcondition::Operations(cfg.clients as u64),
+        Postcondition::MaxLatency(max_latency),
+    ]
+}
+
+impl Implementation for ShardedBank {
+    fn name(&self) -> &'static str {
+        "sharded"
+    }
+
+    fn deposit<'a>(&'a self, account: &'a str, amount: i32) -> BoxFuture<'a, Result<i32, String>> {
+        Box::pin(async move { self.process_deposit(account, amount).await.map_err(str::to_string) })
+    }
+
+    fn balances(&self) -> BoxFuture<'_, BTreeMap<String, i32>> {
+        Box::pin(async move {
+            let mut balances = BTreeMap::new();
+            for (account, balance) in &self.accounts {
+                balances.insert(account.clone(), *balance.lock().await);
+            }
+            balances
+        })
+    }
+}
+
+// The bank manager, reached over its channel
+struct ActorBank(MeteredSender<BankMessage>);
+
+impl Implementation for ActorBank {
+    fn name(&self) -> &'static str {
+        "actor"
+    }
+
+    fn deposit<'a>(&'a self, account: &'a str, amount: i32) -> BoxFuture<'a, Result<i32, String>> {
+        Box::pin(async move { client::deposit(&self.0, account, amount).await.map_err(|e| e.to_string()) })
+    }
+
+    fn balances(&self) -> BoxFuture<'_, BTreeMap<String, i32>> {
+        Box::pin(async move { client::balances(&self.0).await.unwrap().into_iter().collect() })
+    }
+}
+
+// Broken on purpose: reads the balance, does its work without holding the
+// lock, then writes back what it read plus the deposit. Concurrent deposits
+// to one account overwrite each other.
+struct RacyBank {
+    accounts: Mutex<HashMap<String, i32>>,
+    work_delay: Duration,
+}
+
+impl Implementation for RacyBank {
+    fn name(&self) -> &'static str {
+        "racy"
+    }
+
+    fn deposit<'a>(&'a self, account: &'a str, amount: i32) -> BoxFuture<'a, Result<i32, String>> {
+        Box::pin(async move {
+            let read = self.accounts.lock().unwrap().get(account).copied();
+            let balance = read.ok_or("Account not found")? + amount;
+            calibration::simulate_work(self.work_delay).await;
+            self.accounts.lock().unwrap().insert(account.to_string(), balance);
+            Ok(balance)
+        })
+    }
+
+    fn balances(&self) -> BoxFuture<'_, BTreeMap<String, i32>> {
+        Box::pin(async move { self.accounts.lock().unwrap().clone().into_iter().collect() })
+    }
+}
+
+async fn run_differential_example(cfg: Config) {
+    println!("\n=== Differential Example (Candidate Designs vs the Actor) ===");
+    let work_delay = cfg.work_delay / 10;
+    let accounts: HashMap<String, i32> = ["Alice", "Bob", "Carol", "Dave", "Erin"]
+        .into_iter()
+        .map(|account| (account.to_string(), 100))
+        .collect();
+
+    // Mostly known accounts, with every tenth deposit to one that doesn't exist
+    let names = ["Alice", "Bob", "Alice", "Carol", "Alice", "Dave", "Erin", "Alice", "Bob", "Mallory"];
+    let script: Vec<(String, i32)> = (0..40).map(|step| (names[step % names.len()].to_string(), 5 + step as i32 % 3)).collect();
+    let clients = cfg.clients * 2;
+
+    let candidates: Vec<Arc<dyn Implementation>> = vec![
+        Arc::new(ShardedBank::from_accounts(accounts.clone(), work_delay)),
+        Arc::new(RacyBank { accounts: Mutex::new(accounts.clone()), work_delay }),
+    ];
+    for candidate in candidates {
+        let (tx, rx) = channel_with_metrics("bank", 32);
+        let manager = tokio::spawn(run_bank_manager(rx, accounts.clone(), work_delay));
+
+        println!("\n--- actor vs {} ---", candidate.name());
+        let comparison = differential::compare(Arc::new(ActorBank(tx)), candidate, script.clone(), clients).await;
+        comparison.print();
+
+        // The actor's sender went with the comparison's run
+        manager.await.unwrap();
+    }
+}
+
+async fn run_shadow_example(cfg: Config) {
+    println!("\n=== Shadow Traffic Example (Mirroring Requests to a Candidate) ===");
+    let work_delay = cfg.work_delay / 10;
+    let accounts: HashMap<String, i32> = ["Alice", "Bob", "Carol"]
+        .into_iter()
+        .map(|account| (account.to_string(), 100))
+        .collect();
+    let names = ["Alice", "Bob", "Alice", "Carol", "Mallory"];
+    let script: Vec<(String, i32)> = (0..30).map(|step| (names[step % names.len()].to_string(), 5)).collect();
+
+    let candidates: Vec<Arc<dyn Implementation>> = vec![
+        Arc::new(ShardedBank::from_accounts(accounts.clone(), work_delay)),
+        Arc::new(RacyBank { accounts: Mutex::new(accounts.clone()), work_delay }),
+    ];
+    for candidate in candidates {
+        let (tx, rx) = channel_with_metrics("bank", 32);
+        let manager = tokio::spawn(run_bank_manager(rx, accounts.clone(), work_delay));
+
+        // Callers only ever see the actor's answers
+        println!("\n--- actor shadowed by {} ---", candidate.name());
+        let shadow = Arc::new(Shadow::new(Arc::new(ActorBank(tx)), candidate, 8));
+        let served = differential::run(Arc::clone(&shadow) as Arc<dyn Implementation>, Arc::new(script.clone()), cfg.clients * 2).await;
+        let refused = served.outcomes.iter().filter(|outcome| outcome.is_err()).count();
+        println!("Served {} requests from the actor, {} refused, in {:?}", served.outcomes.len(), refused, served.elapsed);
+        shadow.finish().await.print();
+
+        drop(shadow);
+        manager.await.unwrap();
+    }
+}
+
+async fn run_mailbox_overflow_example(cfg: Config) {
+    println!("\n=== Mailbox Overflow Example (Block, Drop Newest, Drop Oldest) ===");
+    let burst = 50;
+    let work = cfg.work_delay / 20;
+
+    for policy in [OverflowPolicy::Block, OverflowPolicy::DropNewest, OverflowPolicy::DropOldest] {
+        let (tx, mut rx) = mailbox::mailbox(8, policy);
+
+        // A slow actor tracking price ticks, burst at it all at once
+        let actor = tokio::spawn(async move {
+            let mut handled = vec![];
+            while let Some(tick) = rx.recv().await {
+                calibration::simulate_work(work).await;
+                handled.push(tick);
+            }
+            (handled, rx.stats())
+        });
+        let started = Instant::now();
+        for tick in 1..=burst {
+            tx.send(tick).await.unwrap();
+        }
+        let burst_time = started.elapsed();
+        let sent = tx.stats();
+        drop(tx);
+
+        let (handled, received) = actor.await.unwrap();
+        println!(
+            "{:<10} burst sent in {:>10?}, handled {:>2}/{}, dropped {:>2}, max depth {}, last tick seen #{}",
+            format!("{:?}", policy),
+            burst_time,
+            received.delivered,
+            burst,
+            sent.dropped,
+            received.max_depth,
+            handled.last().copied().unwrap_or(0)
+        );
+    }
+    println!("Block slows the sender to the actor's pace; DropNewest keeps the oldest ticks, DropOldest the newest");
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Transport {
+    TokioMpsc,
+    // Mutex-guarded queue, blocking when full
+    Mailbox,
+    LockFreeRing,
+}
+
+const TRANSPORT_CAPACITY: usize = 64;
+const TRANSPORT_MESSAGES: usize = 20_000;
+
+// Time `producers` tasks sending `messages` copies of `payload` between them
+// through one transport to a consumer that only counts them
+async fn time_transport<T: Copy + Send + 'static>(transport: Transport, producers: usize, messages: usize, payload: T) -> Duration {
+    let per_producer = messages / producers;
+    let mut tasks = JoinSet::new();
+    let mut received = 0;
+    let started = Instant::now();
+    match transport {
+        // Metered like the app's channels, so this includes the metrics' cost
+        Transport::TokioMpsc => {
+            let (tx, mut rx) = channel_with_metrics("transport", TRANSPORT_CAPACITY);
+            for _ in 0..producers {
+                let tx = tx.clone();
+                tasks.spawn(async move {
+                    for _ in 0..per_producer {
+                        if tx.send(payload).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(tx);
+            while rx.recv().await.is_some() {
+                received += 1;
+            }
+        },
+        Transport::Mailbox => {
+            let (tx, mut rx) = mailbox::mailbox(TRANSPORT_CAPACITY, OverflowPolicy::Block);
+            for _ in 0..producers {
+                let tx = tx.clone();
+                tasks.spawn(async move {
+                    for _ in 0..per_producer {
+                        if tx.send(payload).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(tx);
+            while rx.recv().await.is_some() {
+                received += 1;
+            }
+        },
+        Transport::LockFreeRing => {
+            let (tx, mut rx) = ring::channel(TRANSPORT_CAPACITY);
+            for _ in 0..producers {
+                let tx = tx.clone();
+                tasks.spawn(async move {
+                    for _ in 0..per_producer {
+                        if tx.send(payload).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(tx);
+            while rx.recv().await.is_some() {
+                received += 1;
+            }
+        },
+    }
+    let elapsed = started.elapsed();
+    while tasks.join_next().await.is_some() {}
+    assert_eq!(received, per_producer * producers, "{:?} lost messages", transport);
+    elapsed
+}
+
+async fn print_transport_rows<const SIZE: usize>() {
+    for producers in [1, 4, 16] {
+        let mut row = format!("{:>5}B {:>9}", SIZE, producers);
+        for transport in [Transport::TokioMpsc, Transport::Mailbox, Transport::LockFreeRing] {
+            let elapsed = time_transport(transport, producers, TRANSPORT_MESSAGES, [0u8; SIZE]).await;
+            row += &format!(" {:>10.0}/s", TRANSPORT_MESSAGES as f64 / elapsed.as_secs_f64());
+        }
+        println!("{}", row);
+    }
+}
+
+async fn run_transport_bench_example(_cfg: Config) {
+    println!("\n=== Transport Benchmark (Tokio mpsc, Locked Mailbox, Lock-free Ring) ===");
+    println!("{:>6} {:>9} {:>12} {:>12} {:>12}", "size", "producers", "tokio mpsc", "mailbox", "ring");
+    print_transport_rows::<16>().await;
+    print_transport_rows::<256>().await;
+    print_transport_rows::<2048>().await;
+    println!("Messages per second, {} per run, capacity {}", TRANSPORT_MESSAGES, TRANSPORT_CAPACITY);
+}
+
+pub fn scenarios() -> Vec<Box<dyn Scenario>> {
+    vec![
+        checked_scenario("basic-mutex", "Quick deposits behind a std Mutex", run_basic_mutex_example,
+            |cfg| deposit_postconditions(cfg, Duration::from_millis(50))),
+        checked_scenario("async-mutex", "Slow deposits holding a tokio Mutex across awaits", run_async_mutex_example,
+            |cfg| deposit_postconditions(cfg, cfg.work_delay * cfg.clients as u32 + Duration::from_millis(50))),
+        checked_scenario("message-passing", "Clients talking to a bank manager task over mpsc", run_message_passing_example,
+            |cfg| deposit_postconditions(cfg, cfg.work_delay * cfg.clients as u32 + Duration::from_millis(50))),
+        scenario("fan-out", "Total balance fanned out to shard managers, per-shard budgets under one deadline", run_fan_out_example),
+        scenario("snapshot", "Copy-on-write ArcSwap snapshots with non-blocking reads", run_snapshot_example),
+        scenario("long-report", "Long-running statement job with progress and cancellation", run_long_report_example),
+        checked_scenario("double-entry", "Transfers recorded as balanced journal entries", run_double_entry_example,
+            |_| vec![
+                Postcondition::FinalBalance { account: "Alice", expected: 70 },
+                Postcondition::FinalBalance { account: "Bob", expected: 105 },
+                Postcondition::Operations(1),
+            ]),
+        scenario("contention", "Per-account contention report for a skewed workload", run_contention_example),
+        scenario("chaos", "Mutex, actor and sharded designs under injected latency", run_chaos_example),
+        scenario("pipelining", "Pipelined client with a bounded in-flight window vs sequential", run_pipelining_example),
+        scenario("ledger-query", "Aggregation queries over the double-entry journal", run_ledger_query_example),
+        scenario("mailbox-overflow", "A burst into a full mailbox under each overflow policy", run_mailbox_overflow_example),
+        scenario("differential", "One scripted workload against the actor and a candidate, diffed", run_differential_example),
+        scenario("transport-bench", "Tokio mpsc against a locked mailbox and a lock-free ring", run_transport_bench_example),
+        scenario("shadow", "Mirroring live requests to a candidate design and diffing its answers", run_shadow_example),
+    ]
+}
@@ -0,0 +1,56 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Notify;
+
+// Wakes workers sleeping until there's work or their pool closes, without
+// the ways a bare `Notify` loses wakeups:
+// - `notify` uses `notify_one`, which leaves a permit if no worker is
+//   waiting yet, instead of `notify_waiters`, which only reaches workers
+//   already asleep
+// - `close` sets a flag before waking everyone, and `wait` registers for
+//   wakeups before it checks that flag, so a worker about to sleep either
+//   sees the flag or gets woken
+pub struct WorkSignal {
+    notify: Notify,
+    closed: AtomicBool,
+}
+
+impl WorkSignal {
+    pub fn new() -> Self {
+        WorkSignal {
+            notify: Notify::new(),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    // Call after making work visible, e.g. pushing onto a shared queue
+    pub fn notify(&self) {
+        self.notify.notify_one();
+    }
+
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    // Sleep until notified or closed. Wakeups can be spurious, so callers
+    // recheck their queue and `is_closed` in a loop.
+    pub async fn wait(&self) {
+        let notified = self.notify.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+        if self.is_closed() {
+            return;
+        }
+        notified.await;
+    }
+}
+
+impl Default for WorkSignal {
+    fn default() -> Self {
+        WorkSignal::new()
+    }
+}
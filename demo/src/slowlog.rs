@@ -0,0 +1,82 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, VecDeque};
+use std::panic::Location;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio::time::Duration;
+
+// Store calls slower than this are logged, in nanoseconds. Nothing is
+// logged until a threshold is set.
+static THRESHOLD: AtomicU64 = AtomicU64::new(u64::MAX);
+
+// The newest calls are kept, so a long slow run can't grow the log forever
+const LIMIT: usize = 1000;
+
+static CALLS: Mutex<VecDeque<SlowCall>> = Mutex::new(VecDeque::new());
+
+#[derive(Debug, Clone)]
+pub struct SlowCall {
+    // The call and its parameters
+    pub operation: String,
+    pub duration: Duration,
+    pub site: &'static Location<'static>,
+}
+
+pub fn set_threshold(threshold: Duration) {
+    THRESHOLD.store(threshold.as_nanos().min(u64::MAX as u128) as u64, Ordering::Relaxed);
+}
+
+pub fn threshold() -> Duration {
+    Duration::from_nanos(THRESHOLD.load(Ordering::Relaxed))
+}
+
+// Log a store call that took `elapsed`, if that's over the threshold. The
+// description is only built for slow calls; the call site is the caller's.
+#[track_caller]
+pub fn check(elapsed: Duration, operation: impl FnOnce() -> String) {
+    if elapsed <= threshold() {
+        return;
+    }
+    let site = Location::caller();
+    let mut calls = CALLS.lock().unwrap();
+    if calls.len() == LIMIT {
+        calls.pop_front();
+    }
+    calls.push_back(SlowCall { operation: operation(), duration: elapsed, site });
+}
+
+// Admin: logged calls that took at least `min`, slowest first
+pub fn slowest(min: Duration) -> Vec<SlowCall> {
+    let mut calls: Vec<SlowCall> = CALLS.lock().unwrap().iter().filter(|call| call.duration >= min).cloned().collect();
+    calls.sort_by_key(|call| Reverse(call.duration));
+    calls
+}
+
+// Print the slow calls logged since the last report, by call site, and
+// start over
+pub fn print_report() {
+    let calls = std::mem::take(&mut *CALLS.lock().unwrap());
+    if calls.is_empty() {
+        return;
+    }
+
+    let mut sites: BTreeMap<String, Vec<SlowCall>> = BTreeMap::new();
+    for call in calls {
+        sites.entry(format!("{}:{}", call.site.file(), call.site.line())).or_default().push(call);
+    }
+
+    println!("\n=== Slow store calls (over {:?}) ===", threshold());
+    println!("{:<28} {:>6} {:>12} {:>12}  slowest", "call site", "calls", "avg", "max");
+    for (site, calls) in sites {
+        let total: Duration = calls.iter().map(|call| call.duration).sum();
+        let slowest = calls.iter().max_by_key(|call| call.duration).expect("sites have calls");
+        println!(
+            "{:<28} {:>6} {:>12?} {:>12?}  {}",
+            site,
+            calls.len(),
+            total / calls.len() as u32,
+            slowest.duration,
+            slowest.operation
+        );
+    }
+}
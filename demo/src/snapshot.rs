@@ -0,0 +1,282 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use tokio::time::{Duration, Instant};
+
+use crate::currency::Currency;
+use crate::ledger::{JournalEntry, Ledger, Side};
+
+// Where the serialization and file I/O for a snapshot happen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotMode {
+    // On the manager task: simple, but every queued request waits
+    Inline,
+    // On a blocking thread, from a copy of the state
+    Background,
+}
+
+#[derive(Debug, Clone)]
+pub struct SnapshotReport {
+    pub path: PathBuf,
+    pub entries: usize,
+    pub bytes: u64,
+    // How long the manager was busy with the snapshot
+    pub manager_time: Duration,
+    pub total_time: Duration,
+}
+
+// Point-in-time copy of the books. Taking it is the only part of a
+// background snapshot the manager waits for.
+pub struct LedgerCopy {
+    balances: BTreeMap<String, BTreeMap<Currency, i32>>,
+    entries: Vec<JournalEntry>,
+}
+
+impl LedgerCopy {
+    pub fn of(ledger: &Ledger) -> Self {
+        LedgerCopy {
+            balances: ledger
+                .accounts()
+                .into_iter()
+                .filter_map(|account| ledger.holdings(&account).map(|holdings| (account.to_string(), holdings)))
+                .collect(),
+            entries: ledger.entries().to_vec(),
+        }
+    }
+
+    // Balance records, then one per journal entry, built one at a time
+    // as they're written
+    fn records(&self) -> impl Iterator<Item = Record> + '_ {
+        let balances = self.balances.iter().flat_map(|(account, holdings)| {
+            holdings.iter().map(|(currency, balance)| Record::Balance {
+                account: account.clone(),
+                currency: currency.to_string(),
+                balance: *balance,
+            })
+        });
+        let entries = self.entries.iter().map(|entry| {
+            Record::Entry(EntryRecord {
+                seq: entry.seq,
+                id: entry.id.to_string(),
+                kind: format!("{:?}", entry.kind),
+                recorded_at: entry.recorded_at.to_rfc3339(),
+                millis: entry.stamp.millis,
+                logical: entry.stamp.logical,
+                hash: entry.hash.to_string(),
+                memo: entry.memo.clone(),
+                postings: entry
+                    .postings
+                    .iter()
+                    .map(|posting| PostingRecord {
+                        debit: posting.side == Side::Debit,
+                        account: posting.account.to_string(),
+                        amount: posting.amount,
+                        currency: posting.currency.to_string(),
+                    })
+                    .collect(),
+            })
+        });
+        balances.chain(entries)
+    }
+
+    fn records_len(&self) -> u64 {
+        (self.balances.values().map(BTreeMap::len).sum::<usize>() + self.entries.len()) as u64
+    }
+
+    pub fn entries(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+// Writes the snapshot and reports how long it took, measured from `started`
+pub fn write_snapshot(
+    copy: &LedgerCopy,
+    path: &Path,
+    format: SnapshotFormat,
+    started: Instant,
+    manager_time: Duration,
+) -> io::Result<SnapshotReport> {
+    let mut out = BufWriter::new(File::create(path)?);
+    format.codec().write(&mut copy.records(), copy.records_len(), &mut out)?;
+    out.flush()?;
+    Ok(SnapshotReport {
+        path: path.to_path_buf(),
+        entries: copy.entries(),
+        bytes: out.get_ref().metadata()?.len(),
+        manager_time,
+        total_time: started.elapsed(),
+    })
+}
+
+// Streams a snapshot's records back, one at a time
+pub fn read_snapshot(path: &Path, format: SnapshotFormat) -> io::Result<Records<'static>> {
+    format.codec().read(Box::new(BufReader::new(File::open(path)?)))
+}
+
+// One record of a snapshot. Values are plain strings and numbers, so the
+// file doesn't change when the ledger's own types do.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Record {
+    Balance { account: String, currency: String, balance: i32 },
+    Entry(EntryRecord),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EntryRecord {
+    pub seq: u64,
+    pub id: String,
+    pub kind: String,
+    pub recorded_at: String,
+    pub millis: i64,
+    pub logical: u32,
+    pub hash: String,
+    pub memo: String,
+    pub postings: Vec<PostingRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PostingRecord {
+    pub debit: bool,
+    pub account: String,
+    pub amount: i32,
+    pub currency: String,
+}
+
+pub type Records<'a> = Box<dyn Iterator<Item = io::Result<Record>> + 'a>;
+
+// How snapshot records are encoded. Both directions stream, so a snapshot
+// never has to sit in memory as a whole next to the ledger copy.
+pub trait SnapshotCodec: Send + Sync {
+    fn write(&self, records: &mut dyn Iterator<Item = Record>, count: u64, out: &mut dyn Write) -> io::Result<()>;
+    fn read<'a>(&self, input: Box<dyn Read + 'a>) -> io::Result<Records<'a>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    // One JSON object per line: large, but readable for debugging
+    Json,
+    // bincode records, zstd-compressed once they pass `compress_over` bytes
+    Binary { compress_over: usize },
+}
+
+impl SnapshotFormat {
+    // Small snapshots aren't worth a compressor's time
+    pub const BINARY: SnapshotFormat = SnapshotFormat::Binary { compress_over: 64 * 1024 };
+
+    pub fn codec(self) -> Box<dyn SnapshotCodec> {
+        match self {
+            SnapshotFormat::Json => Box::new(JsonCodec),
+            SnapshotFormat::Binary { compress_over } => Box::new(BinaryCodec { level: 3, compress_over }),
+        }
+    }
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+pub struct JsonCodec;
+
+impl SnapshotCodec for JsonCodec {
+    fn write(&self, records: &mut dyn Iterator<Item = Record>, _count: u64, out: &mut dyn Write) -> io::Result<()> {
+        for record in records {
+            serde_json::to_writer(&mut *out, &record).map_err(invalid_data)?;
+            out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    fn read<'a>(&self, input: Box<dyn Read + 'a>) -> io::Result<Records<'a>> {
+        let records = serde_json::Deserializer::from_reader(input).into_iter::<Record>();
+        Ok(Box::new(records.map(|record| record.map_err(invalid_data))))
+    }
+}
+
+// A flag byte says whether the rest is compressed. The record count goes
+// first, so a truncated file is an error rather than a shorter snapshot.
+pub struct BinaryCodec {
+    level: i32,
+    compress_over: usize,
+}
+
+const RAW: u8 = 0;
+const ZSTD: u8 = 1;
+
+impl SnapshotCodec for BinaryCodec {
+    fn write(&self, records: &mut dyn Iterator<Item = Record>, count: u64, out: &mut dyn Write) -> io::Result<()> {
+        let mut body = Spill::Buffering { out, buffer: vec![], level: self.level, limit: self.compress_over };
+        bincode::serialize_into(&mut body, &count).map_err(invalid_data)?;
+        for record in records {
+            bincode::serialize_into(&mut body, &record).map_err(invalid_data)?;
+        }
+        body.finish()
+    }
+
+    fn read<'a>(&self, mut input: Box<dyn Read + 'a>) -> io::Result<Records<'a>> {
+        let mut flag = [0];
+        input.read_exact(&mut flag)?;
+        let mut body: Box<dyn Read + 'a> = match flag[0] {
+            RAW => input,
+            ZSTD => Box::new(zstd::stream::read::Decoder::new(input)?),
+            other => return Err(invalid_data(format!("unknown snapshot flag {}", other))),
+        };
+        let count: u64 = bincode::deserialize_from(&mut body).map_err(invalid_data)?;
+        Ok(Box::new((0..count).map(move |_| bincode::deserialize_from(&mut body).map_err(invalid_data))))
+    }
+}
+
+// Holds the start of the output back until it passes `limit` bytes. Under
+// the limit it's written as is; past it, it and everything after go
+// through zstd. Either way only the held-back part is ever in memory.
+enum Spill<'a> {
+    Buffering { out: &'a mut dyn Write, buffer: Vec<u8>, level: i32, limit: usize },
+    Compressing(zstd::stream::write::Encoder<'static, &'a mut dyn Write>),
+    // Only while switching over
+    Switching,
+}
+
+impl Spill<'_> {
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Spill::Buffering { out, buffer, .. } => {
+                out.write_all(&[RAW])?;
+                out.write_all(&buffer)
+            },
+            Spill::Compressing(encoder) => encoder.finish().map(|_| ()),
+            Spill::Switching => unreachable!("switching finishes inside write"),
+        }
+    }
+}
+
+impl Write for Spill<'_> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        match self {
+            Spill::Buffering { buffer, limit, .. } if buffer.len() + data.len() <= *limit => {
+                buffer.extend_from_slice(data);
+                Ok(data.len())
+            },
+            Spill::Buffering { .. } => {
+                let Spill::Buffering { out, buffer, level, .. } = std::mem::replace(self, Spill::Switching) else {
+                    unreachable!("matched above")
+                };
+                out.write_all(&[ZSTD])?;
+                let mut encoder = zstd::stream::write::Encoder::new(out, level)?;
+                encoder.write_all(&buffer)?;
+                encoder.write_all(data)?;
+                *self = Spill::Compressing(encoder);
+                Ok(data.len())
+            },
+            Spill::Compressing(encoder) => encoder.write(data),
+            Spill::Switching => unreachable!("switching finishes inside write"),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Spill::Compressing(encoder) => encoder.flush(),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use async_stream::stream;
+use futures::{Stream, StreamExt};
+use tokio::time::{sleep, Duration};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Mutex;  // Changed to tokio::sync::Mutex instead of std::sync::Mutex
+
+use crate::bank::run_bank_manager;
+use crate::client;
+use crate::config::Config;
+use crate::metrics::channel_with_metrics;
+use crate::scenario::{scenario, Scenario};
+use crate::tasks::TaskGroup;
+
+async fn basic_spawn_example(_cfg: Config) {
+    println!("\n=== Basic Spawn Example ===");
+    
+    let handle = tokio::spawn(async {
+        for i in 0..3 {
+            println!("Task 1: Number {}", i);
+            sleep(Duration::from_millis(100)).await;
+        }
+        "Task 1 Complete"
+    });
+    
+    for i in 0..3 {
+        println!("Main task: Number {}", i);
+        sleep(Duration::from_millis(100)).await;
+    }
+    
+    let result = handle.await.unwrap();
+    println!("Spawned task result: {}", result);
+}
+
+async fn multiple_tasks_example(_cfg: Config) {
+    println!("\n=== Multiple Tasks Example ===");
+    
+    let mut tasks = TaskGroup::new();
+    
+    for i in 0..3 {
+        tasks.spawn(async move {
+            println!("Task {} starting", i);
+            sleep(Duration::from_millis(100 * (i + 1) as u64)).await;
+            println!("Task {} completed", i);
+            i
+        });
+    }
+    
+    for result in tasks.join().await {
+        println!("Task returned: {}", result);
+    }
+}
+
+async fn shared_state_example(_cfg: Config) {
+    println!("\n=== Shared State Example ===");
+    
+    // Create shared counter using tokio::sync::Mutex instead of std::sync::Mutex
+    let counter = Arc::new(Mutex::new(0));
+    let mut tasks = TaskGroup::new();
+    
+    for i in 0..5 {
+        let counter = Arc::clone(&counter);
+        tasks.spawn(async move {
+            // Lock the mutex
+            let mut lock = counter.lock().await;  // Note: .await here instead of .unwrap()
+            *lock += 1;
+            println!("Task {} incremented counter to {}", i, *lock);
+            // Lock is automatically dropped at the end of scope
+            drop(lock);  // Optional but explicit
+            
+            // Now we can safely await since we've dropped the lock
+            sleep(Duration::from_millis(100)).await;
+        });
+    }
+    
+    tasks.join().await;
+    
+    let final_count = counter.lock().await;
+    println!("Final counter value: {}", *final_count);
+}
+
+// Deposits generated lazily: each one is produced only when the consumer
+// asks for the next item
+fn deposits(count: usize) -> impl Stream<Item = (&'static str, i32)> {
+    stream! {
+        for i in 0..count {
+            let account = ["Alice", "Bob", "Carol"][i % 3];
+            let amount = 10 * (i as i32 + 1);
+            println!("Produced: deposit {} to {}", amount, account);
+            yield (account, amount);
+        }
+    }
+}
+
+async fn stream_example(cfg: Config) {
+    println!("\n=== Stream Example (async-stream Producer, buffer_unordered Consumer) ===");
+
+    let (tx, rx) = channel_with_metrics("bank", 32);
+    let accounts = ["Alice", "Bob", "Carol"].into_iter().map(|account| (account.to_string(), 100)).collect();
+    let manager = tokio::spawn(run_bank_manager(rx, accounts, cfg.work_delay));
+
+    // Each item becomes a request future; at most 3 run at once and results
+    // come out in completion order, not production order
+    deposits(6)
+        .map(|(account, amount)| {
+            let tx = tx.clone();
+            async move { (account, amount, client::deposit(&tx, account, amount).await) }
+        })
+        .buffer_unordered(3)
+        .for_each(|(account, amount, result)| async move {
+            match result {
+                Ok(balance) => println!("Consumed: {} deposited {} - Balance: {}", account, amount, balance),
+                Err(e) => println!("Consumed: deposit to {} failed - {}", account, e),
+            }
+        })
+        .await;
+
+    drop(tx);
+    manager.await.unwrap();
+}
+
+// Stands in for a check that fails while the workers are still busy
+async fn check_quota() -> Result<(), String> {
+    sleep(Duration::from_millis(50)).await;
+    Err("quota exceeded".to_string())
+}
+
+async fn fetch_all(finished: Arc<AtomicUsize>) -> Result<Vec<u64>, String> {
+    let mut tasks = TaskGroup::new();
+    for i in 1..=3 {
+        let finished = Arc::clone(&finished);
+        tasks.spawn(async move {
+            sleep(Duration::from_millis(200 * i)).await;
+            finished.fetch_add(1, Ordering::SeqCst);
+            i
+        });
+    }
+
+    // Returning early drops the group, which aborts the workers
+    check_quota().await?;
+    Ok(tasks.join().await)
+}
+
+async fn task_group_example(_cfg: Config) {
+    println!("\n=== Task Group Example (No Task Outlives Its Scope) ===");
+
+    let finished = Arc::new(AtomicUsize::new(0));
+    match fetch_all(Arc::clone(&finished)).await {
+        Ok(results) => println!("All tasks returned: {:?}", results),
+        Err(e) => println!("Scope ended early: {}", e),
+    }
+
+    // Wait past the slowest worker: none of them got to finish
+    sleep(Duration::from_millis(700)).await;
+    println!("Workers finished after the scope ended: {}", finished.load(Ordering::SeqCst));
+}
+
+pub fn scenarios() -> Vec<Box<dyn Scenario>> {
+    vec![
+        scenario("basic-spawn", "A spawned task runs alongside the main task", basic_spawn_example),
+        scenario("multiple-tasks", "Several spawned tasks with different workloads", multiple_tasks_example),
+        scenario("shared-counter", "Tasks incrementing a counter behind a tokio Mutex", shared_state_example),
+        scenario("stream", "Stream producer consumed with a bounded number of requests in flight", stream_example),
+        scenario("task-group", "Scoped tasks aborted when their scope returns early", task_group_example),
+    ]
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot, watch, Semaphore};
+use tokio::task::JoinHandle;
+use tokio::time::{timeout, Duration};
+
+use crate::bank::{run_bank_manager, BankError, BankMessage};
+use crate::crash::{self, CrashReport};
+use crate::metrics::{channel_with_metrics, MeteredSender};
+
+// Where clients find the current manager; `None` once the supervisor stops
+type Address = Option<MeteredSender<BankMessage>>;
+
+// Crash reports are filed under this task name
+const MANAGER_TASK: &str = "bank-manager";
+
+// One entry in the restart log
+#[derive(Debug, Clone)]
+pub struct Restart {
+    pub number: u32,
+    // How the manager stopped
+    pub reason: String,
+    // Written by the panic hook, if the manager panicked
+    pub crash: Option<CrashReport>,
+}
+
+enum Command {
+    // Abort the running manager, as if it had crashed
+    Kill,
+    Stop,
+}
+
+// Keeps a bank manager running: whenever it stops without being asked to, a
+// new one is started and its address published, so `ManagerHandle`s follow
+// it there. There's no durable log to replay, so a restarted manager opens
+// with the original balances.
+pub struct Supervisor {
+    address: watch::Receiver<Address>,
+    commands: MeteredSender<Command>,
+    task: JoinHandle<Vec<Restart>>,
+}
+
+impl Supervisor {
+    pub fn start(accounts: HashMap<String, i32>, delay: Duration) -> Self {
+        let (address_tx, address) = watch::channel(None);
+        let (commands, commands_rx) = channel_with_metrics("supervisor", 4);
+        let task = tokio::spawn(supervise(accounts, delay, address_tx, commands_rx));
+        Supervisor { address, commands, task }
+    }
+
+    // `parked` bounds how many sends may wait out a restart at once, and
+    // `restart_wait` how long each waits for the new address
+    pub fn handle(&self, parked: usize, restart_wait: Duration) -> ManagerHandle {
+        ManagerHandle {
+            address: self.address.clone(),
+            parked: Arc::new(Semaphore::new(parked)),
+            restart_wait,
+        }
+    }
+
+    // Simulate the manager crashing
+    pub async fn kill_manager(&self) {
+        let _ = self.commands.send(Command::Kill).await;
+    }
+
+    // Stop restarting, withdraw the address and wait for the last manager to
+    // finish. Answers with the restart log.
+    pub async fn shutdown(self) -> Vec<Restart> {
+        let _ = self.commands.send(Command::Stop).await;
+        self.task.await.unwrap_or_default()
+    }
+}
+
+async fn supervise(
+    accounts: HashMap<String, i32>,
+    delay: Duration,
+    address: watch::Sender<Address>,
+    mut commands: mpsc::Receiver<Command>,
+) -> Vec<Restart> {
+    let mut restarts = vec![];
+    loop {
+        let (tx, rx) = channel_with_metrics("bank", 32);
+        let mut manager = tokio::spawn(crash::named(MANAGER_TASK, run_bank_manager(rx, accounts.clone(), delay)));
+        address.send_replace(Some(tx));
+
+        let outcome = loop {
+            tokio::select! {
+                outcome = &mut manager => break outcome,
+                command = commands.recv() => match command {
+                    Some(Command::Kill) => manager.abort(),
+                    // The manager finishes once the clients' in-flight
+                    // clones of the sender are gone
+                    Some(Command::Stop) | None => {
+                        address.send_replace(None);
+                        let _ = manager.await;
+                        return restarts;
+                    },
+                },
+            }
+        };
+
+        let (reason, crash) = match outcome {
+            Ok(()) => ("returned".to_string(), None),
+            Err(e) if e.is_panic() => ("panicked".to_string(), crash::take_report(MANAGER_TASK)),
+            Err(e) => (e.to_string(), None),
+        };
+        let number = restarts.len() as u32 + 1;
+        println!("Supervisor: manager stopped unexpectedly ({}), starting restart #{}", reason, number);
+        if let Some(report) = &crash {
+            let path = report.path.as_ref().map_or("not written".to_string(), |path| path.display().to_string());
+            println!("Supervisor: crash report {} - {} at {}", path, report.message, report.location);
+        }
+        restarts.push(Restart { number, reason, crash });
+    }
+}
+
+// Client-side handle that resolves the manager through the supervisor on
+// every request instead of holding one sender, so it survives restarts
+#[derive(Clone)]
+pub struct ManagerHandle {
+    address: watch::Receiver<Address>,
+    parked: Arc<Semaphore>,
+    restart_wait: Duration,
+}
+
+impl ManagerHandle {
+    pub async fn deposit(&self, account: &str, amount: i32) -> Result<i32, BankError> {
+        let account = account.to_string();
+        self.request(|respond_to| BankMessage::Deposit { account, amount, respond_to }).await?
+    }
+
+    pub async fn balance(&self, account: &str) -> Result<i32, BankError> {
+        let account = account.to_string();
+        self.request(|respond_to| BankMessage::Balance { account, respond_to }).await?
+    }
+
+    // A manager that dies while holding the request drops its reply. The
+    // request may or may not have been applied, so it isn't retried.
+    pub async fn request<R>(&self, message: impl FnOnce(oneshot::Sender<R>) -> BankMessage) -> Result<R, BankError> {
+        let (respond_to, response) = oneshot::channel();
+        self.send(message(respond_to)).await?;
+        response.await.map_err(|_| BankError::ManagerUnavailable)
+    }
+
+    // A send refused by a dead manager hands the message back, so it can
+    // wait for the restarted manager's address and go there instead
+    async fn send(&self, mut message: BankMessage) -> Result<(), BankError> {
+        let mut address = self.address.clone();
+        let mut permit = None;
+        loop {
+            let current = address.borrow_and_update().clone();
+            let Some(tx) = current else {
+                return Err(BankError::ManagerUnavailable);
+            };
+            match tx.send(message).await {
+                Ok(()) => return Ok(()),
+                Err(refused) => message = refused.0,
+            }
+            drop(tx);
+
+            // Waiting costs a permit, held until the send goes through
+            if permit.is_none() {
+                permit = Some(self.parked.clone().try_acquire_owned().map_err(|_| BankError::ManagerUnavailable)?);
+            }
+            match timeout(self.restart_wait, address.changed()).await {
+                Ok(Ok(())) => {},
+                _ => return Err(BankError::ManagerUnavailable),
+            }
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use std::future::Future;
+use std::panic;
+use tokio::task::JoinSet;
+
+// A scope for spawned tasks. Every task in the group is either awaited by
+// `join` or aborted when the group is dropped, so none outlives the code
+// that started it - not on an early return, an error or a panic either.
+//
+//     let mut group = TaskGroup::new();
+//     for i in 0..3 {
+//         group.spawn(async move { i * 2 });
+//     }
+//     let results = group.join().await; // [0, 2, 4]
+pub struct TaskGroup<T> {
+    // Tasks report their spawn index so results come back in spawn order
+    tasks: JoinSet<(usize, T)>,
+    spawned: usize,
+}
+
+impl<T: Send + 'static> TaskGroup<T> {
+    pub fn new() -> Self {
+        TaskGroup { tasks: JoinSet::new(), spawned: 0 }
+    }
+
+    pub fn spawn<F>(&mut self, task: F)
+    where
+        F: Future<Output = T> + Send + 'static,
+    {
+        let index = self.spawned;
+        self.spawned += 1;
+        self.tasks.spawn(async move { (index, task.await) });
+    }
+
+    // Wait for every task and return their results in spawn order. If a task
+    // panics the panic is re-raised here, and unwinding drops the group,
+    // aborting the tasks still running.
+    pub async fn join(mut self) -> Vec<T> {
+        let mut results = Vec::with_capacity(self.tasks.len());
+        while let Some(joined) = self.tasks.join_next().await {
+            match joined {
+                Ok(result) => results.push(result),
+                Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+                // Tasks are only aborted by dropping the group
+                Err(_) => unreachable!("task in a live group was cancelled"),
+            }
+        }
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+}
+
+impl<T: Send + 'static> Default for TaskGroup<T> {
+    fn default() -> Self {
+        TaskGroup::new()
+    }
+}